@@ -0,0 +1,253 @@
+use anchor_lang::{
+    prelude::*,
+    solana_program::{instruction::Instruction, system_program, sysvar},
+    InstructionData,
+};
+use anchor_spl::{associated_token, token_2022};
+use mushi_stake_vault::{
+    accounts, instruction,
+    ixs::init_pool::InitPoolInput,
+    state::{AccessMode, MainState, PenaltyDestination},
+    StakeInput, UnstakeInput, UpdatePoolInput, ID,
+};
+
+use crate::pda;
+
+fn build(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+/// Keys `initialize` needs that cannot be derived.
+pub struct InitializeAccounts {
+    pub admin: Pubkey,
+    pub mushi_token_mint: Pubkey,
+    /// Owner of `mushi_token_mint`; the stake mint and MUSHI vaults use the same program.
+    pub mushi_token_program: Pubkey,
+    pub eclipse_token_mint: Pubkey,
+    /// Fresh keypair address; it must also sign the transaction.
+    pub stake_token_mint: Pubkey,
+}
+
+pub fn initialize(keys: InitializeAccounts, input: InitPoolInput) -> Instruction {
+    let main_state = pda::main_state(input.pool_id).0;
+    let token_vault_owner = pda::token_vault_owner(&main_state).0;
+    build(
+        accounts::InitializeStakePool {
+            admin: keys.admin,
+            main_state,
+            mushi_token_mint: keys.mushi_token_mint,
+            eclipse_token_mint: keys.eclipse_token_mint,
+            stake_token_mint: keys.stake_token_mint,
+            stake_token_metadata_account: pda::stake_token_metadata(&keys.stake_token_mint).0,
+            token_vault_owner,
+            mushi_token_vault: pda::associated_token_address(&token_vault_owner, &keys.mushi_token_mint, &keys.mushi_token_program),
+            eclipse_token_vault: pda::associated_token_address(&token_vault_owner, &keys.eclipse_token_mint, &token_2022::ID),
+            stake_token_vault: pda::associated_token_address(&token_vault_owner, &keys.stake_token_mint, &keys.mushi_token_program),
+            reward_token_vault: pda::reward_token_vault(&main_state).0,
+            token_program: keys.mushi_token_program,
+            token2022_program: token_2022::ID,
+            mpl_program: mpl_token_metadata::ID,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+        },
+        instruction::Initialize { input },
+    )
+}
+
+/// Where the Eclipse leg of a `stake` is drawn from; must agree with the
+/// pool's `EclipseDepositMode`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EclipseSource {
+    /// MUSHI-only stake in `EclipseDepositMode::Optional`.
+    None,
+    /// The user's Token-2022 Eclipse ATA.
+    User,
+    /// The given Eclipse token account of the `mushi_program` vault.
+    MushiProgramVault(Pubkey),
+}
+
+/// A decoded pool, from which the accounts of user and admin instructions are derived.
+///
+/// In `AccessMode::ProgramGated` the built instructions must be invoked by
+/// `MainState::mushi_program`, which signs as its vault PDA.
+#[derive(Clone)]
+pub struct Pool {
+    pub address: Pubkey,
+    pub state: MainState,
+    /// Owner of the MUSHI mint, either SPL Token or Token-2022.
+    pub mushi_token_program: Pubkey,
+}
+
+impl Pool {
+    pub fn new(state: MainState, mushi_token_program: Pubkey) -> Self {
+        Self {
+            address: pda::main_state(state.pool_id).0,
+            state,
+            mushi_token_program,
+        }
+    }
+
+    pub fn token_vault_owner(&self) -> Pubkey {
+        pda::token_vault_owner(&self.address).0
+    }
+
+    pub fn mushi_token_vault(&self) -> Pubkey {
+        pda::associated_token_address(&self.token_vault_owner(), &self.state.mushi_token_mint, &self.mushi_token_program)
+    }
+
+    pub fn eclipse_token_vault(&self) -> Pubkey {
+        pda::associated_token_address(&self.token_vault_owner(), &self.state.eclipse_token_mint, &token_2022::ID)
+    }
+
+    pub fn mushi_program_token_vault_owner(&self) -> Pubkey {
+        pda::mushi_program_token_vault_owner(&self.state.mushi_program).0
+    }
+
+    pub fn user_mushi_token_ata(&self, user: &Pubkey) -> Pubkey {
+        pda::associated_token_address(user, &self.state.mushi_token_mint, &self.mushi_token_program)
+    }
+
+    pub fn user_eclipse_token_ata(&self, user: &Pubkey) -> Pubkey {
+        pda::associated_token_address(user, &self.state.eclipse_token_mint, &token_2022::ID)
+    }
+
+    pub fn user_stake_token_ata(&self, user: &Pubkey) -> Pubkey {
+        pda::associated_token_address(user, &self.state.stake_token_mint, &self.mushi_token_program)
+    }
+
+    fn allowlist_entry(&self, user: &Pubkey) -> Option<Pubkey> {
+        (self.state.access_mode == AccessMode::Allowlist).then(|| pda::allowlist_entry(&self.address, user).0)
+    }
+
+    pub fn stake(&self, user: &Pubkey, eclipse_source: EclipseSource, input: StakeInput) -> Instruction {
+        let (user_eclipse_token_ata, eclipse_token_mushi_program_vault) = match eclipse_source {
+            EclipseSource::None => (None, None),
+            EclipseSource::User => (Some(self.user_eclipse_token_ata(user)), None),
+            EclipseSource::MushiProgramVault(vault) => (None, Some(vault)),
+        };
+        build(
+            accounts::Stake {
+                user: *user,
+                main_state: self.address,
+                allowlist_entry: self.allowlist_entry(user),
+                stake_entry: pda::stake_entry(&self.address, user).0,
+                mushi_program_token_vault_owner: self.mushi_program_token_vault_owner(),
+                user_mushi_token_ata: self.user_mushi_token_ata(user),
+                user_stake_token_ata: self.user_stake_token_ata(user),
+                mushi_token_vault: self.mushi_token_vault(),
+                mushi_token_mint: self.state.mushi_token_mint,
+                user_eclipse_token_ata,
+                eclipse_token_mushi_program_vault,
+                eclipse_token_vault: self.eclipse_token_vault(),
+                eclipse_token_mint: self.state.eclipse_token_mint,
+                stake_token_mint: self.state.stake_token_mint,
+                token_vault_owner: self.token_vault_owner(),
+                associated_token_program: associated_token::ID,
+                token_program: self.mushi_token_program,
+                token2022_program: token_2022::ID,
+                system_program: system_program::ID,
+            },
+            instruction::Stake { input },
+        )
+    }
+
+    /// Eclipse is returned to `eclipse_token_source`, which must be the
+    /// position's `StakeEntry::eclipse_token_source`; `None` only when no
+    /// Eclipse comes back.
+    pub fn unstake(&self, user: &Pubkey, eclipse_token_source: Option<Pubkey>, input: UnstakeInput) -> Instruction {
+        let treasury_mushi_token_ata = (self.state.penalty_destination == PenaltyDestination::Treasury)
+            .then(|| pda::associated_token_address(&self.state.treasury, &self.state.mushi_token_mint, &self.mushi_token_program));
+        build(
+            accounts::Unstake {
+                user: *user,
+                main_state: self.address,
+                stake_entry: pda::stake_entry(&self.address, user).0,
+                user_mushi_token_ata: self.user_mushi_token_ata(user),
+                user_stake_token_ata: self.user_stake_token_ata(user),
+                mushi_token_vault: self.mushi_token_vault(),
+                treasury_mushi_token_ata,
+                mushi_token_mint: self.state.mushi_token_mint,
+                eclipse_token_staking_program_vault: self.eclipse_token_vault(),
+                eclipse_token_source,
+                eclipse_token_mint: self.state.eclipse_token_mint,
+                stake_token_mint: self.state.stake_token_mint,
+                staking_program_token_vault_owner: self.token_vault_owner(),
+                associated_token_program: associated_token::ID,
+                token_program: self.mushi_token_program,
+                token2022_program: token_2022::ID,
+                system_program: system_program::ID,
+            },
+            instruction::Unstake { input },
+        )
+    }
+
+    /// Signed by `MainState::admin`.
+    pub fn update_pool(&self, input: UpdatePoolInput) -> Instruction {
+        build(
+            accounts::UpdatePool {
+                admin: self.state.admin,
+                main_state: self.address,
+                system_program: system_program::ID,
+            },
+            instruction::UpdatePool { input },
+        )
+    }
+
+    /// Read-only and unsigned; the quote comes back as return data, see
+    /// `state::decode_stake_result`. Fails where `stake` would for `user`, so
+    /// in `AccessMode::ProgramGated` only `mushi_program` can run it.
+    pub fn preview_stake(&self, user: &Pubkey, input: StakeInput) -> Instruction {
+        build(
+            accounts::PreviewStake {
+                user: *user,
+                main_state: self.address,
+                allowlist_entry: self.allowlist_entry(user),
+                mushi_program_token_vault_owner: self.mushi_program_token_vault_owner(),
+                mushi_token_mint: self.state.mushi_token_mint,
+                eclipse_token_mint: self.state.eclipse_token_mint,
+            },
+            instruction::PreviewStake { input },
+        )
+    }
+
+    /// Read-only and unsigned; the quote comes back as return data, see
+    /// `state::decode_unstake_preview`.
+    pub fn preview_unstake(&self, user: &Pubkey, input: UnstakeInput) -> Instruction {
+        build(
+            accounts::PreviewUnstake {
+                user: *user,
+                main_state: self.address,
+                stake_entry: pda::stake_entry(&self.address, user).0,
+                mushi_token_vault: self.mushi_token_vault(),
+                mushi_token_mint: self.state.mushi_token_mint,
+                eclipse_token_mint: self.state.eclipse_token_mint,
+                token_vault_owner: self.token_vault_owner(),
+                token_program: self.mushi_token_program,
+            },
+            instruction::PreviewUnstake { input },
+        )
+    }
+
+    /// Read-only; its result comes back as return data, see `state::decode_pool_stats`.
+    pub fn get_pool_stats(&self) -> Instruction {
+        build(accounts::GetPoolStats { main_state: self.address }, instruction::GetPoolStats {})
+    }
+
+    /// Read-only and unsigned; `user` need not have staked. The result comes
+    /// back as return data, see `state::decode_user_position`.
+    pub fn get_user_position(&self, user: &Pubkey) -> Instruction {
+        build(
+            accounts::GetUserPosition {
+                user: *user,
+                main_state: self.address,
+                stake_entry: pda::stake_entry(&self.address, user).0,
+            },
+            instruction::GetUserPosition {},
+        )
+    }
+}
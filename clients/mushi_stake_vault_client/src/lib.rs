@@ -0,0 +1,14 @@
+//! Off-chain client for the Mushi stake vault program: PDA derivation,
+//! instruction builders and account decoders that need no RPC connection.
+#![allow(clippy::result_large_err)]
+
+pub mod instructions;
+pub mod pda;
+pub mod state;
+
+pub use instructions::{initialize, EclipseSource, InitializeAccounts, Pool};
+pub use mushi_stake_vault::{
+    ixs::init_pool::InitPoolInput,
+    state::{AccessMode, EclipseDepositMode, MainState, PenaltyCurve, PenaltyDestination, StakeEntry},
+    PoolStats, StakeInput, StakeResult, UnstakeInput, UnstakePreview, UnstakeResult, UpdatePoolInput, UserPosition, ID,
+};
@@ -0,0 +1,47 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+use mushi_stake_vault::{
+    state::{MainState, ALLOWLIST_SEED, MUSHIPROGRAM_VAULT_SEED, REWARD_VAULT_SEED, STAKE_ENTRY_SEED, UNSTAKE_REQUEST_SEED, VAULT_OWNER_SEED},
+    ID,
+};
+
+pub fn main_state(pool_id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[MainState::PREFIX_SEED, pool_id.to_le_bytes().as_ref()], &ID)
+}
+
+/// Owner of the pool's MUSHI, Eclipse, stake token and reward vaults.
+pub fn token_vault_owner(main_state: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[VAULT_OWNER_SEED, main_state.as_ref()], &ID)
+}
+
+pub fn reward_token_vault(main_state: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[REWARD_VAULT_SEED, main_state.as_ref()], &ID)
+}
+
+pub fn stake_entry(main_state: &Pubkey, user: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[STAKE_ENTRY_SEED, main_state.as_ref(), user.as_ref()], &ID)
+}
+
+pub fn unstake_request(main_state: &Pubkey, user: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[UNSTAKE_REQUEST_SEED, main_state.as_ref(), user.as_ref()], &ID)
+}
+
+pub fn allowlist_entry(main_state: &Pubkey, user: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[ALLOWLIST_SEED, main_state.as_ref(), user.as_ref()], &ID)
+}
+
+/// Vault PDA `mushi_program` signs its CPIs with.
+pub fn mushi_program_token_vault_owner(mushi_program: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[MUSHIPROGRAM_VAULT_SEED], mushi_program)
+}
+
+/// Metaplex metadata account of the stake token mint.
+pub fn stake_token_metadata(stake_token_mint: &Pubkey) -> (Pubkey, u8) {
+    mpl_token_metadata::accounts::Metadata::find_pda(stake_token_mint)
+}
+
+/// Associated token account of `owner` for `mint` under `token_program`,
+/// which is either SPL Token or Token-2022.
+pub fn associated_token_address(owner: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    get_associated_token_address_with_program_id(owner, mint, token_program)
+}
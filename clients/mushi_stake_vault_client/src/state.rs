@@ -0,0 +1,40 @@
+use anchor_lang::prelude::*;
+use mushi_stake_vault::{
+    state::{MainState, StakeEntry},
+    PoolStats, StakeResult, UnstakePreview, UnstakeResult, UserPosition,
+};
+
+/// Decodes a `MainState` from raw account data, checking the discriminator.
+pub fn decode_main_state(data: &[u8]) -> Result<MainState> {
+    MainState::try_deserialize(&mut &data[..])
+}
+
+/// Decodes a `StakeEntry` from raw account data, checking the discriminator.
+pub fn decode_stake_entry(data: &[u8]) -> Result<StakeEntry> {
+    StakeEntry::try_deserialize(&mut &data[..])
+}
+
+/// Decodes the return data of `get_pool_stats`.
+pub fn decode_pool_stats(data: &[u8]) -> Result<PoolStats> {
+    Ok(PoolStats::try_from_slice(data)?)
+}
+
+/// Decodes the return data of `get_user_position`.
+pub fn decode_user_position(data: &[u8]) -> Result<UserPosition> {
+    Ok(UserPosition::try_from_slice(data)?)
+}
+
+/// Decodes the return data of `stake` and `preview_stake`.
+pub fn decode_stake_result(data: &[u8]) -> Result<StakeResult> {
+    Ok(StakeResult::try_from_slice(data)?)
+}
+
+/// Decodes the return data of `unstake`.
+pub fn decode_unstake_result(data: &[u8]) -> Result<UnstakeResult> {
+    Ok(UnstakeResult::try_from_slice(data)?)
+}
+
+/// Decodes the return data of `preview_unstake`.
+pub fn decode_unstake_preview(data: &[u8]) -> Result<UnstakePreview> {
+    Ok(UnstakePreview::try_from_slice(data)?)
+}
@@ -0,0 +1,341 @@
+#![allow(clippy::result_large_err)]
+
+use anchor_lang::{prelude::*, solana_program::instruction::Instruction, Discriminator};
+use anchor_spl::{token, token_2022};
+use mushi_stake_vault::{
+    instruction,
+    state::{BASIS_POINTS_DIVISOR, INITIAL_VIRTUAL_SHARES},
+};
+use mushi_stake_vault_client::{
+    initialize, pda,
+    state::{
+        decode_main_state, decode_pool_stats, decode_stake_entry, decode_stake_result, decode_unstake_preview,
+        decode_unstake_result, decode_user_position,
+    },
+    AccessMode, EclipseSource, InitPoolInput, InitializeAccounts, MainState,
+    PenaltyDestination, Pool, PoolStats, StakeEntry, StakeInput, StakeResult, UnstakeInput, UnstakePreview, UnstakeResult,
+    UpdatePoolInput, UserPosition, ID,
+};
+
+fn main_state() -> MainState {
+    MainState {
+        pool_id: 3,
+        admin: Pubkey::new_unique(),
+        guardian: Pubkey::new_unique(),
+        mushi_token_amount: 10,
+        eclipse_token_amount: 20,
+        staking_token_total_supply: 30,
+        mushi_token_mint: Pubkey::new_unique(),
+        eclipse_token_mint: Pubkey::new_unique(),
+        stake_token_mint: Pubkey::new_unique(),
+        mushi_program: Pubkey::new_unique(),
+        access_mode: AccessMode::Open,
+        virtual_shares: INITIAL_VIRTUAL_SHARES,
+        eclipse_per_mushi_bps: BASIS_POINTS_DIVISOR,
+        ..Default::default()
+    }
+}
+
+fn account_data<T: AccountSerialize>(account: &T) -> Vec<u8> {
+    let mut data = Vec::new();
+    account.try_serialize(&mut data).unwrap();
+    data
+}
+
+fn stake_input() -> StakeInput {
+    StakeInput { mushi_token_amount: 5, eclipse_token_amount: 5 }
+}
+
+fn unstake_input() -> UnstakeInput {
+    UnstakeInput { amount: 5, min_mushi_token_amount: 1, min_eclipse_token_amount: 1 }
+}
+
+/// Optional accounts left out are passed as the program id.
+fn optional(instruction: &Instruction, index: usize) -> Option<Pubkey> {
+    Some(instruction.accounts[index].pubkey).filter(|key| *key != ID)
+}
+
+#[test]
+fn pdas_use_program_seeds() {
+    let main_state = pda::main_state(3).0;
+    let user = Pubkey::new_unique();
+    assert_eq!(main_state, Pubkey::find_program_address(&[b"main_state", &3u64.to_le_bytes()], &ID).0);
+    assert_ne!(main_state, pda::main_state(4).0);
+    assert_eq!(
+        pda::token_vault_owner(&main_state).0,
+        Pubkey::find_program_address(&[b"vault_owner", main_state.as_ref()], &ID).0
+    );
+    assert_eq!(
+        pda::reward_token_vault(&main_state).0,
+        Pubkey::find_program_address(&[b"reward_vault", main_state.as_ref()], &ID).0
+    );
+    assert_eq!(
+        pda::stake_entry(&main_state, &user).0,
+        Pubkey::find_program_address(&[b"stake_entry", main_state.as_ref(), user.as_ref()], &ID).0
+    );
+    assert_eq!(
+        pda::unstake_request(&main_state, &user).0,
+        Pubkey::find_program_address(&[b"unstake_request", main_state.as_ref(), user.as_ref()], &ID).0
+    );
+    let stake_token_mint = Pubkey::new_unique();
+    assert_eq!(
+        pda::stake_token_metadata(&stake_token_mint).0,
+        Pubkey::find_program_address(
+            &[b"metadata", mpl_token_metadata::ID.as_ref(), stake_token_mint.as_ref()],
+            &mpl_token_metadata::ID
+        )
+        .0
+    );
+}
+
+#[test]
+fn associated_token_address_depends_on_token_program() {
+    let (owner, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+    assert_ne!(
+        pda::associated_token_address(&owner, &mint, &token::ID),
+        pda::associated_token_address(&owner, &mint, &token_2022::ID)
+    );
+}
+
+#[test]
+fn initialize_orders_accounts_and_encodes_input() {
+    let keys = InitializeAccounts {
+        admin: Pubkey::new_unique(),
+        mushi_token_mint: Pubkey::new_unique(),
+        mushi_token_program: token_2022::ID,
+        eclipse_token_mint: Pubkey::new_unique(),
+        stake_token_mint: Pubkey::new_unique(),
+    };
+    let (admin, stake_token_mint, mushi_token_mint) = (keys.admin, keys.stake_token_mint, keys.mushi_token_mint);
+    let ix = initialize(
+        keys,
+        InitPoolInput {
+            pool_id: 3,
+            stake_token_name: "Staked MUSHI".to_string(),
+            stake_token_symbol: "sMUSHI".to_string(),
+            stake_token_uri: String::new(),
+            mushi_program: Pubkey::new_unique(),
+        },
+    );
+
+    assert_eq!(ix.program_id, ID);
+    assert_eq!(ix.accounts.len(), 17);
+    assert_eq!((ix.accounts[0].pubkey, ix.accounts[0].is_signer, ix.accounts[0].is_writable), (admin, true, true));
+    assert_eq!(ix.accounts[1].pubkey, pda::main_state(3).0);
+    assert_eq!((ix.accounts[4].pubkey, ix.accounts[4].is_signer), (stake_token_mint, true));
+    let token_vault_owner = pda::token_vault_owner(&pda::main_state(3).0).0;
+    assert_eq!(ix.accounts[6].pubkey, token_vault_owner);
+    assert_eq!(
+        ix.accounts[7].pubkey,
+        pda::associated_token_address(&token_vault_owner, &mushi_token_mint, &token_2022::ID)
+    );
+    assert_eq!(ix.accounts[11].pubkey, token_2022::ID);
+
+    assert_eq!(&ix.data[..8], instruction::Initialize::DISCRIMINATOR.as_slice());
+    let input = InitPoolInput::try_from_slice(&ix.data[8..]).unwrap();
+    assert_eq!((input.pool_id, input.stake_token_symbol.as_str()), (3, "sMUSHI"));
+}
+
+#[test]
+fn stake_selects_eclipse_source() {
+    let pool = Pool::new(main_state(), token::ID);
+    let user = Pubkey::new_unique();
+    let vault = Pubkey::new_unique();
+
+    let ix = pool.stake(&user, EclipseSource::User, stake_input());
+    assert_eq!(ix.accounts.len(), 19);
+    assert_eq!((ix.accounts[0].pubkey, ix.accounts[0].is_signer), (user, true));
+    assert_eq!(ix.accounts[1].pubkey, pool.address);
+    assert_eq!(optional(&ix, 2), None);
+    assert_eq!(ix.accounts[3].pubkey, pda::stake_entry(&pool.address, &user).0);
+    assert_eq!(optional(&ix, 9), Some(pool.user_eclipse_token_ata(&user)));
+    assert_eq!(optional(&ix, 10), None);
+    assert_eq!(&ix.data[..8], instruction::Stake::DISCRIMINATOR.as_slice());
+    assert_eq!(StakeInput::try_from_slice(&ix.data[8..]).unwrap().mushi_token_amount, 5);
+
+    let ix = pool.stake(&user, EclipseSource::MushiProgramVault(vault), stake_input());
+    assert_eq!((optional(&ix, 9), optional(&ix, 10)), (None, Some(vault)));
+
+    let ix = pool.stake(&user, EclipseSource::None, stake_input());
+    assert_eq!((optional(&ix, 9), optional(&ix, 10)), (None, None));
+}
+
+#[test]
+fn stake_and_its_preview_pass_allowlist_entry_only_in_allowlist_mode() {
+    let mut state = main_state();
+    state.access_mode = AccessMode::Allowlist;
+    let pool = Pool::new(state, token::ID);
+    let user = Pubkey::new_unique();
+    let allowlist_entry = Some(pda::allowlist_entry(&pool.address, &user).0);
+    assert_eq!(optional(&pool.stake(&user, EclipseSource::User, stake_input()), 2), allowlist_entry);
+    assert_eq!(optional(&pool.preview_stake(&user, stake_input()), 2), allowlist_entry);
+    // Exits are not gated, so unstake never needs the entry.
+    assert_eq!(pool.unstake(&user, None, unstake_input()).accounts.len(), 17);
+}
+
+#[test]
+fn unstake_passes_treasury_account_only_for_treasury_penalties() {
+    let mut state = main_state();
+    let user = Pubkey::new_unique();
+    let eclipse_token_source = Pubkey::new_unique();
+    let ix = Pool::new(state.clone(), token_2022::ID).unstake(&user, Some(eclipse_token_source), unstake_input());
+    assert_eq!(ix.accounts.len(), 17);
+    assert_eq!(optional(&ix, 6), None);
+    assert_eq!(optional(&ix, 9), Some(eclipse_token_source));
+    assert_eq!(ix.accounts[15].pubkey, token_2022::ID);
+    assert_eq!(UnstakeInput::try_from_slice(&ix.data[8..]).unwrap().min_eclipse_token_amount, 1);
+
+    state.penalty_destination = PenaltyDestination::Treasury;
+    state.treasury = Pubkey::new_unique();
+    let pool = Pool::new(state, token_2022::ID);
+    let ix = pool.unstake(&user, None, unstake_input());
+    assert_eq!(optional(&ix, 9), None);
+    assert_eq!(
+        optional(&ix, 6),
+        Some(pda::associated_token_address(&pool.state.treasury, &pool.state.mushi_token_mint, &token_2022::ID))
+    );
+}
+
+#[test]
+fn update_pool_is_signed_by_admin() {
+    let pool = Pool::new(main_state(), token::ID);
+    let ix = pool.update_pool(UpdatePoolInput {
+        mushi_program: pool.state.mushi_program,
+        access_mode: Some(AccessMode::Allowlist),
+        guardian: None,
+        unstake_cooldown: Some(60),
+        early_unstake_penalty_bps: None,
+        penalty_decay_period: None,
+        penalty_curve: None,
+        penalty_destination: None,
+        treasury: None,
+        eclipse_deposit_mode: None,
+        eclipse_per_mushi_bps: None,
+    });
+    assert_eq!((ix.accounts[0].pubkey, ix.accounts[0].is_signer), (pool.state.admin, true));
+    assert_eq!(ix.accounts[1].pubkey, pool.address);
+    let input = UpdatePoolInput::try_from_slice(&ix.data[8..]).unwrap();
+    assert_eq!((input.access_mode, input.unstake_cooldown), (Some(AccessMode::Allowlist), Some(60)));
+}
+
+#[test]
+fn decodes_accounts_from_raw_data() {
+    let state = main_state();
+    let decoded = decode_main_state(&account_data(&state)).unwrap();
+    assert_eq!((decoded.pool_id, decoded.admin, decoded.staking_token_total_supply), (3, state.admin, 30));
+
+    let stake_entry = StakeEntry {
+        user: Pubkey::new_unique(),
+        last_staked: 42,
+        mushi_token_staked_amount: 1,
+        eclipse_token_staked_amount: 2,
+        stake_token_balance: 3,
+        rewards_amount: 4,
+        reward_per_share_paid: 5,
+        eclipse_token_source: Pubkey::new_unique(),
+    };
+    let decoded = decode_stake_entry(&account_data(&stake_entry)).unwrap();
+    assert_eq!((decoded.user, decoded.last_staked, decoded.reward_per_share_paid), (stake_entry.user, 42, 5));
+
+    assert!(decode_main_state(&account_data(&stake_entry)).is_err());
+    assert!(decode_stake_entry(&account_data(&state)).is_err());
+    assert!(decode_main_state(&[]).is_err());
+}
+
+#[test]
+fn view_instructions_are_unsigned_and_results_decode() {
+    let pool = Pool::new(main_state(), token::ID);
+    let user = Pubkey::new_unique();
+
+    let ix = pool.get_pool_stats();
+    assert_eq!(ix.accounts.len(), 1);
+    assert_eq!((ix.accounts[0].pubkey, ix.accounts[0].is_signer, ix.accounts[0].is_writable), (pool.address, false, false));
+    assert_eq!(&ix.data[..8], instruction::GetPoolStats::DISCRIMINATOR.as_slice());
+
+    let ix = pool.get_user_position(&user);
+    assert!(ix.accounts.iter().all(|meta| !meta.is_signer && !meta.is_writable));
+    assert_eq!(ix.accounts[0].pubkey, user);
+    assert_eq!(ix.accounts[2].pubkey, pda::stake_entry(&pool.address, &user).0);
+    assert_eq!(&ix.data[..8], instruction::GetUserPosition::DISCRIMINATOR.as_slice());
+
+    let stats = PoolStats {
+        main_state: pool.address,
+        pool_id: 3,
+        paused: 0,
+        access_mode: AccessMode::Open,
+        mushi_token_amount: 10,
+        eclipse_token_amount: 20,
+        staking_token_total_supply: 30,
+        virtual_shares: INITIAL_VIRTUAL_SHARES,
+        pending_unstake_mushi_amount: 0,
+        pending_unstake_eclipse_amount: 0,
+        exchange_rate: 1,
+        reward_rate: 0,
+        reward_end_time: 0,
+        acc_reward_per_share: 7,
+        timestamp: 42,
+    };
+    assert_eq!(decode_pool_stats(&stats.try_to_vec().unwrap()).unwrap(), stats);
+    let position = UserPosition {
+        main_state: pool.address,
+        user,
+        stake_token_balance: 1,
+        mushi_token_value: 2,
+        mushi_token_staked_amount: 3,
+        eclipse_token_staked_amount: 4,
+        pending_rewards: 5,
+        last_staked: 6,
+        timestamp: 7,
+    };
+    assert_eq!(decode_user_position(&position.try_to_vec().unwrap()).unwrap(), position);
+    assert!(decode_user_position(&[1, 2, 3]).is_err());
+}
+
+#[test]
+fn decodes_stake_and_unstake_results() {
+    let stake = StakeResult {
+        mushi_token_amount: 1,
+        eclipse_token_amount: 2,
+        stake_token_amount: 3,
+        total_mushi_token_amount: 4,
+        total_eclipse_token_amount: 5,
+        staking_token_total_supply: 6,
+    };
+    assert_eq!(decode_stake_result(&stake.try_to_vec().unwrap()).unwrap(), stake);
+    let unstake = UnstakeResult {
+        stake_token_amount: 1,
+        mushi_token_amount: 2,
+        eclipse_token_amount: 3,
+        penalty_amount: 4,
+        total_mushi_token_amount: 5,
+        total_eclipse_token_amount: 6,
+        staking_token_total_supply: 7,
+    };
+    assert_eq!(decode_unstake_result(&unstake.try_to_vec().unwrap()).unwrap(), unstake);
+    assert!(decode_unstake_result(&stake.try_to_vec().unwrap()).is_err());
+    let preview = UnstakePreview { result: unstake, mushi_token_received: 1, eclipse_token_received: 2, unlock_time: Some(3) };
+    assert_eq!(decode_unstake_preview(&preview.try_to_vec().unwrap()).unwrap(), preview);
+}
+
+#[test]
+fn preview_instructions_are_unsigned_and_encode_input() {
+    let pool = Pool::new(main_state(), token::ID);
+    let user = Pubkey::new_unique();
+
+    let ix = pool.preview_stake(&user, stake_input());
+    assert!(ix.accounts.iter().all(|meta| !meta.is_signer && !meta.is_writable));
+    assert_eq!(ix.accounts[0].pubkey, user);
+    assert_eq!(ix.accounts[1].pubkey, pool.address);
+    assert_eq!(optional(&ix, 2), None);
+    assert_eq!(ix.accounts[3].pubkey, pool.mushi_program_token_vault_owner());
+    assert_eq!(ix.accounts[4].pubkey, pool.state.mushi_token_mint);
+    assert_eq!(&ix.data[..8], instruction::PreviewStake::DISCRIMINATOR.as_slice());
+    assert_eq!(StakeInput::try_from_slice(&ix.data[8..]).unwrap().eclipse_token_amount, 5);
+
+    let ix = pool.preview_unstake(&user, unstake_input());
+    assert!(ix.accounts.iter().all(|meta| !meta.is_signer && !meta.is_writable));
+    assert_eq!(ix.accounts[2].pubkey, pda::stake_entry(&pool.address, &user).0);
+    assert_eq!(ix.accounts[3].pubkey, pool.mushi_token_vault());
+    assert_eq!(&ix.data[..8], instruction::PreviewUnstake::DISCRIMINATOR.as_slice());
+    assert_eq!(UnstakeInput::try_from_slice(&ix.data[8..]).unwrap().amount, 5);
+}
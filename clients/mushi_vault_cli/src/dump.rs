@@ -0,0 +1,95 @@
+use std::{collections::BTreeMap, fs, path::Path, str::FromStr};
+
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use solana_sdk::{account::Account, pubkey::Pubkey};
+
+/// Account as returned by JSON-RPC with `base64` encoding and written by
+/// `solana account <ADDRESS> --output json`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UiAccount {
+    pub lamports: u64,
+    pub data: (String, String),
+    pub owner: String,
+    pub executable: bool,
+    #[serde(default)]
+    pub rent_epoch: u64,
+}
+
+impl UiAccount {
+    pub fn from_account(account: &Account) -> Self {
+        Self {
+            lamports: account.lamports,
+            data: (STANDARD.encode(&account.data), "base64".to_string()),
+            owner: account.owner.to_string(),
+            executable: account.executable,
+            rent_epoch: account.rent_epoch,
+        }
+    }
+
+    pub fn decode(&self) -> Result<Account> {
+        if self.data.1 != "base64" {
+            bail!("unsupported account data encoding `{}`, expected base64", self.data.1);
+        }
+        Ok(Account {
+            lamports: self.lamports,
+            data: STANDARD.decode(&self.data.0).context("invalid base64 account data")?,
+            owner: Pubkey::from_str(&self.owner)?,
+            executable: self.executable,
+            rent_epoch: self.rent_epoch,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct KeyedAccount {
+    pub pubkey: String,
+    pub account: UiAccount,
+}
+
+impl KeyedAccount {
+    pub fn decode(&self) -> Result<(Pubkey, Account)> {
+        Ok((Pubkey::from_str(&self.pubkey)?, self.account.decode()?))
+    }
+}
+
+/// A dump file holds one account, or a list of them as returned by `getProgramAccounts`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DumpFile {
+    One(KeyedAccount),
+    Many(Vec<KeyedAccount>),
+}
+
+/// Loads every account in `path`, a dump file or a directory of `*.json` dump files.
+pub fn load(path: &Path) -> Result<BTreeMap<Pubkey, Account>> {
+    let mut files = Vec::new();
+    if path.is_dir() {
+        for entry in fs::read_dir(path)? {
+            let file = entry?.path();
+            if file.extension().is_some_and(|extension| extension == "json") {
+                files.push(file);
+            }
+        }
+        files.sort();
+    } else {
+        files.push(path.to_path_buf());
+    }
+
+    let mut accounts = BTreeMap::new();
+    for file in files {
+        let contents = fs::read_to_string(&file).with_context(|| format!("reading {}", file.display()))?;
+        let dump: DumpFile = serde_json::from_str(&contents).with_context(|| format!("parsing {}", file.display()))?;
+        let keyed = match dump {
+            DumpFile::One(account) => vec![account],
+            DumpFile::Many(accounts) => accounts,
+        };
+        for account in keyed {
+            let (key, account) = account.decode().with_context(|| format!("decoding {}", file.display()))?;
+            accounts.insert(key, account);
+        }
+    }
+    Ok(accounts)
+}
@@ -0,0 +1,155 @@
+use std::fmt::Write;
+
+use anchor_lang::{AccountDeserialize, Discriminator};
+use anchor_spl::{token, token_2022, token_interface};
+use anyhow::{anyhow, Context, Result};
+use mushi_stake_vault::ixs::audit_pool::pool_audit;
+use mushi_stake_vault_client::{
+    pda,
+    state::{decode_main_state, decode_stake_entry},
+    Pool, StakeEntry, ID,
+};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::source::AccountSource;
+
+/// Loads pool `pool_id` and works out which token program its MUSHI mint uses:
+/// the mint's owner when the mint is available, otherwise whichever program
+/// the MUSHI vault was created under.
+pub fn load_pool(source: &AccountSource, pool_id: u64) -> Result<Pool> {
+    let address = pda::main_state(pool_id).0;
+    let account = source
+        .get_account(&address)?
+        .with_context(|| format!("main state {address} of pool {pool_id} not found"))?;
+    let state = decode_main_state(&account.data).map_err(|err| anyhow!("decoding main state {address}: {err}"))?;
+
+    let mushi_token_program = match source.get_account(&state.mushi_token_mint)? {
+        Some(mint) => mint.owner,
+        None => {
+            let token_2022_pool = Pool::new(state.clone(), token_2022::ID);
+            if source.get_account(&token_2022_pool.mushi_token_vault())?.is_some() {
+                token_2022::ID
+            } else {
+                token::ID
+            }
+        }
+    };
+    Ok(Pool::new(state, mushi_token_program))
+}
+
+fn token_amount(source: &AccountSource, address: &Pubkey) -> Result<Option<u64>> {
+    let Some(account) = source.get_account(address)? else {
+        return Ok(None);
+    };
+    let token_account = token_interface::TokenAccount::try_deserialize(&mut account.data.as_slice())
+        .map_err(|err| anyhow!("decoding token account {address}: {err}"))?;
+    Ok(Some(token_account.amount))
+}
+
+fn mint_supply(source: &AccountSource, address: &Pubkey) -> Result<Option<u64>> {
+    let Some(account) = source.get_account(address)? else {
+        return Ok(None);
+    };
+    let mint = token_interface::Mint::try_deserialize(&mut account.data.as_slice())
+        .map_err(|err| anyhow!("decoding mint {address}: {err}"))?;
+    Ok(Some(mint.supply))
+}
+
+fn or_missing(amount: Option<u64>) -> String {
+    amount.map_or_else(|| "missing".to_string(), |amount| amount.to_string())
+}
+
+/// `MainState` fields followed by the vault balances and their drift from the counters.
+pub fn main_state_report(source: &AccountSource, pool_id: u64) -> Result<String> {
+    let pool = load_pool(source, pool_id)?;
+    let state = &pool.state;
+    let mut report = String::new();
+    writeln!(report, "main_state:                     {}", pool.address)?;
+    writeln!(report, "pool_id:                        {}", state.pool_id)?;
+    writeln!(report, "admin:                          {}", state.admin)?;
+    writeln!(report, "pending_admin:                  {}", state.pending_admin)?;
+    writeln!(report, "guardian:                       {}", state.guardian)?;
+    writeln!(report, "paused:                         {:#06b}", state.paused)?;
+    writeln!(report, "mushi_program:                  {}", state.mushi_program)?;
+    writeln!(report, "access_mode:                    {:?}", state.access_mode)?;
+    writeln!(report, "mushi_token_mint:               {} ({})", state.mushi_token_mint, pool.mushi_token_program)?;
+    writeln!(report, "eclipse_token_mint:             {}", state.eclipse_token_mint)?;
+    writeln!(report, "stake_token_mint:               {}", state.stake_token_mint)?;
+    writeln!(report, "mushi_token_amount:             {}", state.mushi_token_amount)?;
+    writeln!(report, "eclipse_token_amount:           {}", state.eclipse_token_amount)?;
+    writeln!(report, "staking_token_total_supply:     {}", state.staking_token_total_supply)?;
+    writeln!(report, "virtual_shares:                 {}", state.virtual_shares)?;
+    writeln!(report, "pending_unstake_mushi_amount:   {}", state.pending_unstake_mushi_amount)?;
+    writeln!(report, "pending_unstake_eclipse_amount: {}", state.pending_unstake_eclipse_amount)?;
+    writeln!(report, "unstake_cooldown:               {}s", state.unstake_cooldown)?;
+    writeln!(report, "early_unstake_penalty_bps:      {}", state.early_unstake_penalty_bps)?;
+    writeln!(report, "penalty_decay_period:           {}s", state.penalty_decay_period)?;
+    writeln!(report, "penalty_curve:                  {:?}", state.penalty_curve)?;
+    writeln!(report, "penalty_destination:            {:?}", state.penalty_destination)?;
+    writeln!(report, "treasury:                       {}", state.treasury)?;
+    writeln!(report, "eclipse_deposit_mode:           {:?}", state.eclipse_deposit_mode)?;
+    writeln!(report, "eclipse_per_mushi_bps:          {}", state.eclipse_per_mushi_bps)?;
+    writeln!(report, "reward_rate:                    {}/s", state.reward_rate)?;
+    writeln!(report, "reward_window:                  {}..{}", state.reward_start_time, state.reward_end_time)?;
+    writeln!(report, "last_reward_update:             {}", state.last_reward_update)?;
+    writeln!(report, "acc_reward_per_share:           {}", state.acc_reward_per_share)?;
+
+    let mushi_token_vault = pool.mushi_token_vault();
+    let eclipse_token_vault = pool.eclipse_token_vault();
+    let reward_token_vault = pda::reward_token_vault(&pool.address).0;
+    let mushi_token_vault_amount = token_amount(source, &mushi_token_vault)?;
+    let eclipse_token_vault_amount = token_amount(source, &eclipse_token_vault)?;
+    let stake_token_supply = mint_supply(source, &state.stake_token_mint)?;
+    writeln!(report)?;
+    writeln!(report, "mushi_token_vault:              {} = {}", mushi_token_vault, or_missing(mushi_token_vault_amount))?;
+    writeln!(report, "eclipse_token_vault:            {} = {}", eclipse_token_vault, or_missing(eclipse_token_vault_amount))?;
+    writeln!(report, "reward_token_vault:             {} = {}", reward_token_vault, or_missing(token_amount(source, &reward_token_vault)?))?;
+    writeln!(report, "stake_token_supply:             {}", or_missing(stake_token_supply))?;
+
+    if let (Some(mushi), Some(eclipse), Some(supply)) = (mushi_token_vault_amount, eclipse_token_vault_amount, stake_token_supply) {
+        let audit = pool_audit(state, pool.address, mushi, eclipse, supply, 0);
+        writeln!(report, "mushi_token_delta:              {}", audit.mushi_token_delta)?;
+        writeln!(report, "eclipse_token_delta:            {}", audit.eclipse_token_delta)?;
+        writeln!(report, "stake_token_supply_delta:       {}", audit.stake_token_supply_delta)?;
+    }
+    Ok(report)
+}
+
+/// Stake entries of pool `pool_id`, keyed by their address.
+pub fn stake_entries(source: &AccountSource, pool_id: u64) -> Result<Vec<(Pubkey, StakeEntry)>> {
+    let main_state = pda::main_state(pool_id).0;
+    let mut entries = Vec::new();
+    for (address, account) in source.get_program_accounts(&ID, &StakeEntry::DISCRIMINATOR)? {
+        let entry = decode_stake_entry(&account.data).map_err(|err| anyhow!("decoding stake entry {address}: {err}"))?;
+        // Entries carry no pool key; only the PDA ties them to a pool.
+        if pda::stake_entry(&main_state, &entry.user).0 == address {
+            entries.push((address, entry));
+        }
+    }
+    entries.sort_by_key(|(_, entry)| std::cmp::Reverse(entry.stake_token_balance));
+    Ok(entries)
+}
+
+pub fn stake_entries_report(source: &AccountSource, pool_id: u64) -> Result<String> {
+    let entries = stake_entries(source, pool_id)?;
+    let mut report = String::new();
+    writeln!(
+        report,
+        "{:<44}  {:>20}  {:>20}  {:>20}  {:>20}  {:>12}",
+        "user", "stake_token_balance", "mushi_staked", "eclipse_staked", "rewards", "last_staked"
+    )?;
+    for (_, entry) in &entries {
+        writeln!(
+            report,
+            "{:<44}  {:>20}  {:>20}  {:>20}  {:>20}  {:>12}",
+            entry.user.to_string(),
+            entry.stake_token_balance,
+            entry.mushi_token_staked_amount,
+            entry.eclipse_token_staked_amount,
+            entry.rewards_amount,
+            entry.last_staked
+        )?;
+    }
+    writeln!(report, "{} stake entries", entries.len())?;
+    Ok(report)
+}
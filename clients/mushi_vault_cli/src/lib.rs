@@ -0,0 +1,7 @@
+//! Library behind the `mushi-vault` CLI: account loading from RPC or dumped
+//! JSON files, and the pool reports the inspection subcommands print.
+
+pub mod dump;
+pub mod inspect;
+pub mod rpc;
+pub mod source;
@@ -0,0 +1,279 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use mushi_stake_vault_client::{
+    initialize, pda, AccessMode, EclipseDepositMode, InitPoolInput, InitializeAccounts, PenaltyCurve, PenaltyDestination,
+    UpdatePoolInput,
+};
+use mushi_vault_cli::{dump, inspect, rpc::RpcClient, source::AccountSource};
+use solana_sdk::{
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Keypair, Signer},
+    transaction::Transaction,
+};
+
+#[derive(Parser)]
+#[command(name = "mushi-vault", about = "Operate and inspect Mushi stake vault pools")]
+struct Cli {
+    /// JSON-RPC endpoint.
+    #[arg(long, short = 'u', global = true, default_value = "http://127.0.0.1:8899")]
+    url: String,
+    /// Read accounts from a `solana account --output json` dump file or a
+    /// directory of them instead of the cluster.
+    #[arg(long, global = true, value_name = "PATH")]
+    offline: Option<PathBuf>,
+    /// Fee payer and admin keypair.
+    #[arg(long, short = 'k', global = true)]
+    keypair: Option<PathBuf>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create a pool and its stake token mint.
+    Init {
+        #[arg(long, default_value_t = 0)]
+        pool_id: u64,
+        #[arg(long)]
+        mushi_token_mint: Pubkey,
+        #[arg(long)]
+        eclipse_token_mint: Pubkey,
+        #[arg(long)]
+        mushi_program: Pubkey,
+        #[arg(long, default_value = "Staked MUSHI")]
+        name: String,
+        #[arg(long, default_value = "sMUSHI")]
+        symbol: String,
+        #[arg(long, default_value = "")]
+        uri: String,
+        /// Simulate the transaction and print its logs without sending it.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Change pool settings; omitted options keep their current values.
+    UpdatePool {
+        #[arg(long, default_value_t = 0)]
+        pool_id: u64,
+        #[arg(long)]
+        mushi_program: Option<Pubkey>,
+        #[arg(long)]
+        access_mode: Option<AccessModeArg>,
+        #[arg(long)]
+        guardian: Option<Pubkey>,
+        #[arg(long, value_name = "SECONDS")]
+        unstake_cooldown: Option<i64>,
+        #[arg(long)]
+        early_unstake_penalty_bps: Option<u16>,
+        #[arg(long, value_name = "SECONDS")]
+        penalty_decay_period: Option<i64>,
+        #[arg(long)]
+        penalty_curve: Option<PenaltyCurveArg>,
+        #[arg(long)]
+        penalty_destination: Option<PenaltyDestinationArg>,
+        #[arg(long)]
+        treasury: Option<Pubkey>,
+        #[arg(long)]
+        eclipse_deposit_mode: Option<EclipseDepositModeArg>,
+        #[arg(long)]
+        eclipse_per_mushi_bps: Option<u64>,
+        /// Simulate the transaction and print its logs without sending it.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Print a pool's main state, vault balances and counter drift.
+    MainState {
+        #[arg(long, default_value_t = 0)]
+        pool_id: u64,
+    },
+    /// List a pool's stake entries, largest first.
+    StakeEntries {
+        #[arg(long, default_value_t = 0)]
+        pool_id: u64,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum AccessModeArg {
+    Open,
+    ProgramGated,
+    Allowlist,
+}
+
+impl From<AccessModeArg> for AccessMode {
+    fn from(value: AccessModeArg) -> Self {
+        match value {
+            AccessModeArg::Open => AccessMode::Open,
+            AccessModeArg::ProgramGated => AccessMode::ProgramGated,
+            AccessModeArg::Allowlist => AccessMode::Allowlist,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum PenaltyCurveArg {
+    Linear,
+    Cliff,
+}
+
+impl From<PenaltyCurveArg> for PenaltyCurve {
+    fn from(value: PenaltyCurveArg) -> Self {
+        match value {
+            PenaltyCurveArg::Linear => PenaltyCurve::Linear,
+            PenaltyCurveArg::Cliff => PenaltyCurve::Cliff,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum PenaltyDestinationArg {
+    Vault,
+    Treasury,
+}
+
+impl From<PenaltyDestinationArg> for PenaltyDestination {
+    fn from(value: PenaltyDestinationArg) -> Self {
+        match value {
+            PenaltyDestinationArg::Vault => PenaltyDestination::Vault,
+            PenaltyDestinationArg::Treasury => PenaltyDestination::Treasury,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum EclipseDepositModeArg {
+    Required,
+    Optional,
+    MushiProgramVault,
+}
+
+impl From<EclipseDepositModeArg> for EclipseDepositMode {
+    fn from(value: EclipseDepositModeArg) -> Self {
+        match value {
+            EclipseDepositModeArg::Required => EclipseDepositMode::Required,
+            EclipseDepositModeArg::Optional => EclipseDepositMode::Optional,
+            EclipseDepositModeArg::MushiProgramVault => EclipseDepositMode::MushiProgramVault,
+        }
+    }
+}
+
+fn read_keypair(path: Option<&PathBuf>) -> Result<Keypair> {
+    let path = match path {
+        Some(path) => path.clone(),
+        None => PathBuf::from(std::env::var("HOME").context("HOME is not set")?).join(".config/solana/id.json"),
+    };
+    read_keypair_file(&path).map_err(|err| anyhow::anyhow!("reading keypair {}: {err}", path.display()))
+}
+
+/// Signs `instruction` with `signers`, the first paying fees, then either
+/// simulates it or sends it and waits for confirmation.
+fn submit(rpc: &RpcClient, instruction: Instruction, signers: &[&Keypair], dry_run: bool) -> Result<()> {
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&signers[0].pubkey()),
+        signers,
+        rpc.get_latest_blockhash()?,
+    );
+    if dry_run {
+        let simulation = rpc.simulate_transaction(&transaction)?;
+        for log in &simulation.logs {
+            println!("{log}");
+        }
+        match simulation.err {
+            Some(err) => bail!("simulation failed: {err}"),
+            None => println!("simulation succeeded"),
+        }
+    } else {
+        println!("signature: {}", rpc.send_and_confirm_transaction(&transaction)?);
+    }
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let source = match &cli.offline {
+        Some(path) => AccountSource::Offline(dump::load(path)?),
+        None => AccountSource::Rpc(RpcClient::new(cli.url.clone())),
+    };
+    let rpc = || match &cli.offline {
+        Some(_) => bail!("this command sends a transaction and cannot run with --offline"),
+        None => Ok(RpcClient::new(cli.url.clone())),
+    };
+
+    match cli.command {
+        Command::Init { pool_id, mushi_token_mint, eclipse_token_mint, mushi_program, name, symbol, uri, dry_run } => {
+            let rpc = rpc()?;
+            let admin = read_keypair(cli.keypair.as_ref())?;
+            let mushi_token_program = source
+                .get_account(&mushi_token_mint)?
+                .with_context(|| format!("MUSHI mint {mushi_token_mint} not found"))?
+                .owner;
+            let stake_token_mint = Keypair::new();
+            let instruction = initialize(
+                InitializeAccounts {
+                    admin: admin.pubkey(),
+                    mushi_token_mint,
+                    mushi_token_program,
+                    eclipse_token_mint,
+                    stake_token_mint: stake_token_mint.pubkey(),
+                },
+                InitPoolInput {
+                    pool_id,
+                    stake_token_name: name,
+                    stake_token_symbol: symbol,
+                    stake_token_uri: uri,
+                    mushi_program,
+                },
+            );
+            println!("main_state: {}", pda::main_state(pool_id).0);
+            println!("stake_token_mint: {}", stake_token_mint.pubkey());
+            submit(&rpc, instruction, &[&admin, &stake_token_mint], dry_run)
+        }
+        Command::UpdatePool {
+            pool_id,
+            mushi_program,
+            access_mode,
+            guardian,
+            unstake_cooldown,
+            early_unstake_penalty_bps,
+            penalty_decay_period,
+            penalty_curve,
+            penalty_destination,
+            treasury,
+            eclipse_deposit_mode,
+            eclipse_per_mushi_bps,
+            dry_run,
+        } => {
+            let rpc = rpc()?;
+            let admin = read_keypair(cli.keypair.as_ref())?;
+            let pool = inspect::load_pool(&source, pool_id)?;
+            if pool.state.admin != admin.pubkey() {
+                bail!("{} is not the admin of pool {pool_id}; the admin is {}", admin.pubkey(), pool.state.admin);
+            }
+            let instruction = pool.update_pool(UpdatePoolInput {
+                mushi_program: mushi_program.unwrap_or(pool.state.mushi_program),
+                access_mode: access_mode.map(Into::into),
+                guardian,
+                unstake_cooldown,
+                early_unstake_penalty_bps,
+                penalty_decay_period,
+                penalty_curve: penalty_curve.map(Into::into),
+                penalty_destination: penalty_destination.map(Into::into),
+                treasury,
+                eclipse_deposit_mode: eclipse_deposit_mode.map(Into::into),
+                eclipse_per_mushi_bps,
+            });
+            submit(&rpc, instruction, &[&admin], dry_run)
+        }
+        Command::MainState { pool_id } => {
+            print!("{}", inspect::main_state_report(&source, pool_id)?);
+            Ok(())
+        }
+        Command::StakeEntries { pool_id } => {
+            print!("{}", inspect::stake_entries_report(&source, pool_id)?);
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use std::{str::FromStr, thread, time::Duration};
+
+use anyhow::{anyhow, bail, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde_json::{json, Value};
+use solana_sdk::{account::Account, hash::Hash, pubkey::Pubkey, signature::Signature, transaction::Transaction};
+
+use crate::dump::{KeyedAccount, UiAccount};
+
+/// Minimal JSON-RPC client covering the calls the CLI makes.
+pub struct RpcClient {
+    url: String,
+}
+
+/// Outcome of `simulateTransaction`.
+pub struct Simulation {
+    pub err: Option<Value>,
+    pub logs: Vec<String>,
+}
+
+impl RpcClient {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self { url: url.into() }
+    }
+
+    fn call(&self, method: &str, params: Value) -> Result<Value> {
+        let response: Value = ureq::post(&self.url)
+            .send_json(json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params }))?
+            .into_json()?;
+        if let Some(error) = response.get("error") {
+            bail!("{method} failed: {error}");
+        }
+        Ok(response["result"].clone())
+    }
+
+    pub fn get_account(&self, key: &Pubkey) -> Result<Option<Account>> {
+        let result = self.call("getAccountInfo", json!([key.to_string(), { "encoding": "base64", "commitment": "confirmed" }]))?;
+        match &result["value"] {
+            Value::Null => Ok(None),
+            value => Ok(Some(serde_json::from_value::<UiAccount>(value.clone())?.decode()?)),
+        }
+    }
+
+    /// Accounts of `program` whose data starts with `prefix`.
+    pub fn get_program_accounts(&self, program: &Pubkey, prefix: &[u8]) -> Result<Vec<(Pubkey, Account)>> {
+        let result = self.call(
+            "getProgramAccounts",
+            json!([
+                program.to_string(),
+                {
+                    "encoding": "base64",
+                    "commitment": "confirmed",
+                    "filters": [{ "memcmp": { "offset": 0, "bytes": STANDARD.encode(prefix), "encoding": "base64" } }],
+                },
+            ]),
+        )?;
+        serde_json::from_value::<Vec<KeyedAccount>>(result)?.iter().map(KeyedAccount::decode).collect()
+    }
+
+    pub fn get_latest_blockhash(&self) -> Result<Hash> {
+        let result = self.call("getLatestBlockhash", json!([{ "commitment": "confirmed" }]))?;
+        let blockhash = result["value"]["blockhash"].as_str().ok_or_else(|| anyhow!("missing blockhash"))?;
+        Ok(Hash::from_str(blockhash)?)
+    }
+
+    pub fn simulate_transaction(&self, transaction: &Transaction) -> Result<Simulation> {
+        let result = self.call(
+            "simulateTransaction",
+            json!([STANDARD.encode(bincode::serialize(transaction)?), { "encoding": "base64", "sigVerify": true, "commitment": "confirmed" }]),
+        )?;
+        let value = &result["value"];
+        Ok(Simulation {
+            err: Some(value["err"].clone()).filter(|err| !err.is_null()),
+            logs: serde_json::from_value(value["logs"].clone()).unwrap_or_default(),
+        })
+    }
+
+    /// Sends `transaction` and waits up to a minute for it to be confirmed.
+    pub fn send_and_confirm_transaction(&self, transaction: &Transaction) -> Result<Signature> {
+        let result = self.call(
+            "sendTransaction",
+            json!([STANDARD.encode(bincode::serialize(transaction)?), { "encoding": "base64", "preflightCommitment": "confirmed" }]),
+        )?;
+        let signature = Signature::from_str(result.as_str().ok_or_else(|| anyhow!("missing signature"))?)?;
+        for _ in 0..60 {
+            let statuses = self.call("getSignatureStatuses", json!([[signature.to_string()]]))?;
+            let status = &statuses["value"][0];
+            if !status.is_null() {
+                if !status["err"].is_null() {
+                    bail!("transaction {signature} failed: {}", status["err"]);
+                }
+                if matches!(status["confirmationStatus"].as_str(), Some("confirmed" | "finalized")) {
+                    return Ok(signature);
+                }
+            }
+            thread::sleep(Duration::from_secs(1));
+        }
+        bail!("transaction {signature} was not confirmed in time")
+    }
+}
@@ -0,0 +1,33 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use solana_sdk::{account::Account, pubkey::Pubkey};
+
+use crate::rpc::RpcClient;
+
+/// Where the CLI reads accounts from: a live cluster or dumped account files.
+pub enum AccountSource {
+    Rpc(RpcClient),
+    Offline(BTreeMap<Pubkey, Account>),
+}
+
+impl AccountSource {
+    pub fn get_account(&self, key: &Pubkey) -> Result<Option<Account>> {
+        match self {
+            Self::Rpc(client) => client.get_account(key),
+            Self::Offline(accounts) => Ok(accounts.get(key).cloned()),
+        }
+    }
+
+    /// Accounts owned by `program` whose data starts with `prefix`.
+    pub fn get_program_accounts(&self, program: &Pubkey, prefix: &[u8]) -> Result<Vec<(Pubkey, Account)>> {
+        match self {
+            Self::Rpc(client) => client.get_program_accounts(program, prefix),
+            Self::Offline(accounts) => Ok(accounts
+                .iter()
+                .filter(|(_, account)| account.owner == *program && account.data.starts_with(prefix))
+                .map(|(key, account)| (*key, account.clone()))
+                .collect()),
+        }
+    }
+}
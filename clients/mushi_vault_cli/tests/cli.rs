@@ -0,0 +1,205 @@
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+use anchor_lang::{
+    prelude::*,
+    solana_program::{program_option::COption, program_pack::Pack},
+};
+use anchor_spl::{
+    token::{self, spl_token},
+    token_2022,
+};
+use mushi_stake_vault::state::{BASIS_POINTS_DIVISOR, INITIAL_VIRTUAL_SHARES};
+use mushi_stake_vault_client::{
+    pda, AccessMode, MainState, Pool, StakeEntry, ID,
+};
+use mushi_vault_cli::{
+    dump::{self, KeyedAccount, UiAccount},
+    inspect,
+    source::AccountSource,
+};
+use solana_sdk::account::Account;
+
+fn main_state(pool_id: u64) -> MainState {
+    MainState {
+        pool_id,
+        admin: Pubkey::new_unique(),
+        guardian: Pubkey::new_unique(),
+        mushi_token_amount: 1_000,
+        eclipse_token_amount: 500,
+        staking_token_total_supply: 900,
+        mushi_token_mint: Pubkey::new_unique(),
+        eclipse_token_mint: Pubkey::new_unique(),
+        stake_token_mint: Pubkey::new_unique(),
+        mushi_program: Pubkey::new_unique(),
+        access_mode: AccessMode::Open,
+        virtual_shares: INITIAL_VIRTUAL_SHARES,
+        pending_unstake_mushi_amount: 100,
+        eclipse_per_mushi_bps: BASIS_POINTS_DIVISOR,
+        ..Default::default()
+    }
+}
+
+fn stake_entry(user: Pubkey, stake_token_balance: u64) -> StakeEntry {
+    StakeEntry {
+        user,
+        mushi_token_staked_amount: stake_token_balance,
+        eclipse_token_staked_amount: stake_token_balance,
+        stake_token_balance,
+        ..Default::default()
+    }
+}
+
+fn program_account<T: AccountSerialize>(account: &T) -> Account {
+    let mut data = Vec::new();
+    account.try_serialize(&mut data).unwrap();
+    Account { lamports: 1, data, owner: ID, executable: false, rent_epoch: 0 }
+}
+
+fn token_account(mint: Pubkey, owner: Pubkey, amount: u64, token_program: Pubkey) -> Account {
+    let mut data = vec![0; spl_token::state::Account::LEN];
+    spl_token::state::Account {
+        mint,
+        owner,
+        amount,
+        state: spl_token::state::AccountState::Initialized,
+        ..Default::default()
+    }
+    .pack_into_slice(&mut data);
+    Account { lamports: 1, data, owner: token_program, executable: false, rent_epoch: 0 }
+}
+
+fn mint(supply: u64, token_program: Pubkey) -> Account {
+    let mut data = vec![0; spl_token::state::Mint::LEN];
+    spl_token::state::Mint {
+        mint_authority: COption::None,
+        supply,
+        decimals: 6,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    }
+    .pack_into_slice(&mut data);
+    Account { lamports: 1, data, owner: token_program, executable: false, rent_epoch: 0 }
+}
+
+/// A pool whose MUSHI vault is short by `mushi_shortfall` against its counters.
+fn pool_accounts(state: &MainState, mushi_token_program: Pubkey, mushi_shortfall: u64) -> BTreeMap<Pubkey, Account> {
+    let pool = Pool::new(state.clone(), mushi_token_program);
+    let owner = pool.token_vault_owner();
+    let mushi_token_vault_amount = state.mushi_token_amount + state.pending_unstake_mushi_amount - mushi_shortfall;
+    BTreeMap::from([
+        (pool.address, program_account(state)),
+        (state.mushi_token_mint, mint(0, mushi_token_program)),
+        (state.stake_token_mint, mint(state.staking_token_total_supply, mushi_token_program)),
+        (
+            pool.mushi_token_vault(),
+            token_account(state.mushi_token_mint, owner, mushi_token_vault_amount, mushi_token_program),
+        ),
+        (
+            pool.eclipse_token_vault(),
+            token_account(state.eclipse_token_mint, owner, state.eclipse_token_amount, token_2022::ID),
+        ),
+    ])
+}
+
+/// Writes `accounts` as `solana account --output json` dumps into a fresh directory.
+fn dump_dir(name: &str, accounts: &BTreeMap<Pubkey, Account>) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("mushi_vault_cli_{name}_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    for (key, account) in accounts {
+        let keyed = KeyedAccount { pubkey: key.to_string(), account: UiAccount::from_account(account) };
+        fs::write(dir.join(format!("{key}.json")), serde_json::to_string(&keyed).unwrap()).unwrap();
+    }
+    fs::write(dir.join("README"), "not an account dump").unwrap();
+    dir
+}
+
+#[test]
+fn dump_round_trips_single_files_and_lists() {
+    let state = main_state(0);
+    let accounts = pool_accounts(&state, token::ID, 0);
+    let dir = dump_dir("round_trip", &accounts);
+    assert_eq!(dump::load(&dir).unwrap(), accounts);
+
+    let list: Vec<_> = accounts
+        .iter()
+        .map(|(key, account)| KeyedAccount { pubkey: key.to_string(), account: UiAccount::from_account(account) })
+        .collect();
+    let file = dir.join("program_accounts.json");
+    fs::write(&file, serde_json::to_string(&list).unwrap()).unwrap();
+    assert_eq!(dump::load(&file).unwrap(), accounts);
+
+    fs::write(&file, r#"{"pubkey":"11111111111111111111111111111111","account":{"lamports":1,"data":["","base58"],"owner":"11111111111111111111111111111111","executable":false}}"#).unwrap();
+    assert!(dump::load(&file).is_err());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn main_state_report_decodes_offline_dump() {
+    let state = main_state(2);
+    let dir = dump_dir("main_state", &pool_accounts(&state, token_2022::ID, 40));
+    let source = AccountSource::Offline(dump::load(&dir).unwrap());
+    let report = inspect::main_state_report(&source, 2).unwrap();
+    fs::remove_dir_all(dir).unwrap();
+
+    let line = |field: &str| {
+        report
+            .lines()
+            .find(|line| line.starts_with(&format!("{field}:")))
+            .unwrap_or_else(|| panic!("{field} missing from report:\n{report}"))
+            .split_once(':')
+            .unwrap()
+            .1
+            .trim()
+            .to_string()
+    };
+    assert_eq!(line("main_state"), pda::main_state(2).0.to_string());
+    assert_eq!(line("admin"), state.admin.to_string());
+    assert_eq!(line("access_mode"), "Open");
+    assert_eq!(line("mushi_token_mint"), format!("{} ({})", state.mushi_token_mint, token_2022::ID));
+    assert!(line("mushi_token_vault").ends_with("= 1060"));
+    assert!(line("reward_token_vault").ends_with("= missing"));
+    assert_eq!(line("stake_token_supply"), "900");
+    assert_eq!(line("mushi_token_delta"), "-40");
+    assert_eq!(line("eclipse_token_delta"), "0");
+    assert_eq!(line("stake_token_supply_delta"), "0");
+}
+
+#[test]
+fn load_pool_falls_back_to_vault_owner_without_mint() {
+    for token_program in [token::ID, token_2022::ID] {
+        let state = main_state(1);
+        let mut accounts = pool_accounts(&state, token_program, 0);
+        accounts.remove(&state.mushi_token_mint);
+        let pool = inspect::load_pool(&AccountSource::Offline(accounts), 1).unwrap();
+        assert_eq!(pool.mushi_token_program, token_program);
+    }
+
+    let Err(error) = inspect::load_pool(&AccountSource::Offline(BTreeMap::new()), 1) else {
+        panic!("missing main state loaded");
+    };
+    assert!(error.to_string().contains("not found"));
+}
+
+#[test]
+fn stake_entries_are_filtered_by_pool_and_sorted_by_balance() {
+    let state = main_state(0);
+    let mut accounts = pool_accounts(&state, token::ID, 0);
+    let main_state = pda::main_state(0).0;
+    let (small, large) = (Pubkey::new_unique(), Pubkey::new_unique());
+    accounts.insert(pda::stake_entry(&main_state, &small).0, program_account(&stake_entry(small, 10)));
+    accounts.insert(pda::stake_entry(&main_state, &large).0, program_account(&stake_entry(large, 20)));
+    // An entry of another pool and one at a non-PDA address are left out.
+    let other = Pubkey::new_unique();
+    accounts.insert(pda::stake_entry(&pda::main_state(1).0, &other).0, program_account(&stake_entry(other, 30)));
+    accounts.insert(Pubkey::new_unique(), program_account(&stake_entry(other, 40)));
+
+    let source = AccountSource::Offline(accounts);
+    let entries = inspect::stake_entries(&source, 0).unwrap();
+    let users: Vec<_> = entries.iter().map(|(_, entry)| entry.user).collect();
+    assert_eq!(users, [large, small]);
+
+    let report = inspect::stake_entries_report(&source, 0).unwrap();
+    assert!(report.lines().nth(1).unwrap().starts_with(&large.to_string()));
+    assert!(report.ends_with("2 stake entries\n"));
+}
@@ -12,4 +12,52 @@ pub enum MushiStakeVaultError {
     UnauthorizedProgramCall,
     #[msg("Only admin can perform this action")]
     UnauthorizedAdminAction,
+    #[msg("Reward vault balance is insufficient to pay out rewards")]
+    InsufficientRewardVaultBalance,
+    #[msg("Invalid reward emission schedule")]
+    InvalidRewardSchedule,
+    #[msg("Pool has an unstake cooldown, use request_unstake instead")]
+    UnstakeCooldownActive,
+    #[msg("Unstaked tokens are still in their cooldown period")]
+    UnstakeStillLocked,
+    #[msg("No pending unstake request to withdraw")]
+    NoPendingUnstake,
+    #[msg("Unstake cooldown must not be negative")]
+    InvalidUnstakeCooldown,
+    #[msg("Invalid early-unstake penalty configuration")]
+    InvalidPenaltyConfig,
+    #[msg("Missing or invalid treasury token account")]
+    InvalidTreasuryAccount,
+    #[msg("User is not on the pool allowlist")]
+    NotAllowlisted,
+    #[msg("Only the pending admin can accept the admin transfer")]
+    UnauthorizedPendingAdmin,
+    #[msg("No admin transfer is pending")]
+    NoPendingAdminTransfer,
+    #[msg("Invalid new admin")]
+    InvalidNewAdmin,
+    #[msg("This instruction is paused")]
+    ProgramPaused,
+    #[msg("Only admin or guardian can pause the program")]
+    UnauthorizedPauseAuthority,
+    #[msg("Unknown pause flags")]
+    InvalidPauseFlags,
+    #[msg("Eclipse token account required by the pool's deposit mode is missing")]
+    MissingEclipseTokenAccount,
+    #[msg("Eclipse amount does not match the pool's MUSHI/Eclipse ratio")]
+    InvalidDepositRatio,
+    #[msg("Unstake returns less than the requested minimum")]
+    UnstakeSlippageExceeded,
+    #[msg("Token-2022 mint has an unsupported extension")]
+    UnsupportedMintExtension,
+    #[msg("Token-2022 transfer fee could not be computed")]
+    InvalidTransferFee,
+    #[msg("Arithmetic overflow")]
+    MathOverflow,
+    #[msg("No return data from the stake vault program")]
+    MissingReturnData,
+    #[msg("Eclipse account differs from the one the position's Eclipse was staked from")]
+    EclipseSourceMismatch,
+    #[msg("Vaults or stake mint hold less than the pool's counters")]
+    ReconcileShortfall,
 }
\ No newline at end of file
@@ -0,0 +1,157 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{AccessMode, EclipseDepositMode, PenaltyCurve, PenaltyDestination, ReconcileMode};
+
+#[event]
+pub struct PoolInitialized {
+    pub main_state: Pubkey,
+    pub admin: Pubkey,
+    pub mushi_token_mint: Pubkey,
+    pub eclipse_token_mint: Pubkey,
+    pub stake_token_mint: Pubkey,
+    pub mushi_program: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PoolUpdated {
+    pub main_state: Pubkey,
+    pub admin: Pubkey,
+    pub mushi_program: Pubkey,
+    pub access_mode: AccessMode,
+    pub guardian: Pubkey,
+    pub unstake_cooldown: i64,
+    pub early_unstake_penalty_bps: u16,
+    pub penalty_decay_period: i64,
+    pub penalty_curve: PenaltyCurve,
+    pub penalty_destination: PenaltyDestination,
+    pub treasury: Pubkey,
+    pub eclipse_deposit_mode: EclipseDepositMode,
+    pub eclipse_per_mushi_bps: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct Staked {
+    pub main_state: Pubkey,
+    pub user: Pubkey,
+    pub mushi_token_amount: u64,
+    pub eclipse_token_amount: u64,
+    pub stake_token_amount: u64,
+    pub total_mushi_token_amount: u64,
+    pub total_eclipse_token_amount: u64,
+    pub staking_token_total_supply: u64,
+    pub timestamp: i64,
+}
+
+/// Emitted by `unstake`, and by `request_unstake` with `unlock_time` set.
+#[event]
+pub struct Unstaked {
+    pub main_state: Pubkey,
+    pub user: Pubkey,
+    pub mushi_token_amount: u64,
+    pub eclipse_token_amount: u64,
+    pub stake_token_amount: u64,
+    pub penalty_amount: u64,
+    pub unlock_time: Option<i64>,
+    pub total_mushi_token_amount: u64,
+    pub total_eclipse_token_amount: u64,
+    pub staking_token_total_supply: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct UnstakeWithdrawn {
+    pub main_state: Pubkey,
+    pub user: Pubkey,
+    pub mushi_token_amount: u64,
+    pub eclipse_token_amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RewardsFunded {
+    pub main_state: Pubkey,
+    pub admin: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RewardRateSet {
+    pub main_state: Pubkey,
+    pub admin: Pubkey,
+    pub reward_rate: u64,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RewardsClaimed {
+    pub main_state: Pubkey,
+    pub user: Pubkey,
+    pub rewards_amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AdminTransferProposed {
+    pub main_state: Pubkey,
+    pub admin: Pubkey,
+    pub pending_admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AdminTransferAccepted {
+    pub main_state: Pubkey,
+    pub previous_admin: Pubkey,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AdminTransferCancelled {
+    pub main_state: Pubkey,
+    pub admin: Pubkey,
+    pub cancelled_admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PauseUpdated {
+    pub main_state: Pubkey,
+    pub authority: Pubkey,
+    pub paused: u8,
+    pub timestamp: i64,
+}
+
+/// Emitted by `audit_pool`, and by `reconcile_pool` before it corrects anything.
+/// Deltas are actual minus expected.
+#[event]
+#[derive(Clone)]
+pub struct PoolAudited {
+    pub main_state: Pubkey,
+    pub mushi_token_vault_amount: u64,
+    pub expected_mushi_token_amount: u128,
+    pub mushi_token_delta: i128,
+    pub eclipse_token_vault_amount: u64,
+    pub expected_eclipse_token_amount: u128,
+    pub eclipse_token_delta: i128,
+    pub stake_token_supply: u64,
+    pub staking_token_total_supply: u64,
+    pub stake_token_supply_delta: i128,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PoolReconciled {
+    pub main_state: Pubkey,
+    pub mode: ReconcileMode,
+    pub donated_mushi_token_amount: u64,
+    pub mushi_token_amount: u64,
+    pub eclipse_token_amount: u64,
+    pub staking_token_total_supply: u64,
+    pub timestamp: i64,
+}
@@ -0,0 +1,36 @@
+use anchor_lang::prelude::*;
+use crate::{state::{MainState, PAUSE_ADMIN}, errors::MushiStakeVaultError, events::AdminTransferAccepted};
+
+pub fn handler(ctx: Context<AcceptAdmin>) -> Result<()> {
+    ctx.accounts.main_state.require_not_paused(PAUSE_ADMIN)?;
+
+    let main_state = &mut ctx.accounts.main_state;
+    let previous_admin = main_state.admin;
+    main_state.admin = main_state.pending_admin;
+    main_state.pending_admin = Pubkey::default();
+
+    emit!(AdminTransferAccepted {
+        main_state: main_state.key(),
+        previous_admin,
+        admin: main_state.admin,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct AcceptAdmin<'info> {
+    #[account(
+        constraint = main_state.pending_admin != Pubkey::default() @ MushiStakeVaultError::NoPendingAdminTransfer,
+        address = main_state.pending_admin @ MushiStakeVaultError::UnauthorizedPendingAdmin,
+        mut
+    )]
+    pub pending_admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [MainState::PREFIX_SEED, main_state.pool_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub main_state: Box<Account<'info, MainState>>,
+    pub system_program: Program<'info, System>,
+}
@@ -0,0 +1,34 @@
+use anchor_lang::prelude::*;
+use crate::{state::{AllowlistEntry, MainState, ALLOWLIST_SEED, PAUSE_ADMIN}, errors::MushiStakeVaultError};
+
+pub fn handler(ctx: Context<AddToAllowlist>) -> Result<()> {
+    ctx.accounts.main_state.require_not_paused(PAUSE_ADMIN)?;
+
+    ctx.accounts.allowlist_entry.user = ctx.accounts.user.key();
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct AddToAllowlist<'info> {
+    #[account(
+        address = main_state.admin @ MushiStakeVaultError::UnauthorizedAdminAction,
+        mut
+    )]
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [MainState::PREFIX_SEED, main_state.pool_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub main_state: Box<Account<'info, MainState>>,
+    /// CHECK: wallet being allowlisted, only used as a seed
+    pub user: AccountInfo<'info>,
+    #[account(
+        init,
+        payer = admin,
+        seeds = [ALLOWLIST_SEED, main_state.key().as_ref(), user.key().as_ref()],
+        bump,
+        space = 8 + AllowlistEntry::MAX_SIZE,
+    )]
+    pub allowlist_entry: Box<Account<'info, AllowlistEntry>>,
+    pub system_program: Program<'info, System>,
+}
@@ -0,0 +1,93 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::{get_associated_token_address_with_program_id, AssociatedToken},
+    token_interface,
+};
+use crate::{events::PoolAudited, state::{MainState, VAULT_OWNER_SEED}};
+
+/// Compares the `MainState` counters against what the vaults and the stake
+/// mint actually hold. Pending unstakes stay in the vaults until withdrawn,
+/// so they count towards the expected balances.
+pub fn pool_audit(
+    main_state: &MainState,
+    main_state_key: Pubkey,
+    mushi_token_vault_amount: u64,
+    eclipse_token_vault_amount: u64,
+    stake_token_supply: u64,
+    timestamp: i64,
+) -> PoolAudited {
+    let expected_mushi_token_amount = main_state.mushi_token_amount as u128 + main_state.pending_unstake_mushi_amount as u128;
+    let expected_eclipse_token_amount = main_state.eclipse_token_amount as u128 + main_state.pending_unstake_eclipse_amount as u128;
+    PoolAudited {
+        main_state: main_state_key,
+        mushi_token_vault_amount,
+        expected_mushi_token_amount,
+        mushi_token_delta: mushi_token_vault_amount as i128 - expected_mushi_token_amount as i128,
+        eclipse_token_vault_amount,
+        expected_eclipse_token_amount,
+        eclipse_token_delta: eclipse_token_vault_amount as i128 - expected_eclipse_token_amount as i128,
+        stake_token_supply,
+        staking_token_total_supply: main_state.staking_token_total_supply,
+        stake_token_supply_delta: stake_token_supply as i128 - main_state.staking_token_total_supply as i128,
+        timestamp,
+    }
+}
+
+pub fn handler(ctx: Context<AuditPool>) -> Result<()> {
+    emit!(pool_audit(
+        &ctx.accounts.main_state,
+        ctx.accounts.main_state.key(),
+        ctx.accounts.mushi_token_vault.amount,
+        ctx.accounts.eclipse_token_vault.amount,
+        ctx.accounts.stake_token_mint.supply,
+        Clock::get()?.unix_timestamp,
+    ));
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct AuditPool<'info> {
+    #[account(
+        seeds = [MainState::PREFIX_SEED, main_state.pool_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub main_state: Box<Account<'info, MainState>>,
+    #[account(
+        token::mint = mushi_token_mint,
+        token::authority = token_vault_owner,
+        token::token_program = token_program,
+        address = get_associated_token_address_with_program_id(&token_vault_owner.key(), &mushi_token_mint.key(), &token_program.key()),
+    )]
+    pub mushi_token_vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    #[account(
+        mint::token_program = token_program,
+        address = main_state.mushi_token_mint,
+    )]
+    pub mushi_token_mint: InterfaceAccount<'info, token_interface::Mint>,
+    #[account(
+        token::mint = eclipse_token_mint,
+        token::authority = token_vault_owner,
+        token::token_program = token2022_program,
+        address = get_associated_token_address_with_program_id(&token_vault_owner.key(), &eclipse_token_mint.key(), &token2022_program.key()),
+    )]
+    pub eclipse_token_vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    #[account(
+        mint::token_program = token2022_program,
+        address = main_state.eclipse_token_mint,
+    )]
+    pub eclipse_token_mint: InterfaceAccount<'info, token_interface::Mint>,
+    #[account(
+        mint::token_program = token_program,
+        address = main_state.stake_token_mint,
+    )]
+    pub stake_token_mint: InterfaceAccount<'info, token_interface::Mint>,
+    #[account(
+        seeds = [VAULT_OWNER_SEED, main_state.key().as_ref()],
+        bump,
+    )]
+    pub token_vault_owner: SystemAccount<'info>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, token_interface::TokenInterface>,
+    pub token2022_program: Interface<'info, token_interface::TokenInterface>,
+}
@@ -0,0 +1,35 @@
+use anchor_lang::prelude::*;
+use crate::{state::{MainState, PAUSE_ADMIN}, errors::MushiStakeVaultError, events::AdminTransferCancelled};
+
+pub fn handler(ctx: Context<CancelAdminTransfer>) -> Result<()> {
+    ctx.accounts.main_state.require_not_paused(PAUSE_ADMIN)?;
+
+    let main_state = &mut ctx.accounts.main_state;
+    require!(main_state.pending_admin != Pubkey::default(), MushiStakeVaultError::NoPendingAdminTransfer);
+    let cancelled_admin = main_state.pending_admin;
+    main_state.pending_admin = Pubkey::default();
+
+    emit!(AdminTransferCancelled {
+        main_state: main_state.key(),
+        admin: main_state.admin,
+        cancelled_admin,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CancelAdminTransfer<'info> {
+    #[account(
+        address = main_state.admin @ MushiStakeVaultError::UnauthorizedAdminAction,
+        mut
+    )]
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [MainState::PREFIX_SEED, main_state.pool_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub main_state: Box<Account<'info, MainState>>,
+    pub system_program: Program<'info, System>,
+}
@@ -0,0 +1,93 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface;
+use crate::{
+    errors::MushiStakeVaultError,
+    events::RewardsClaimed, state::{MainState, StakeEntry, PAUSE_CLAIM, REWARD_VAULT_SEED, STAKE_ENTRY_SEED, VAULT_OWNER_SEED}, utils::{transfer_tokens, TransferTokenInput}
+};
+
+pub fn handler(ctx: Context<ClaimRewards>) -> Result<()> {
+    ctx.accounts.main_state.require_not_paused(PAUSE_CLAIM)?;
+
+    let now = Clock::get()?.unix_timestamp;
+    let main_state = &mut ctx.accounts.main_state;
+    main_state.update_rewards(now);
+
+    let stake_entry = &mut ctx.accounts.stake_entry;
+    stake_entry.settle_rewards(main_state.acc_reward_per_share);
+    let rewards_amount = stake_entry.rewards_amount;
+
+    // Pay everything or nothing: a partial payout would silently lose the remainder.
+    require!(rewards_amount <= ctx.accounts.reward_token_vault.amount, MushiStakeVaultError::InsufficientRewardVaultBalance);
+
+    let main_state_key = ctx.accounts.main_state.key();
+    let bump = *ctx.bumps.get("token_vault_owner").unwrap();
+    let signer_seeds: &[&[&[u8]]] = &[&[VAULT_OWNER_SEED, main_state_key.as_ref(), &[bump]]];
+
+    transfer_tokens(
+        TransferTokenInput {
+            from: ctx.accounts.reward_token_vault.to_account_info(),
+            to: ctx.accounts.user_mushi_token_ata.to_account_info(),
+            authority: ctx.accounts.token_vault_owner.to_account_info(),
+            mint: ctx.accounts.mushi_token_mint.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+            amount: rewards_amount,
+            decimals: ctx.accounts.mushi_token_mint.decimals,
+        },
+        Some(signer_seeds),
+    )?;
+
+    ctx.accounts.stake_entry.rewards_amount = 0;
+
+    emit!(RewardsClaimed {
+        main_state: ctx.accounts.main_state.key(),
+        user: ctx.accounts.user.key(),
+        rewards_amount,
+        timestamp: now,
+    });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ClaimRewards<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [MainState::PREFIX_SEED, main_state.pool_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub main_state: Box<Account<'info, MainState>>,
+    #[account(
+        mut,
+        seeds = [STAKE_ENTRY_SEED, main_state.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub stake_entry: Box<Account<'info, StakeEntry>>,
+    #[account(
+        mut,
+        token::mint = mushi_token_mint,
+        token::authority = user,
+        token::token_program = token_program,
+    )]
+    pub user_mushi_token_ata: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [REWARD_VAULT_SEED, main_state.key().as_ref()],
+        bump,
+        token::mint = mushi_token_mint,
+        token::authority = token_vault_owner,
+        token::token_program = token_program,
+    )]
+    pub reward_token_vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    #[account(
+        mint::token_program = token_program,
+        address = main_state.mushi_token_mint,
+    )]
+    pub mushi_token_mint: InterfaceAccount<'info, token_interface::Mint>,
+    #[account(
+        seeds = [VAULT_OWNER_SEED, main_state.key().as_ref()],
+        bump,
+    )]
+    pub token_vault_owner: SystemAccount<'info>,
+    pub token_program: Interface<'info, token_interface::TokenInterface>,
+}
@@ -0,0 +1,78 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface;
+use crate::{
+    errors::MushiStakeVaultError, events::RewardsFunded, state::{MainState, PAUSE_ADMIN, REWARD_VAULT_SEED, VAULT_OWNER_SEED}, utils::{transfer_tokens, TransferTokenInput}
+};
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone)]
+pub struct FundRewardsInput {
+    pub amount: u64,
+}
+
+pub fn handler(ctx: Context<FundRewards>, input: FundRewardsInput) -> Result<()> {
+    ctx.accounts.main_state.require_not_paused(PAUSE_ADMIN)?;
+
+    require!(input.amount <= ctx.accounts.admin_mushi_token_ata.amount, MushiStakeVaultError::InsufficientMushiTokenAmount);
+
+    transfer_tokens(
+        TransferTokenInput {
+            from: ctx.accounts.admin_mushi_token_ata.to_account_info(),
+            to: ctx.accounts.reward_token_vault.to_account_info(),
+            authority: ctx.accounts.admin.to_account_info(),
+            mint: ctx.accounts.mushi_token_mint.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+            amount: input.amount,
+            decimals: ctx.accounts.mushi_token_mint.decimals,
+        },
+        None,
+    )?;
+
+    emit!(RewardsFunded {
+        main_state: ctx.accounts.main_state.key(),
+        admin: ctx.accounts.admin.key(),
+        amount: input.amount,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct FundRewards<'info> {
+    #[account(
+        address = main_state.admin @ MushiStakeVaultError::UnauthorizedAdminAction,
+        mut
+    )]
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [MainState::PREFIX_SEED, main_state.pool_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub main_state: Box<Account<'info, MainState>>,
+    #[account(
+        mut,
+        token::mint = mushi_token_mint,
+        token::authority = admin,
+        token::token_program = token_program,
+    )]
+    pub admin_mushi_token_ata: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [REWARD_VAULT_SEED, main_state.key().as_ref()],
+        bump,
+        token::mint = mushi_token_mint,
+        token::authority = token_vault_owner,
+        token::token_program = token_program,
+    )]
+    pub reward_token_vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    #[account(
+        mint::token_program = token_program,
+        address = main_state.mushi_token_mint,
+    )]
+    pub mushi_token_mint: InterfaceAccount<'info, token_interface::Mint>,
+    #[account(
+        seeds = [VAULT_OWNER_SEED, main_state.key().as_ref()],
+        bump,
+    )]
+    pub token_vault_owner: SystemAccount<'info>,
+    pub token_program: Interface<'info, token_interface::TokenInterface>,
+}
@@ -0,0 +1,60 @@
+use anchor_lang::prelude::*;
+use crate::state::{AccessMode, MainState, EXCHANGE_RATE_PRECISION};
+
+/// Pool totals returned by `get_pool_stats`, with rewards accrued up to `timestamp`.
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PoolStats {
+    pub main_state: Pubkey,
+    pub pool_id: u64,
+    pub paused: u8,
+    pub access_mode: AccessMode,
+    pub mushi_token_amount: u64,
+    pub eclipse_token_amount: u64,
+    pub staking_token_total_supply: u64,
+    pub virtual_shares: u64,
+    pub pending_unstake_mushi_amount: u64,
+    pub pending_unstake_eclipse_amount: u64,
+    /// MUSHI redeemed for `EXCHANGE_RATE_PRECISION` stake tokens, rounded down.
+    pub exchange_rate: u64,
+    pub reward_rate: u64,
+    pub reward_end_time: i64,
+    pub acc_reward_per_share: u128,
+    pub timestamp: i64,
+}
+
+/// Builds `PoolStats` from a copy of `main_state` brought up to `now`; the
+/// account itself is left untouched.
+pub fn pool_stats(main_state: &MainState, main_state_key: Pubkey, now: i64) -> Result<PoolStats> {
+    let mut main_state = main_state.clone();
+    main_state.update_rewards(now);
+    Ok(PoolStats {
+        main_state: main_state_key,
+        pool_id: main_state.pool_id,
+        paused: main_state.paused,
+        access_mode: main_state.access_mode,
+        mushi_token_amount: main_state.mushi_token_amount,
+        eclipse_token_amount: main_state.eclipse_token_amount,
+        staking_token_total_supply: main_state.staking_token_total_supply,
+        virtual_shares: main_state.virtual_shares,
+        pending_unstake_mushi_amount: main_state.pending_unstake_mushi_amount,
+        pending_unstake_eclipse_amount: main_state.pending_unstake_eclipse_amount,
+        exchange_rate: main_state.mushi_tokens_for_stake_tokens(EXCHANGE_RATE_PRECISION)?,
+        reward_rate: main_state.reward_rate,
+        reward_end_time: main_state.reward_end_time,
+        acc_reward_per_share: main_state.acc_reward_per_share,
+        timestamp: now,
+    })
+}
+
+pub fn handler(ctx: Context<GetPoolStats>) -> Result<PoolStats> {
+    pool_stats(&ctx.accounts.main_state, ctx.accounts.main_state.key(), Clock::get()?.unix_timestamp)
+}
+
+#[derive(Accounts)]
+pub struct GetPoolStats<'info> {
+    #[account(
+        seeds = [MainState::PREFIX_SEED, main_state.pool_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub main_state: Box<Account<'info, MainState>>,
+}
@@ -0,0 +1,91 @@
+use anchor_lang::prelude::*;
+use crate::state::{MainState, StakeEntry, STAKE_ENTRY_SEED};
+
+/// A user's stake returned by `get_user_position`, valued at `timestamp`.
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct UserPosition {
+    pub main_state: Pubkey,
+    pub user: Pubkey,
+    pub stake_token_balance: u64,
+    /// MUSHI the balance redeems for at the current exchange rate, before any
+    /// early-unstake penalty.
+    pub mushi_token_value: u64,
+    pub mushi_token_staked_amount: u64,
+    pub eclipse_token_staked_amount: u64,
+    /// Claimable rewards, including those accrued since the last settlement.
+    pub pending_rewards: u64,
+    pub last_staked: i64,
+    pub timestamp: i64,
+}
+
+/// Builds `UserPosition` from copies of the pool and of the user's entry;
+/// `stake_entry` is `None` when the user never staked.
+pub fn user_position(
+    main_state: &MainState,
+    main_state_key: Pubkey,
+    user: Pubkey,
+    stake_entry: Option<&StakeEntry>,
+    now: i64,
+) -> Result<UserPosition> {
+    let Some(stake_entry) = stake_entry else {
+        return Ok(UserPosition {
+            main_state: main_state_key,
+            user,
+            stake_token_balance: 0,
+            mushi_token_value: 0,
+            mushi_token_staked_amount: 0,
+            eclipse_token_staked_amount: 0,
+            pending_rewards: 0,
+            last_staked: 0,
+            timestamp: now,
+        });
+    };
+    let mut main_state = main_state.clone();
+    main_state.update_rewards(now);
+    let mut stake_entry = stake_entry.clone();
+    stake_entry.settle_rewards(main_state.acc_reward_per_share);
+    Ok(UserPosition {
+        main_state: main_state_key,
+        user,
+        stake_token_balance: stake_entry.stake_token_balance,
+        mushi_token_value: main_state.mushi_tokens_for_stake_tokens(stake_entry.stake_token_balance)?,
+        mushi_token_staked_amount: stake_entry.mushi_token_staked_amount,
+        eclipse_token_staked_amount: stake_entry.eclipse_token_staked_amount,
+        pending_rewards: stake_entry.rewards_amount,
+        last_staked: stake_entry.last_staked,
+        timestamp: now,
+    })
+}
+
+pub fn handler(ctx: Context<GetUserPosition>) -> Result<UserPosition> {
+    let stake_entry_info = ctx.accounts.stake_entry.to_account_info();
+    let stake_entry = if stake_entry_info.owner == &crate::ID && !stake_entry_info.data_is_empty() {
+        Some(Account::<StakeEntry>::try_from(&stake_entry_info)?.into_inner())
+    } else {
+        None
+    };
+    user_position(
+        &ctx.accounts.main_state,
+        ctx.accounts.main_state.key(),
+        ctx.accounts.user.key(),
+        stake_entry.as_ref(),
+        Clock::get()?.unix_timestamp,
+    )
+}
+
+#[derive(Accounts)]
+pub struct GetUserPosition<'info> {
+    /// CHECK: only used to derive `stake_entry`; need not sign.
+    pub user: UncheckedAccount<'info>,
+    #[account(
+        seeds = [MainState::PREFIX_SEED, main_state.pool_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub main_state: Box<Account<'info, MainState>>,
+    /// CHECK: the user's `StakeEntry` PDA, which may not exist yet; decoded in the handler.
+    #[account(
+        seeds = [STAKE_ENTRY_SEED, main_state.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub stake_entry: UncheckedAccount<'info>,
+}
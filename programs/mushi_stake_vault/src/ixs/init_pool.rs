@@ -2,11 +2,19 @@ use {
     anchor_lang::prelude::*,
 };
 
-use anchor_lang::solana_program::program::invoke_signed;
+use anchor_lang::{
+    solana_program::program::{invoke, invoke_signed},
+    system_program::{self, CreateAccount},
+};
 use anchor_spl::{
-    associated_token::AssociatedToken,
+    associated_token::{self, get_associated_token_address_with_program_id, AssociatedToken},
     token_interface,
 };
+use spl_token_2022::{
+    extension::{metadata_pointer, ExtensionType},
+    instruction::initialize_mint2,
+    state::Mint,
+};
 
 use mpl_token_metadata::{
     instructions::{CreateMetadataAccountV3, CreateMetadataAccountV3InstructionArgs},
@@ -14,11 +22,15 @@ use mpl_token_metadata::{
 };
 
 use crate::{
-    state::{MainState, VAULT_OWNER_SEED}, utils::{burn_tokens, mint_to_tokens_by_main_state}
+    events::PoolInitialized,
+    state::{AccessMode, EclipseDepositMode, MainState, PenaltyCurve, PenaltyDestination, BASIS_POINTS_DIVISOR, INITIAL_VIRTUAL_SHARES, REWARD_VAULT_SEED, VAULT_OWNER_SEED},
+    utils::verify_supported_mint_extensions,
 };
 
 #[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone)]
 pub struct InitPoolInput {
+    /// Distinguishes pools hosted by the same deployment; part of the `MainState` seeds.
+    pub pool_id: u64,
     pub stake_token_name: String,
     pub stake_token_symbol: String,
     pub stake_token_uri: String,
@@ -29,36 +41,50 @@ pub fn init_pool(
     ctx: Context<InitializeStakePool>,
     input: InitPoolInput,
 ) -> Result<()> {
+    verify_supported_mint_extensions(&ctx.accounts.mushi_token_mint.to_account_info())?;
+    verify_supported_mint_extensions(&ctx.accounts.eclipse_token_mint.to_account_info())?;
+
+    create_stake_token_mint(ctx.accounts, ctx.accounts.main_state.key())?;
+
     let main_state = &mut ctx.accounts.main_state;
+    main_state.pool_id = input.pool_id;
     main_state.admin = ctx.accounts.admin.key();
+    main_state.pending_admin = Pubkey::default();
+    main_state.guardian = ctx.accounts.admin.key();
+    main_state.paused = 0;
     main_state.mushi_token_mint = ctx.accounts.mushi_token_mint.key();
     main_state.eclipse_token_mint = ctx.accounts.eclipse_token_mint.key();
     main_state.stake_token_mint = ctx.accounts.stake_token_mint.key();
     main_state.mushi_token_amount = 0;
     main_state.eclipse_token_amount = 0;
     main_state.mushi_program = input.mushi_program;
+    main_state.access_mode = AccessMode::ProgramGated;
+    main_state.staking_token_total_supply = 0;
+    main_state.virtual_shares = INITIAL_VIRTUAL_SHARES;
+    main_state.unstake_cooldown = 0;
+    main_state.early_unstake_penalty_bps = 0;
+    main_state.penalty_decay_period = 0;
+    main_state.penalty_curve = PenaltyCurve::Linear;
+    main_state.penalty_destination = PenaltyDestination::Vault;
+    main_state.treasury = Pubkey::default();
+    main_state.eclipse_deposit_mode = EclipseDepositMode::Required;
+    main_state.eclipse_per_mushi_bps = BASIS_POINTS_DIVISOR;
+    main_state.reward_rate = 0;
+    main_state.reward_start_time = 0;
+    main_state.reward_end_time = 0;
+    main_state.last_reward_update = Clock::get()?.unix_timestamp;
 
-    let stake_token_vault = &mut ctx.accounts.stake_token_vault;
-    let stake_token_program = &ctx.accounts.token_program;
-    //mint stake tokens
-    mint_to_tokens_by_main_state(
-        ctx.accounts.stake_token_mint.to_account_info(),
-        main_state.to_account_info(),
-        stake_token_vault.to_account_info(),
-        stake_token_program.to_account_info(),
-        1_000_000_000,
-        *ctx.bumps.get("main_state").unwrap(),
-    )?;
-
-    //burn tokens
-    burn_tokens(
-        ctx.accounts.stake_token_vault.to_account_info(),
-        ctx.accounts.stake_token_mint.to_account_info(),
-        ctx.accounts.token_vault_owner.to_account_info(),
-        stake_token_program.to_account_info(),
-        1_000_000_000,
-        Some(&[&[VAULT_OWNER_SEED, &[*ctx.bumps.get("token_vault_owner").unwrap()]]]),
-    )?;
+    associated_token::create(CpiContext::new(
+        ctx.accounts.associated_token_program.to_account_info(),
+        associated_token::Create {
+            payer: ctx.accounts.admin.to_account_info(),
+            associated_token: ctx.accounts.stake_token_vault.to_account_info(),
+            authority: ctx.accounts.token_vault_owner.to_account_info(),
+            mint: ctx.accounts.stake_token_mint.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+        },
+    ))?;
 
     // set token metadata
     let set_metadata_ix = CreateMetadataAccountV3 {
@@ -101,13 +127,67 @@ pub fn init_pool(
         ],
         &[&[
             MainState::PREFIX_SEED,
+            &main_state.pool_id.to_le_bytes(),
             &[*ctx.bumps.get("main_state").unwrap()],
         ]],
     )?;
+
+    emit!(PoolInitialized {
+        main_state: main_state.key(),
+        admin: main_state.admin,
+        mushi_token_mint: main_state.mushi_token_mint,
+        eclipse_token_mint: main_state.eclipse_token_mint,
+        stake_token_mint: main_state.stake_token_mint,
+        mushi_program: main_state.mushi_program,
+        timestamp: main_state.last_reward_update,
+    });
+    Ok(())
+}
+
+/// Creates the stake mint with `main_state` as mint and freeze authority. Under
+/// Token-2022 the mint also gets a MetadataPointer to its Metaplex metadata
+/// account, which must be initialized before the mint itself.
+fn create_stake_token_mint(accounts: &InitializeStakePool, main_state: Pubkey) -> Result<()> {
+    let token_program = accounts.token_program.key();
+    let mint = accounts.stake_token_mint.key();
+    let is_token_2022 = token_program == spl_token_2022::ID;
+    let extensions: &[ExtensionType] = if is_token_2022 { &[ExtensionType::MetadataPointer] } else { &[] };
+    let space = ExtensionType::try_calculate_account_len::<Mint>(extensions)?;
+
+    system_program::create_account(
+        CpiContext::new(
+            accounts.system_program.to_account_info(),
+            CreateAccount {
+                from: accounts.admin.to_account_info(),
+                to: accounts.stake_token_mint.to_account_info(),
+            },
+        ),
+        accounts.rent.minimum_balance(space),
+        space as u64,
+        &token_program,
+    )?;
+
+    let mint_infos = [accounts.stake_token_mint.to_account_info(), accounts.token_program.to_account_info()];
+    if is_token_2022 {
+        invoke(
+            &metadata_pointer::instruction::initialize(
+                &token_program,
+                &mint,
+                Some(main_state),
+                Some(accounts.stake_token_metadata_account.key()),
+            )?,
+            &mint_infos,
+        )?;
+    }
+    invoke(
+        &initialize_mint2(&token_program, &mint, &main_state, Some(&main_state), 9)?,
+        &mint_infos,
+    )?;
     Ok(())
 }
 
 #[derive(Accounts)]
+#[instruction(input: InitPoolInput)]
 pub struct InitializeStakePool<'info> {
     #[account(mut)]
     pub admin: Signer<'info>,
@@ -116,13 +196,14 @@ pub struct InitializeStakePool<'info> {
     #[account(
         init,
         payer = admin,
-        seeds = [MainState::PREFIX_SEED],
+        seeds = [MainState::PREFIX_SEED, input.pool_id.to_le_bytes().as_ref()],
         bump,
         space =  8 + MainState::MAX_SIZE,
     )]
     pub main_state: Account<'info, MainState>,
     #[account(
         mut,
+        mint::token_program = token_program,
     )]
     pub mushi_token_mint: InterfaceAccount<'info, token_interface::Mint>,
     #[account(
@@ -131,16 +212,14 @@ pub struct InitializeStakePool<'info> {
     )]
     pub eclipse_token_mint: InterfaceAccount<'info, token_interface::Mint>,
 
-    #[account(
-        init,
-        payer = admin,
-        signer,
-        mint::decimals = 9,
-        mint::authority = main_state,
-        mint::freeze_authority=main_state,
-        mint::token_program = token_program,
-    )]
-    pub stake_token_mint: Box<InterfaceAccount<'info, token_interface::Mint>>,
+    // Created by the handler under the MUSHI mint's token program, so a
+    // Token-2022 MUSHI pool gets a Token-2022 stake mint. Anchor's `mint::`
+    // constraints cannot add extensions, and a Token-2022 stake mint carries a
+    // MetadataPointer to its Metaplex metadata account. `main_state` keeps the
+    // freeze authority to hold stake token accounts frozen, which makes stake
+    // tokens non-transferable; see `StakeTokenAccount`.
+    #[account(mut)]
+    pub stake_token_mint: Signer<'info>,
 
     ///CHECK:
     #[account(
@@ -153,7 +232,7 @@ pub struct InitializeStakePool<'info> {
 
     #[account(
         mut,
-        seeds = [VAULT_OWNER_SEED],
+        seeds = [VAULT_OWNER_SEED, main_state.key().as_ref()],
         bump,
     )]
     pub token_vault_owner: SystemAccount<'info>,
@@ -175,18 +254,32 @@ pub struct InitializeStakePool<'info> {
     )]
     pub eclipse_token_vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
 
+    /// CHECK: created by the handler once the stake mint exists.
+    #[account(
+        mut,
+        address = get_associated_token_address_with_program_id(
+            &token_vault_owner.key(),
+            &stake_token_mint.key(),
+            &token_program.key(),
+        ),
+    )]
+    pub stake_token_vault: UncheckedAccount<'info>,
+
     #[account(
         init,
         payer = admin,
-        associated_token::mint = stake_token_mint,
-        associated_token::authority = token_vault_owner,
-        associated_token::token_program = token_program,
+        seeds = [REWARD_VAULT_SEED, main_state.key().as_ref()],
+        bump,
+        token::mint = mushi_token_mint,
+        token::authority = token_vault_owner,
+        token::token_program = token_program,
     )]
-    pub stake_token_vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    pub reward_token_vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
 
     pub token_program: Interface<'info, token_interface::TokenInterface>,
     pub token2022_program: Interface<'info, token_interface::TokenInterface>,
     ///CHECK:
+    #[account(address = mpl_token_metadata::ID)]
     pub mpl_program: AccountInfo<'info>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
@@ -1,8 +1,22 @@
+pub mod accept_admin;
+pub mod add_to_allowlist;
+pub mod audit_pool;
+pub mod cancel_admin_transfer;
+pub mod claim_rewards;
+pub mod fund_rewards;
+pub mod get_pool_stats;
+pub mod get_user_position;
 pub mod init_pool;
+pub mod preview_stake;
+pub mod preview_unstake;
+pub mod propose_admin;
+pub mod reconcile_pool;
+pub mod remove_from_allowlist;
+pub mod request_unstake;
+pub mod set_pause;
+pub mod set_reward_rate;
 pub mod stake;
 pub mod unstake;
 pub mod update_pool;
+pub mod withdraw_unstaked;
 pub use init_pool::*;
-pub use stake::*;
-pub use unstake::*;
-pub use update_pool::*;
\ No newline at end of file
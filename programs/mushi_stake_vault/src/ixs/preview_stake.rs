@@ -0,0 +1,60 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface;
+use crate::{
+    ixs::stake::{quote_stake_with_mints, StakeInput, StakeResult},
+    state::{AllowlistEntry, EclipseDepositMode, MainState, ALLOWLIST_SEED, MUSHIPROGRAM_VAULT_SEED, PAUSE_STAKE},
+    utils::verify_access,
+};
+
+/// Quotes `stake` for `user` without moving tokens, failing where `stake`
+/// would on the pause flags and the access mode. The Eclipse leg is assumed
+/// present unless the pool's deposit mode is `Optional` and
+/// `eclipse_token_amount` is zero.
+pub fn handler(ctx: Context<PreviewStake>, input: StakeInput) -> Result<StakeResult> {
+    ctx.accounts.main_state.require_not_paused(PAUSE_STAKE)?;
+    verify_access(
+        &ctx.accounts.main_state,
+        &ctx.accounts.user.key(),
+        &ctx.accounts.mushi_program_token_vault_owner,
+        ctx.accounts.allowlist_entry.as_deref().map(|entry| &**entry),
+    )?;
+
+    let deposits_eclipse = match ctx.accounts.main_state.eclipse_deposit_mode {
+        EclipseDepositMode::Optional => input.eclipse_token_amount > 0,
+        EclipseDepositMode::Required | EclipseDepositMode::MushiProgramVault => true,
+    };
+    quote_stake_with_mints(
+        &ctx.accounts.main_state,
+        &input,
+        &ctx.accounts.mushi_token_mint,
+        &ctx.accounts.eclipse_token_mint,
+        deposits_eclipse,
+    )
+}
+
+#[derive(Accounts)]
+pub struct PreviewStake<'info> {
+    /// CHECK: only used to derive `allowlist_entry`; need not sign.
+    pub user: UncheckedAccount<'info>,
+    #[account(
+        seeds = [MainState::PREFIX_SEED, main_state.pool_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub main_state: Box<Account<'info, MainState>>,
+    #[account(
+        seeds = [ALLOWLIST_SEED, main_state.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub allowlist_entry: Option<Box<Account<'info, AllowlistEntry>>>,
+    /// CHECK: `MUSHIPROGRAM_VAULT_SEED` PDA of `main_state.mushi_program`; `verify_access` checks it signed in `AccessMode::ProgramGated`
+    #[account(
+        seeds = [MUSHIPROGRAM_VAULT_SEED],
+        bump,
+        seeds::program = main_state.mushi_program,
+    )]
+    pub mushi_program_token_vault_owner: UncheckedAccount<'info>,
+    #[account(address = main_state.mushi_token_mint)]
+    pub mushi_token_mint: InterfaceAccount<'info, token_interface::Mint>,
+    #[account(address = main_state.eclipse_token_mint)]
+    pub eclipse_token_mint: InterfaceAccount<'info, token_interface::Mint>,
+}
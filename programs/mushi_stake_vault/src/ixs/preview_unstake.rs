@@ -0,0 +1,82 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{associated_token::get_associated_token_address_with_program_id, token_interface};
+use crate::{
+    errors::MushiStakeVaultError,
+    ixs::unstake::{quote_unstake, UnstakeInput, UnstakeResult},
+    state::{MainState, StakeEntry, PAUSE_UNSTAKE, STAKE_ENTRY_SEED, VAULT_OWNER_SEED},
+    utils::transfer_fee,
+};
+
+/// Returned by `preview_unstake`.
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct UnstakePreview {
+    /// What `unstake`, or `request_unstake` while a cooldown is configured,
+    /// would redeem.
+    pub result: UnstakeResult,
+    /// MUSHI landing in the user's account after any Token-2022 transfer fee.
+    pub mushi_token_received: u64,
+    /// Eclipse landing in the entry's `eclipse_token_source` after any Token-2022 transfer fee.
+    pub eclipse_token_received: u64,
+    /// `None` when `unstake` pays out at once; otherwise when
+    /// `withdraw_unstaked` can release a request made now.
+    pub unlock_time: Option<i64>,
+}
+
+/// Quotes the exit open to the user at the current time without moving
+/// tokens: `unstake`, or `request_unstake` while the pool has a cooldown.
+/// Applies the same pause, exchange rate, early-unstake penalty, slippage and
+/// vault checks.
+pub fn handler(ctx: Context<PreviewUnstake>, input: UnstakeInput) -> Result<UnstakePreview> {
+    let main_state = &ctx.accounts.main_state;
+    main_state.require_not_paused(PAUSE_UNSTAKE)?;
+    let now = Clock::get()?.unix_timestamp;
+    let unlock_time = match main_state.unstake_cooldown {
+        0 => None,
+        cooldown => Some(now.checked_add(cooldown).ok_or(MushiStakeVaultError::MathOverflow)?),
+    };
+    let result = quote_unstake(main_state, &ctx.accounts.stake_entry, &input, ctx.accounts.mushi_token_vault.amount, now)?;
+    let mushi_token_fee = transfer_fee(&ctx.accounts.mushi_token_mint.to_account_info(), result.mushi_token_amount)?;
+    let eclipse_token_fee = transfer_fee(&ctx.accounts.eclipse_token_mint.to_account_info(), result.eclipse_token_amount)?;
+    Ok(UnstakePreview {
+        mushi_token_received: result.mushi_token_amount.checked_sub(mushi_token_fee).ok_or(MushiStakeVaultError::MathOverflow)?,
+        eclipse_token_received: result.eclipse_token_amount.checked_sub(eclipse_token_fee).ok_or(MushiStakeVaultError::MathOverflow)?,
+        result,
+        unlock_time,
+    })
+}
+
+#[derive(Accounts)]
+pub struct PreviewUnstake<'info> {
+    /// CHECK: only used to derive `stake_entry`; need not sign.
+    pub user: UncheckedAccount<'info>,
+    #[account(
+        seeds = [MainState::PREFIX_SEED, main_state.pool_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub main_state: Box<Account<'info, MainState>>,
+    #[account(
+        seeds = [STAKE_ENTRY_SEED, main_state.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub stake_entry: Box<Account<'info, StakeEntry>>,
+    #[account(
+        token::mint = mushi_token_mint,
+        token::authority = token_vault_owner,
+        token::token_program = token_program,
+        address = get_associated_token_address_with_program_id(&token_vault_owner.key(), &mushi_token_mint.key(), &token_program.key()),
+    )]
+    pub mushi_token_vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    #[account(
+        mint::token_program = token_program,
+        address = main_state.mushi_token_mint,
+    )]
+    pub mushi_token_mint: InterfaceAccount<'info, token_interface::Mint>,
+    #[account(address = main_state.eclipse_token_mint)]
+    pub eclipse_token_mint: InterfaceAccount<'info, token_interface::Mint>,
+    #[account(
+        seeds = [VAULT_OWNER_SEED, main_state.key().as_ref()],
+        bump,
+    )]
+    pub token_vault_owner: SystemAccount<'info>,
+    pub token_program: Interface<'info, token_interface::TokenInterface>,
+}
@@ -0,0 +1,43 @@
+use anchor_lang::prelude::*;
+use crate::{state::{MainState, PAUSE_ADMIN}, errors::MushiStakeVaultError, events::AdminTransferProposed};
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone)]
+pub struct ProposeAdminInput {
+    pub new_admin: Pubkey,
+}
+
+pub fn handler(ctx: Context<ProposeAdmin>, input: ProposeAdminInput) -> Result<()> {
+    ctx.accounts.main_state.require_not_paused(PAUSE_ADMIN)?;
+
+    require!(
+        input.new_admin != Pubkey::default() && input.new_admin != ctx.accounts.main_state.admin,
+        MushiStakeVaultError::InvalidNewAdmin
+    );
+
+    let main_state = &mut ctx.accounts.main_state;
+    main_state.pending_admin = input.new_admin;
+
+    emit!(AdminTransferProposed {
+        main_state: main_state.key(),
+        admin: main_state.admin,
+        pending_admin: main_state.pending_admin,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ProposeAdmin<'info> {
+    #[account(
+        address = main_state.admin @ MushiStakeVaultError::UnauthorizedAdminAction,
+        mut
+    )]
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [MainState::PREFIX_SEED, main_state.pool_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub main_state: Box<Account<'info, MainState>>,
+    pub system_program: Program<'info, System>,
+}
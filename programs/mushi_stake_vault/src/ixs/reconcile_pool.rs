@@ -0,0 +1,136 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::{get_associated_token_address_with_program_id, AssociatedToken},
+    token_interface,
+};
+use crate::{
+    errors::MushiStakeVaultError,
+    events::PoolReconciled,
+    ixs::audit_pool::pool_audit,
+    state::{MainState, ReconcileMode, PAUSE_ADMIN, REWARD_VAULT_SEED, VAULT_OWNER_SEED},
+    utils::{transfer_tokens, TransferTokenInput},
+};
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone)]
+pub struct ReconcilePoolInput {
+    pub mode: ReconcileMode,
+}
+
+pub fn handler(ctx: Context<ReconcilePool>, input: ReconcilePoolInput) -> Result<()> {
+    ctx.accounts.main_state.require_not_paused(PAUSE_ADMIN)?;
+
+    let now = Clock::get()?.unix_timestamp;
+    let audit = pool_audit(
+        &ctx.accounts.main_state,
+        ctx.accounts.main_state.key(),
+        ctx.accounts.mushi_token_vault.amount,
+        ctx.accounts.eclipse_token_vault.amount,
+        ctx.accounts.stake_token_mint.supply,
+        now,
+    );
+    emit!(audit.clone());
+
+    // Stake entries are owed what the counters say, so a shortfall is never
+    // written into them; only a MUSHI surplus is absorbed.
+    require!(
+        audit.mushi_token_delta >= 0 && audit.eclipse_token_delta >= 0 && audit.stake_token_supply_delta >= 0,
+        MushiStakeVaultError::ReconcileShortfall
+    );
+    let mushi_token_surplus = audit.mushi_token_delta as u64;
+    let donate = input.mode == ReconcileMode::DonateSurplusToRewards;
+    if donate && mushi_token_surplus > 0 {
+        let main_state_key = ctx.accounts.main_state.key();
+        let bump = *ctx.bumps.get("token_vault_owner").unwrap();
+        transfer_tokens(
+            TransferTokenInput {
+                from: ctx.accounts.mushi_token_vault.to_account_info(),
+                to: ctx.accounts.reward_token_vault.to_account_info(),
+                authority: ctx.accounts.token_vault_owner.to_account_info(),
+                mint: ctx.accounts.mushi_token_mint.to_account_info(),
+                token_program: ctx.accounts.token_program.to_account_info(),
+                amount: mushi_token_surplus,
+                decimals: ctx.accounts.mushi_token_mint.decimals,
+            },
+            Some(&[&[VAULT_OWNER_SEED, main_state_key.as_ref(), &[bump]]]),
+        )?;
+    }
+
+    let main_state = &mut ctx.accounts.main_state;
+    if !donate {
+        main_state.mushi_token_amount = main_state.mushi_token_amount.checked_add(mushi_token_surplus).ok_or(MushiStakeVaultError::MathOverflow)?;
+    }
+
+    emit!(PoolReconciled {
+        main_state: main_state.key(),
+        mode: input.mode,
+        donated_mushi_token_amount: if donate { mushi_token_surplus } else { 0 },
+        mushi_token_amount: main_state.mushi_token_amount,
+        eclipse_token_amount: main_state.eclipse_token_amount,
+        staking_token_total_supply: main_state.staking_token_total_supply,
+        timestamp: now,
+    });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ReconcilePool<'info> {
+    #[account(
+        address = main_state.admin @ MushiStakeVaultError::UnauthorizedAdminAction,
+        mut
+    )]
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [MainState::PREFIX_SEED, main_state.pool_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub main_state: Box<Account<'info, MainState>>,
+    #[account(
+        mut,
+        token::mint = mushi_token_mint,
+        token::authority = token_vault_owner,
+        token::token_program = token_program,
+        address = get_associated_token_address_with_program_id(&token_vault_owner.key(), &mushi_token_mint.key(), &token_program.key()),
+    )]
+    pub mushi_token_vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [REWARD_VAULT_SEED, main_state.key().as_ref()],
+        bump,
+        token::mint = mushi_token_mint,
+        token::authority = token_vault_owner,
+        token::token_program = token_program,
+    )]
+    pub reward_token_vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    #[account(
+        mint::token_program = token_program,
+        address = main_state.mushi_token_mint,
+    )]
+    pub mushi_token_mint: InterfaceAccount<'info, token_interface::Mint>,
+    #[account(
+        token::mint = eclipse_token_mint,
+        token::authority = token_vault_owner,
+        token::token_program = token2022_program,
+        address = get_associated_token_address_with_program_id(&token_vault_owner.key(), &eclipse_token_mint.key(), &token2022_program.key()),
+    )]
+    pub eclipse_token_vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    #[account(
+        mint::token_program = token2022_program,
+        address = main_state.eclipse_token_mint,
+    )]
+    pub eclipse_token_mint: InterfaceAccount<'info, token_interface::Mint>,
+    #[account(
+        mint::token_program = token_program,
+        address = main_state.stake_token_mint,
+    )]
+    pub stake_token_mint: InterfaceAccount<'info, token_interface::Mint>,
+    #[account(
+        seeds = [VAULT_OWNER_SEED, main_state.key().as_ref()],
+        bump,
+    )]
+    pub token_vault_owner: SystemAccount<'info>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, token_interface::TokenInterface>,
+    pub token2022_program: Interface<'info, token_interface::TokenInterface>,
+}
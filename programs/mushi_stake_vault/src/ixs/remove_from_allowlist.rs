@@ -0,0 +1,31 @@
+use anchor_lang::prelude::*;
+use crate::{state::{AllowlistEntry, MainState, ALLOWLIST_SEED, PAUSE_ADMIN}, errors::MushiStakeVaultError};
+
+pub fn handler(ctx: Context<RemoveFromAllowlist>) -> Result<()> {
+    ctx.accounts.main_state.require_not_paused(PAUSE_ADMIN)?;
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct RemoveFromAllowlist<'info> {
+    #[account(
+        address = main_state.admin @ MushiStakeVaultError::UnauthorizedAdminAction,
+        mut
+    )]
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [MainState::PREFIX_SEED, main_state.pool_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub main_state: Box<Account<'info, MainState>>,
+    /// CHECK: wallet being removed, only used as a seed
+    pub user: AccountInfo<'info>,
+    #[account(
+        mut,
+        close = admin,
+        seeds = [ALLOWLIST_SEED, main_state.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub allowlist_entry: Box<Account<'info, AllowlistEntry>>,
+    pub system_program: Program<'info, System>,
+}
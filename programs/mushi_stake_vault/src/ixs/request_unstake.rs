@@ -0,0 +1,173 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{associated_token::get_associated_token_address_with_program_id, token_interface};
+use crate::{
+    errors::MushiStakeVaultError,
+    events::Unstaked,
+    ixs::unstake::{settle_unstake, UnstakeAmounts, UnstakeInput},
+    state::{MainState, PenaltyDestination, StakeEntry, UnstakeRequest, PAUSE_UNSTAKE, STAKE_ENTRY_SEED, UNSTAKE_REQUEST_SEED, VAULT_OWNER_SEED},
+    utils::{burn_tokens, transfer_tokens, StakeTokenAccount, TransferTokenInput},
+};
+
+pub fn handler(ctx: Context<RequestUnstake>, input: UnstakeInput) -> Result<()> {
+    ctx.accounts.main_state.require_not_paused(PAUSE_UNSTAKE)?;
+
+
+    let stake_token_amount = input.amount;
+    let now = Clock::get()?.unix_timestamp;
+    let eclipse_token_source = ctx.accounts.stake_entry.eclipse_token_source;
+    let UnstakeAmounts {
+        mushi_token_amount,
+        eclipse_token_amount,
+        penalty_amount,
+    } = settle_unstake(
+        &mut ctx.accounts.main_state,
+        &mut ctx.accounts.stake_entry,
+        &input,
+        ctx.accounts.mushi_token_vault.amount,
+        now,
+    )?;
+
+    if penalty_amount > 0 && ctx.accounts.main_state.penalty_destination == PenaltyDestination::Treasury {
+        let treasury_mushi_token_ata = ctx.accounts.treasury_mushi_token_ata.as_ref()
+            .ok_or(MushiStakeVaultError::InvalidTreasuryAccount)?;
+        let main_state_key = ctx.accounts.main_state.key();
+        let bump = *ctx.bumps.get("staking_program_token_vault_owner").unwrap();
+        transfer_tokens(
+            TransferTokenInput {
+                from: ctx.accounts.mushi_token_vault.to_account_info(),
+                to: treasury_mushi_token_ata.to_account_info(),
+                authority: ctx.accounts.staking_program_token_vault_owner.to_account_info(),
+                mint: ctx.accounts.mushi_token_mint.to_account_info(),
+                token_program: ctx.accounts.token_program.to_account_info(),
+                amount: penalty_amount,
+                decimals: ctx.accounts.mushi_token_mint.decimals,
+            },
+            Some(&[&[VAULT_OWNER_SEED, main_state_key.as_ref(), &[bump]]]),
+        )?;
+    }
+
+    let stake_token_account = StakeTokenAccount {
+        account: ctx.accounts.user_stake_token_ata.to_account_info(),
+        mint: ctx.accounts.stake_token_mint.to_account_info(),
+        main_state: ctx.accounts.main_state.to_account_info(),
+        token_program: ctx.accounts.token_program.to_account_info(),
+        pool_id: ctx.accounts.main_state.pool_id,
+        main_state_bump: *ctx.bumps.get("main_state").unwrap(),
+    };
+    if ctx.accounts.user_stake_token_ata.is_frozen() {
+        stake_token_account.thaw()?;
+    }
+    burn_tokens(
+        ctx.accounts.user_stake_token_ata.to_account_info(),
+        ctx.accounts.stake_token_mint.to_account_info(),
+        ctx.accounts.user.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        stake_token_amount,
+        None,
+    )?;
+    // An emptied account is left thawed so the user can close it.
+    if ctx.accounts.user_stake_token_ata.amount > stake_token_amount {
+        stake_token_account.freeze()?;
+    }
+
+    // Redeemed tokens stay in the vaults, out of the exchange rate, until withdrawn.
+    let main_state = &mut ctx.accounts.main_state;
+    main_state.pending_unstake_mushi_amount = main_state.pending_unstake_mushi_amount.checked_add(mushi_token_amount).ok_or(MushiStakeVaultError::MathOverflow)?;
+    main_state.pending_unstake_eclipse_amount = main_state.pending_unstake_eclipse_amount.checked_add(eclipse_token_amount).ok_or(MushiStakeVaultError::MathOverflow)?;
+
+    // Topping up an open request restarts its cooldown.
+    let unstake_request = &mut ctx.accounts.unstake_request;
+    unstake_request.user = ctx.accounts.user.key();
+    unstake_request.mushi_token_amount = unstake_request.mushi_token_amount.checked_add(mushi_token_amount).ok_or(MushiStakeVaultError::MathOverflow)?;
+    if eclipse_token_amount > 0 {
+        // One request returns its Eclipse to a single account.
+        require!(
+            unstake_request.eclipse_token_amount == 0 || unstake_request.eclipse_token_source == eclipse_token_source,
+            MushiStakeVaultError::EclipseSourceMismatch
+        );
+        unstake_request.eclipse_token_source = eclipse_token_source;
+    }
+    unstake_request.eclipse_token_amount = unstake_request.eclipse_token_amount.checked_add(eclipse_token_amount).ok_or(MushiStakeVaultError::MathOverflow)?;
+    unstake_request.unlock_time = now.checked_add(main_state.unstake_cooldown).ok_or(MushiStakeVaultError::MathOverflow)?;
+
+    emit!(Unstaked {
+        main_state: main_state.key(),
+        user: ctx.accounts.user.key(),
+        mushi_token_amount,
+        eclipse_token_amount,
+        stake_token_amount,
+        penalty_amount,
+        unlock_time: Some(unstake_request.unlock_time),
+        total_mushi_token_amount: main_state.mushi_token_amount,
+        total_eclipse_token_amount: main_state.eclipse_token_amount,
+        staking_token_total_supply: main_state.staking_token_total_supply,
+        timestamp: now,
+    });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct RequestUnstake<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [MainState::PREFIX_SEED, main_state.pool_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub main_state: Box<Account<'info, MainState>>,
+    #[account(
+        mut,
+        seeds = [STAKE_ENTRY_SEED, main_state.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub stake_entry: Box<Account<'info, StakeEntry>>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [UNSTAKE_REQUEST_SEED, main_state.key().as_ref(), user.key().as_ref()],
+        bump,
+        space = 8 + UnstakeRequest::MAX_SIZE,
+    )]
+    pub unstake_request: Box<Account<'info, UnstakeRequest>>,
+    #[account(
+        mut,
+        token::mint = stake_token_mint,
+        token::authority = user,
+        token::token_program = token_program,
+    )]
+    pub user_stake_token_ata: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    #[account(
+        mut,
+        mint::token_program = token_program,
+        address = main_state.stake_token_mint,
+    )]
+    pub stake_token_mint: InterfaceAccount<'info, token_interface::Mint>,
+    #[account(
+        mut,
+        token::mint = mushi_token_mint,
+        token::authority = staking_program_token_vault_owner,
+        token::token_program = token_program,
+        address = get_associated_token_address_with_program_id(&staking_program_token_vault_owner.key(), &mushi_token_mint.key(), &token_program.key()),
+    )]
+    pub mushi_token_vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    #[account(
+        mut,
+        token::mint = mushi_token_mint,
+        token::token_program = token_program,
+        constraint = treasury_mushi_token_ata.owner == main_state.treasury @ MushiStakeVaultError::InvalidTreasuryAccount,
+    )]
+    pub treasury_mushi_token_ata: Option<Box<InterfaceAccount<'info, token_interface::TokenAccount>>>,
+    #[account(
+        mint::token_program = token_program,
+        address = main_state.mushi_token_mint,
+    )]
+    pub mushi_token_mint: InterfaceAccount<'info, token_interface::Mint>,
+    #[account(
+        seeds = [VAULT_OWNER_SEED, main_state.key().as_ref()],
+        bump,
+    )]
+    pub staking_program_token_vault_owner: SystemAccount<'info>,
+    pub token_program: Interface<'info, token_interface::TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
@@ -0,0 +1,44 @@
+use anchor_lang::prelude::*;
+use crate::{state::{MainState, PAUSE_ALL}, errors::MushiStakeVaultError, events::PauseUpdated};
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone)]
+pub struct SetPauseInput {
+    /// Full `PAUSE_*` bitfield to apply; `0` unpauses everything. The
+    /// guardian may only add flags, so clearing any takes the admin.
+    pub paused: u8,
+}
+
+pub fn handler(ctx: Context<SetPause>, input: SetPauseInput) -> Result<()> {
+    require!(input.paused & !PAUSE_ALL == 0, MushiStakeVaultError::InvalidPauseFlags);
+
+    let main_state = &mut ctx.accounts.main_state;
+    if ctx.accounts.authority.key() != main_state.admin {
+        require!(input.paused & main_state.paused == main_state.paused, MushiStakeVaultError::UnauthorizedPauseAuthority);
+    }
+    main_state.paused = input.paused;
+
+    emit!(PauseUpdated {
+        main_state: main_state.key(),
+        authority: ctx.accounts.authority.key(),
+        paused: main_state.paused,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetPause<'info> {
+    #[account(
+        constraint = authority.key() == main_state.admin || authority.key() == main_state.guardian
+            @ MushiStakeVaultError::UnauthorizedPauseAuthority,
+        mut
+    )]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [MainState::PREFIX_SEED, main_state.pool_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub main_state: Box<Account<'info, MainState>>,
+    pub system_program: Program<'info, System>,
+}
@@ -0,0 +1,68 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface;
+use crate::{events::RewardRateSet, state::{MainState, PAUSE_ADMIN, REWARD_VAULT_SEED}, errors::MushiStakeVaultError};
+
+/// Emission schedule: `reward_rate` tokens per second between `start_time` and
+/// `end_time`. Extending a running campaign is done by resubmitting it with a
+/// later `end_time`. The reward vault must already hold everything still to be
+/// emitted, `reward_rate * (end_time - max(start_time, now))`.
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone)]
+pub struct SetRewardRateInput {
+    pub reward_rate: u64,
+    pub start_time: i64,
+    pub end_time: i64,
+}
+
+pub fn handler(ctx: Context<SetRewardRate>, input: SetRewardRateInput) -> Result<()> {
+    ctx.accounts.main_state.require_not_paused(PAUSE_ADMIN)?;
+
+    let now = Clock::get()?.unix_timestamp;
+    require!(
+        input.end_time > input.start_time && input.end_time > now,
+        MushiStakeVaultError::InvalidRewardSchedule
+    );
+
+    let remaining_duration = (input.end_time as i128 - input.start_time.max(now) as i128) as u128;
+    require!(
+        (input.reward_rate as u128) * remaining_duration <= ctx.accounts.reward_token_vault.amount as u128,
+        MushiStakeVaultError::InsufficientRewardVaultBalance
+    );
+
+    let main_state = &mut ctx.accounts.main_state;
+    // Settle everything earned under the old schedule before switching over.
+    main_state.update_rewards(now);
+    main_state.reward_rate = input.reward_rate;
+    main_state.reward_start_time = input.start_time;
+    main_state.reward_end_time = input.end_time;
+
+    emit!(RewardRateSet {
+        main_state: main_state.key(),
+        admin: ctx.accounts.admin.key(),
+        reward_rate: input.reward_rate,
+        start_time: input.start_time,
+        end_time: input.end_time,
+        timestamp: now,
+    });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetRewardRate<'info> {
+    #[account(
+        address = main_state.admin @ MushiStakeVaultError::UnauthorizedAdminAction,
+        mut
+    )]
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [MainState::PREFIX_SEED, main_state.pool_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub main_state: Box<Account<'info, MainState>>,
+    #[account(
+        seeds = [REWARD_VAULT_SEED, main_state.key().as_ref()],
+        bump,
+    )]
+    pub reward_token_vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    pub system_program: Program<'info, System>,
+}
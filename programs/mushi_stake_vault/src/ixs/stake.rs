@@ -1,92 +1,255 @@
 use anchor_lang::prelude::*;
-use anchor_spl::{associated_token::AssociatedToken, token_interface};
+use anchor_spl::{
+    associated_token::{self, get_associated_token_address_with_program_id, AssociatedToken},
+    token_interface,
+};
 use crate::{
-    errors::MushiStakeVaultError, state::{MainState, VAULT_OWNER_SEED}, utils::{mint_to_tokens_by_main_state, TransferTokenInput, transfer_tokens, TransferToken2022Input, transfer_token_2022}
+    errors::MushiStakeVaultError,
+    events::Staked,
+    state::{AllowlistEntry, EclipseDepositMode, MainState, StakeEntry, ALLOWLIST_SEED, MUSHIPROGRAM_VAULT_SEED, PAUSE_STAKE, STAKE_ENTRY_SEED, VAULT_OWNER_SEED},
+    utils::{mint_to_tokens_by_main_state, transfer_tokens, transfer_fee, verify_access, verify_mushi_program_caller, verify_supported_mint_extensions, StakeTokenAccount, TransferTokenInput},
 };
 
 #[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone)]
 pub struct StakeInput {
-    pub amount: u64,
+    /// MUSHI to deposit; stake tokens are minted at the current exchange rate.
+    pub mushi_token_amount: u64,
+    /// Eclipse to deposit according to `MainState::eclipse_deposit_mode`; must
+    /// match `mushi_token_amount` at `MainState::eclipse_per_mushi_bps`, or be
+    /// zero when an optional Eclipse leg is skipped. Any Token-2022 transfer
+    /// fee comes out of this amount.
+    pub eclipse_token_amount: u64,
 }
 
-pub fn handler(ctx: Context<Stake>, input: StakeInput) -> Result<()> {
-    // Verify that this function is being called by the authorized program
-    let instructions_sysvar = ctx.accounts.instruction_sysvar.as_ref();
-    
-    // Get the current instruction index
-    let current_index = anchor_lang::solana_program::sysvar::instructions::load_current_index_checked(instructions_sysvar)?;
-    
-    // Get the current instruction and check who called us
-    let current_ix = anchor_lang::solana_program::sysvar::instructions::load_instruction_at_checked(
-        current_index as usize, 
-        instructions_sysvar
-    )?;
-    
-    // Check if the current instruction's program ID matches the mushi_program
-    if current_ix.program_id != ctx.accounts.main_state.mushi_program {
-        // Check if we were called by the mushi_program via CPI
-        if current_index == 0 {
-            // If we're the first instruction, there's no caller, so unauthorized
-            return Err(MushiStakeVaultError::UnauthorizedProgramCall.into());
+/// Returned by `stake` as return data for CPI callers.
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct StakeResult {
+    /// MUSHI credited to the pool, net of any Token-2022 transfer fee.
+    pub mushi_token_amount: u64,
+    /// Eclipse credited to the pool, net of any Token-2022 transfer fee.
+    pub eclipse_token_amount: u64,
+    pub stake_token_amount: u64,
+    pub total_mushi_token_amount: u64,
+    pub total_eclipse_token_amount: u64,
+    pub staking_token_total_supply: u64,
+}
+
+/// What depositing `input` into `main_state` credits and mints. Fees are the
+/// Token-2022 transfer fees withheld from each leg; `eclipse_transfer_fee` is
+/// `None` when the Eclipse leg is skipped. `stake` and `preview_stake` both go
+/// through this, so a preview matches the result of staking against the same state.
+pub fn quote_stake(
+    main_state: &MainState,
+    input: &StakeInput,
+    mushi_decimals: u8,
+    eclipse_decimals: u8,
+    mushi_transfer_fee: u64,
+    eclipse_transfer_fee: Option<u64>,
+) -> Result<StakeResult> {
+    // Stake tokens are minted for what the vault receives.
+    let mushi_token_amount = input.mushi_token_amount.checked_sub(mushi_transfer_fee).ok_or(MushiStakeVaultError::MathOverflow)?;
+    let stake_token_amount = main_state.stake_tokens_for_deposit(mushi_token_amount)?;
+
+    require!(stake_token_amount > 0, MushiStakeVaultError::InsufficientStakeTokenAmount);
+
+    // Only Eclipse that actually lands in the vault is credited, so unstake can never pay out more than it received.
+    let eclipse_token_amount = match eclipse_transfer_fee {
+        Some(eclipse_transfer_fee) => {
+            let required_eclipse_token_amount = main_state.eclipse_tokens_for_deposit(input.mushi_token_amount, mushi_decimals, eclipse_decimals)?;
+            require!(input.eclipse_token_amount == required_eclipse_token_amount, MushiStakeVaultError::InvalidDepositRatio);
+            input.eclipse_token_amount.checked_sub(eclipse_transfer_fee).ok_or(MushiStakeVaultError::MathOverflow)?
         }
-        
-        // Check the previous instruction to see if it's the authorized program
-        let prev_ix = anchor_lang::solana_program::sysvar::instructions::load_instruction_at_checked(
-            current_index as usize - 1, 
-            instructions_sysvar
-        )?;
-        
-        if prev_ix.program_id != ctx.accounts.main_state.mushi_program {
-            return Err(MushiStakeVaultError::UnauthorizedProgramCall.into());
+        None => {
+            require!(input.eclipse_token_amount == 0, MushiStakeVaultError::MissingEclipseTokenAccount);
+            0
         }
-    }
+    };
+
+    Ok(StakeResult {
+        mushi_token_amount,
+        eclipse_token_amount,
+        stake_token_amount,
+        total_mushi_token_amount: main_state.mushi_token_amount.checked_add(mushi_token_amount).ok_or(MushiStakeVaultError::MathOverflow)?,
+        total_eclipse_token_amount: main_state.eclipse_token_amount.checked_add(eclipse_token_amount).ok_or(MushiStakeVaultError::MathOverflow)?,
+        staking_token_total_supply: main_state.staking_token_total_supply.checked_add(stake_token_amount).ok_or(MushiStakeVaultError::MathOverflow)?,
+    })
+}
 
-    let eclipse_token_amount = input.amount;
-    let mushi_token_amount = input.amount;
-    let stake_token_amount = input.amount;
+/// Checks both mints and quotes `input` against their current transfer fees.
+pub fn quote_stake_with_mints(
+    main_state: &MainState,
+    input: &StakeInput,
+    mushi_token_mint: &InterfaceAccount<token_interface::Mint>,
+    eclipse_token_mint: &InterfaceAccount<token_interface::Mint>,
+    deposits_eclipse: bool,
+) -> Result<StakeResult> {
+    // MUSHI may itself be a Token-2022 mint.
+    let mushi_token_mint_info = mushi_token_mint.to_account_info();
+    verify_supported_mint_extensions(&mushi_token_mint_info)?;
+    let mushi_transfer_fee = transfer_fee(&mushi_token_mint_info, input.mushi_token_amount)?;
+    let eclipse_transfer_fee = if deposits_eclipse {
+        let eclipse_token_mint_info = eclipse_token_mint.to_account_info();
+        verify_supported_mint_extensions(&eclipse_token_mint_info)?;
+        // Withheld in the vault account; it never becomes spendable balance.
+        Some(transfer_fee(&eclipse_token_mint_info, input.eclipse_token_amount)?)
+    } else {
+        None
+    };
+    quote_stake(
+        main_state,
+        input,
+        mushi_token_mint.decimals,
+        eclipse_token_mint.decimals,
+        mushi_transfer_fee,
+        eclipse_transfer_fee,
+    )
+}
 
-    require!(mushi_token_amount <= ctx.accounts.user_mushi_token_ata.amount, MushiStakeVaultError::InsufficientMushiTokenAmount);
+pub fn handler(ctx: Context<Stake>, input: StakeInput) -> Result<StakeResult> {
+    ctx.accounts.main_state.require_not_paused(PAUSE_STAKE)?;
+
+    verify_access(
+        &ctx.accounts.main_state,
+        &ctx.accounts.user.key(),
+        &ctx.accounts.mushi_program_token_vault_owner,
+        ctx.accounts.allowlist_entry.as_deref().map(|entry| &**entry),
+    )?;
+
+    require!(input.mushi_token_amount <= ctx.accounts.user_mushi_token_ata.amount, MushiStakeVaultError::InsufficientMushiTokenAmount);
+
+    let eclipse_token_source = match ctx.accounts.main_state.eclipse_deposit_mode {
+        EclipseDepositMode::Required => Some((
+            ctx.accounts.user_eclipse_token_ata.as_ref().ok_or(MushiStakeVaultError::MissingEclipseTokenAccount)?,
+            ctx.accounts.user.to_account_info(),
+        )),
+        EclipseDepositMode::Optional => ctx
+            .accounts
+            .user_eclipse_token_ata
+            .as_ref()
+            .map(|ata| (ata, ctx.accounts.user.to_account_info())),
+        EclipseDepositMode::MushiProgramVault => {
+            verify_mushi_program_caller(&ctx.accounts.mushi_program_token_vault_owner)?;
+            Some((
+                ctx.accounts.eclipse_token_mushi_program_vault.as_ref().ok_or(MushiStakeVaultError::MissingEclipseTokenAccount)?,
+                ctx.accounts.mushi_program_token_vault_owner.to_account_info(),
+            ))
+        }
+    };
+
+    // All of a position's Eclipse is returned to one account, so it has to
+    // keep coming from the same one.
+    let eclipse_token_source_key = eclipse_token_source.as_ref().map(|(from, _)| from.key());
+    if let Some(eclipse_token_source_key) = eclipse_token_source_key {
+        let stake_entry = &ctx.accounts.stake_entry;
+        require!(
+            stake_entry.eclipse_token_staked_amount == 0 || stake_entry.eclipse_token_source == eclipse_token_source_key,
+            MushiStakeVaultError::EclipseSourceMismatch
+        );
+    }
+
+    let result = quote_stake_with_mints(
+        &ctx.accounts.main_state,
+        &input,
+        &ctx.accounts.mushi_token_mint,
+        &ctx.accounts.eclipse_token_mint,
+        eclipse_token_source.is_some(),
+    )?;
 
     transfer_tokens(
         TransferTokenInput {
             from: ctx.accounts.user_mushi_token_ata.to_account_info(),
             to: ctx.accounts.mushi_token_vault.to_account_info(),
             authority: ctx.accounts.user.to_account_info(),
+            mint: ctx.accounts.mushi_token_mint.to_account_info(),
             token_program: ctx.accounts.token_program.to_account_info(),
-            amount: mushi_token_amount,
+            amount: input.mushi_token_amount,
+            decimals: ctx.accounts.mushi_token_mint.decimals,
         },
         None,
     )?;
 
-    // Transfer eclipse token from user to vault
-    // transfer_token_2022(
-    //     TransferToken2022Input {
-    //         from: ctx.accounts.user_eclipse_token_ata.to_account_info(),
-    //         to: ctx.accounts.eclipse_token_vault.to_account_info(),
-    //         authority: ctx.accounts.user.to_account_info(),
-    //         mint: ctx.accounts.eclipse_token_mint.to_account_info(),
-    //         token_program: ctx.accounts.token2022_program.to_account_info(),
-    //         amount: eclipse_token_amount,
-    //         decimals: ctx.accounts.eclipse_token_mint.decimals,
-    //     },
-    //     None,
-    // )?;
+    if let Some((from, authority)) = eclipse_token_source {
+        require!(input.eclipse_token_amount <= from.amount, MushiStakeVaultError::InsufficientEclipseTokenAmount);
+        transfer_tokens(
+            TransferTokenInput {
+                from: from.to_account_info(),
+                to: ctx.accounts.eclipse_token_vault.to_account_info(),
+                authority,
+                mint: ctx.accounts.eclipse_token_mint.to_account_info(),
+                token_program: ctx.accounts.token2022_program.to_account_info(),
+                amount: input.eclipse_token_amount,
+                decimals: ctx.accounts.eclipse_token_mint.decimals,
+            },
+            None,
+        )?;
+    }
 
+    associated_token::create_idempotent(CpiContext::new(
+        ctx.accounts.associated_token_program.to_account_info(),
+        associated_token::Create {
+            payer: ctx.accounts.user.to_account_info(),
+            associated_token: ctx.accounts.user_stake_token_ata.to_account_info(),
+            authority: ctx.accounts.user.to_account_info(),
+            mint: ctx.accounts.stake_token_mint.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+        },
+    ))?;
+
+    let stake_token_account = StakeTokenAccount {
+        account: ctx.accounts.user_stake_token_ata.to_account_info(),
+        mint: ctx.accounts.stake_token_mint.to_account_info(),
+        main_state: ctx.accounts.main_state.to_account_info(),
+        token_program: ctx.accounts.token_program.to_account_info(),
+        pool_id: ctx.accounts.main_state.pool_id,
+        main_state_bump: *ctx.bumps.get("main_state").unwrap(),
+    };
+    if stake_token_account.is_frozen()? {
+        stake_token_account.thaw()?;
+    }
     mint_to_tokens_by_main_state(
         ctx.accounts.stake_token_mint.to_account_info(),
         ctx.accounts.main_state.to_account_info(),
         ctx.accounts.user_stake_token_ata.to_account_info(),
         ctx.accounts.token_program.to_account_info(),
-        stake_token_amount,
+        result.stake_token_amount,
+        ctx.accounts.main_state.pool_id,
         *ctx.bumps.get("main_state").unwrap(),
     )?;
+    stake_token_account.freeze()?;
 
     
+    let now = Clock::get()?.unix_timestamp;
     let main_state = &mut ctx.accounts.main_state;
-    main_state.mushi_token_amount += mushi_token_amount;
-    main_state.eclipse_token_amount += eclipse_token_amount;
-    main_state.staking_token_total_supply += stake_token_amount;
-    Ok(())
+    main_state.update_rewards(now);
+    ctx.accounts.stake_entry.settle_rewards(main_state.acc_reward_per_share);
+
+    main_state.mushi_token_amount = result.total_mushi_token_amount;
+    main_state.eclipse_token_amount = result.total_eclipse_token_amount;
+    main_state.staking_token_total_supply = result.staking_token_total_supply;
+
+    let stake_entry = &mut ctx.accounts.stake_entry;
+    stake_entry.user = ctx.accounts.user.key();
+    stake_entry.last_staked = now;
+    stake_entry.mushi_token_staked_amount = stake_entry.mushi_token_staked_amount.checked_add(result.mushi_token_amount).ok_or(MushiStakeVaultError::MathOverflow)?;
+    stake_entry.eclipse_token_staked_amount = stake_entry.eclipse_token_staked_amount.checked_add(result.eclipse_token_amount).ok_or(MushiStakeVaultError::MathOverflow)?;
+    stake_entry.stake_token_balance = stake_entry.stake_token_balance.checked_add(result.stake_token_amount).ok_or(MushiStakeVaultError::MathOverflow)?;
+    if let Some(eclipse_token_source_key) = eclipse_token_source_key.filter(|_| result.eclipse_token_amount > 0) {
+        stake_entry.eclipse_token_source = eclipse_token_source_key;
+    }
+
+    emit!(Staked {
+        main_state: main_state.key(),
+        user: ctx.accounts.user.key(),
+        mushi_token_amount: result.mushi_token_amount,
+        eclipse_token_amount: result.eclipse_token_amount,
+        stake_token_amount: result.stake_token_amount,
+        total_mushi_token_amount: result.total_mushi_token_amount,
+        total_eclipse_token_amount: result.total_eclipse_token_amount,
+        staking_token_total_supply: result.staking_token_total_supply,
+        timestamp: now,
+    });
+    Ok(result)
 }
 
 #[derive(Accounts)]
@@ -95,33 +258,50 @@ pub struct Stake<'info> {
     pub user: Signer<'info>,
     #[account(    
         mut,
-        seeds = [MainState::PREFIX_SEED],
+        seeds = [MainState::PREFIX_SEED, main_state.pool_id.to_le_bytes().as_ref()],
         bump,
     )]
     pub main_state: Box<Account<'info, MainState>>,
-    /// CHECK: This is the Solana instructions sysvar
-    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
-    pub instruction_sysvar: AccountInfo<'info>,
     #[account(
-        mut,
-        associated_token::mint = mushi_token_mint,
-        associated_token::authority = user,
-        associated_token::token_program = token_program,
+        seeds = [ALLOWLIST_SEED, main_state.key().as_ref(), user.key().as_ref()],
+        bump,
     )]
-    pub user_mushi_token_ata: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    pub allowlist_entry: Option<Box<Account<'info, AllowlistEntry>>>,
     #[account(
         init_if_needed,
         payer = user,
-        associated_token::mint = stake_token_mint,
-        associated_token::authority = user,
-        associated_token::token_program = token_program,
+        seeds = [STAKE_ENTRY_SEED, main_state.key().as_ref(), user.key().as_ref()],
+        bump,
+        space = 8 + StakeEntry::MAX_SIZE,
+    )]
+    pub stake_entry: Box<Account<'info, StakeEntry>>,
+    /// CHECK: `MUSHIPROGRAM_VAULT_SEED` PDA of `main_state.mushi_program`; `verify_mushi_program_caller` checks it signed
+    #[account(
+        seeds = [MUSHIPROGRAM_VAULT_SEED],
+        bump,
+        seeds::program = main_state.mushi_program,
+    )]
+    pub mushi_program_token_vault_owner: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        token::mint = mushi_token_mint,
+        token::authority = user,
+        token::token_program = token_program,
     )]
-    pub user_stake_token_ata: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    pub user_mushi_token_ata: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    /// CHECK: the user's stake token ATA under `token_program`, created by the handler when missing.
+    /// Anchor 0.28 derives `init_if_needed` ATAs with the legacy token program only.
+    #[account(
+        mut,
+        address = get_associated_token_address_with_program_id(&user.key(), &stake_token_mint.key(), &token_program.key()),
+    )]
+    pub user_stake_token_ata: UncheckedAccount<'info>,
     #[account(
         mut,
-        associated_token::mint = mushi_token_mint,
-        associated_token::authority = token_vault_owner,
-        associated_token::token_program = token_program,
+        token::mint = mushi_token_mint,
+        token::authority = token_vault_owner,
+        token::token_program = token_program,
+        address = get_associated_token_address_with_program_id(&token_vault_owner.key(), &mushi_token_mint.key(), &token_program.key()),
     )]
     pub mushi_token_vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
     #[account(
@@ -130,11 +310,26 @@ pub struct Stake<'info> {
         address = main_state.mushi_token_mint,
     )]
     pub mushi_token_mint: InterfaceAccount<'info, token_interface::Mint>,
+    #[account(
+        mut,
+        token::mint = eclipse_token_mint,
+        token::authority = user,
+        token::token_program = token2022_program,
+    )]
+    pub user_eclipse_token_ata: Option<Box<InterfaceAccount<'info, token_interface::TokenAccount>>>,
+    #[account(
+        mut,
+        token::mint = eclipse_token_mint,
+        token::authority = mushi_program_token_vault_owner,
+        token::token_program = token2022_program,
+    )]
+    pub eclipse_token_mushi_program_vault: Option<Box<InterfaceAccount<'info, token_interface::TokenAccount>>>,
     #[account(
         mut,
         token::mint = eclipse_token_mint,
         token::authority = token_vault_owner,
         token::token_program = token2022_program,
+        address = get_associated_token_address_with_program_id(&token_vault_owner.key(), &eclipse_token_mint.key(), &token2022_program.key()),
     )]
     pub eclipse_token_vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
     #[account(
@@ -151,7 +346,7 @@ pub struct Stake<'info> {
     pub stake_token_mint: InterfaceAccount<'info, token_interface::Mint>,
     #[account(
         mut,
-        seeds = [VAULT_OWNER_SEED],
+        seeds = [VAULT_OWNER_SEED, main_state.key().as_ref()],
         bump,
     )]
     pub token_vault_owner: SystemAccount<'info>,
@@ -1,79 +1,220 @@
 use anchor_lang::prelude::*;
-use anchor_spl::{associated_token::AssociatedToken, token_interface};
+use anchor_spl::{
+    associated_token::{get_associated_token_address_with_program_id, AssociatedToken},
+    token_interface,
+};
 use crate::{
-    errors::MushiStakeVaultError, state::{MainState, MUSHIPROGRAM_VAULT_SEED, VAULT_OWNER_SEED}, utils::{burn_tokens, transfer_token_2022, transfer_tokens, TransferToken2022Input, TransferTokenInput}
+    errors::MushiStakeVaultError,
+    events::Unstaked, state::{MainState, PenaltyDestination, StakeEntry, PAUSE_UNSTAKE, STAKE_ENTRY_SEED, VAULT_OWNER_SEED}, utils::{burn_tokens, transfer_tokens, StakeTokenAccount, TransferTokenInput}
 };
 
 #[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone)]
 pub struct UnstakeInput {
+    /// Stake tokens to burn; MUSHI is returned at the current exchange rate.
     pub amount: u64,
+    /// Smallest net MUSHI the caller accepts after any early-unstake penalty.
+    pub min_mushi_token_amount: u64,
+    /// Smallest Eclipse the caller accepts, in Eclipse base units, before any
+    /// Token-2022 transfer fee charged to the receiver.
+    pub min_eclipse_token_amount: u64,
 }
 
-pub fn handler(ctx: Context<Unstake>, input: UnstakeInput) -> Result<()> {
-    // Verify that this function is being called by the authorized program
-    let instructions_sysvar = ctx.accounts.instruction_sysvar.as_ref();
-    
-    // Get the current instruction index
-    let current_index = anchor_lang::solana_program::sysvar::instructions::load_current_index_checked(instructions_sysvar)?;
-    
-    // Get the current instruction and check who called us
-    let current_ix = anchor_lang::solana_program::sysvar::instructions::load_instruction_at_checked(
-        current_index as usize, 
-        instructions_sysvar
-    )?;
-    
-    // Check if the current instruction's program ID matches the mushi_program
-    if current_ix.program_id != ctx.accounts.main_state.mushi_program {
-        // Check if we were called by the mushi_program via CPI
-        if current_index == 0 {
-            // If we're the first instruction, there's no caller, so unauthorized
-            return Err(MushiStakeVaultError::UnauthorizedProgramCall.into());
-        }
-        
-        // Check the previous instruction to see if it's the authorized program
-        let prev_ix = anchor_lang::solana_program::sysvar::instructions::load_instruction_at_checked(
-            current_index as usize - 1, 
-            instructions_sysvar
-        )?;
-        
-        if prev_ix.program_id != ctx.accounts.main_state.mushi_program {
-            return Err(MushiStakeVaultError::UnauthorizedProgramCall.into());
-        }
+/// Token amounts released by burning stake tokens out of a `StakeEntry`.
+pub struct UnstakeAmounts {
+    /// MUSHI owed to the user, net of `penalty_amount`.
+    pub mushi_token_amount: u64,
+    pub eclipse_token_amount: u64,
+    /// Early-unstake penalty; left in the vault or owed to the treasury
+    /// depending on `MainState::penalty_destination`.
+    pub penalty_amount: u64,
+}
+
+/// Settles rewards and removes `input.amount` stake tokens from the pool
+/// totals and the user's entry, returning what they redeem for. Fails unless
+/// `mushi_token_vault_amount` covers the redeemed MUSHI, a treasury-bound
+/// penalty and every pending unstake.
+pub fn settle_unstake(
+    main_state: &mut MainState,
+    stake_entry: &mut StakeEntry,
+    input: &UnstakeInput,
+    mushi_token_vault_amount: u64,
+    now: i64,
+) -> Result<UnstakeAmounts> {
+    let stake_token_amount = input.amount;
+    require!(
+        stake_token_amount > 0 && stake_token_amount <= stake_entry.stake_token_balance,
+        MushiStakeVaultError::InsufficientStakeTokenAmount
+    );
+
+    let redeemed_mushi_token_amount = main_state.mushi_tokens_for_stake_tokens(stake_token_amount)?;
+    let penalty_amount = main_state.early_unstake_penalty(redeemed_mushi_token_amount, stake_entry.last_staked, now)?;
+    let mushi_token_amount = redeemed_mushi_token_amount.checked_sub(penalty_amount).ok_or(MushiStakeVaultError::MathOverflow)?;
+    let eclipse_token_amount = stake_entry.pro_rata(stake_entry.eclipse_token_staked_amount, stake_token_amount)?;
+    let mushi_token_principal = stake_entry.pro_rata(stake_entry.mushi_token_staked_amount, stake_token_amount)?;
+    require!(
+        mushi_token_amount >= input.min_mushi_token_amount && eclipse_token_amount >= input.min_eclipse_token_amount,
+        MushiStakeVaultError::UnstakeSlippageExceeded
+    );
+
+    // Whether the MUSHI leaves now or waits out a cooldown, the vault has to
+    // hold it on top of a treasury-bound penalty and earlier pending unstakes.
+    let treasury_penalty_amount = match main_state.penalty_destination {
+        PenaltyDestination::Treasury => penalty_amount,
+        PenaltyDestination::Vault => 0,
+    };
+    let required_mushi_token_amount =
+        mushi_token_amount as u128 + treasury_penalty_amount as u128 + main_state.pending_unstake_mushi_amount as u128;
+    require!(
+        required_mushi_token_amount <= mushi_token_vault_amount as u128,
+        MushiStakeVaultError::InsufficientMushiTokenAmount
+    );
+
+    main_state.update_rewards(now);
+    stake_entry.settle_rewards(main_state.acc_reward_per_share);
+
+    main_state.mushi_token_amount = main_state.mushi_token_amount.checked_sub(redeemed_mushi_token_amount).ok_or(MushiStakeVaultError::MathOverflow)?;
+    if main_state.penalty_destination == PenaltyDestination::Vault {
+        main_state.mushi_token_amount = main_state.mushi_token_amount.checked_add(penalty_amount).ok_or(MushiStakeVaultError::MathOverflow)?;
     }
-    
-    let eclipse_token_amount = input.amount;
-    let mushi_token_amount = input.amount;
+    main_state.eclipse_token_amount = main_state.eclipse_token_amount.checked_sub(eclipse_token_amount).ok_or(MushiStakeVaultError::MathOverflow)?;
+    main_state.staking_token_total_supply = main_state.staking_token_total_supply.checked_sub(stake_token_amount).ok_or(MushiStakeVaultError::MathOverflow)?;
+
+    stake_entry.mushi_token_staked_amount = stake_entry.mushi_token_staked_amount.checked_sub(mushi_token_principal).ok_or(MushiStakeVaultError::MathOverflow)?;
+    stake_entry.eclipse_token_staked_amount = stake_entry.eclipse_token_staked_amount.checked_sub(eclipse_token_amount).ok_or(MushiStakeVaultError::MathOverflow)?;
+    stake_entry.stake_token_balance = stake_entry.stake_token_balance.checked_sub(stake_token_amount).ok_or(MushiStakeVaultError::MathOverflow)?;
+
+    Ok(UnstakeAmounts {
+        mushi_token_amount,
+        eclipse_token_amount,
+        penalty_amount,
+    })
+}
+
+/// Returned by `unstake` as return data for CPI callers.
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct UnstakeResult {
+    pub stake_token_amount: u64,
+    /// MUSHI sent to the user, net of `penalty_amount`.
+    pub mushi_token_amount: u64,
+    /// Eclipse returned to the entry's `eclipse_token_source`, before any
+    /// Token-2022 transfer fee charged to the receiver.
+    pub eclipse_token_amount: u64,
+    pub penalty_amount: u64,
+    pub total_mushi_token_amount: u64,
+    pub total_eclipse_token_amount: u64,
+    pub staking_token_total_supply: u64,
+}
+
+fn unstake_result(main_state: &MainState, stake_token_amount: u64, amounts: &UnstakeAmounts) -> UnstakeResult {
+    UnstakeResult {
+        stake_token_amount,
+        mushi_token_amount: amounts.mushi_token_amount,
+        eclipse_token_amount: amounts.eclipse_token_amount,
+        penalty_amount: amounts.penalty_amount,
+        total_mushi_token_amount: main_state.mushi_token_amount,
+        total_eclipse_token_amount: main_state.eclipse_token_amount,
+        staking_token_total_supply: main_state.staking_token_total_supply,
+    }
+}
+
+/// `settle_unstake` run on copies of the pool and the user's entry: what
+/// `unstake` at `now` would return, slippage and vault checks included. Used
+/// by `preview_unstake`.
+pub fn quote_unstake(
+    main_state: &MainState,
+    stake_entry: &StakeEntry,
+    input: &UnstakeInput,
+    mushi_token_vault_amount: u64,
+    now: i64,
+) -> Result<UnstakeResult> {
+    let mut main_state = main_state.clone();
+    let mut stake_entry = stake_entry.clone();
+    let amounts = settle_unstake(&mut main_state, &mut stake_entry, input, mushi_token_vault_amount, now)?;
+    Ok(unstake_result(&main_state, input.amount, &amounts))
+}
+
+pub fn handler(ctx: Context<Unstake>, input: UnstakeInput) -> Result<UnstakeResult> {
+    ctx.accounts.main_state.require_not_paused(PAUSE_UNSTAKE)?;
+
+    require!(ctx.accounts.main_state.unstake_cooldown == 0, MushiStakeVaultError::UnstakeCooldownActive);
+
     let stake_token_amount = input.amount;
+    let now = Clock::get()?.unix_timestamp;
+    let amounts = settle_unstake(
+        &mut ctx.accounts.main_state,
+        &mut ctx.accounts.stake_entry,
+        &input,
+        ctx.accounts.mushi_token_vault.amount,
+        now,
+    )?;
+    let UnstakeAmounts {
+        mushi_token_amount,
+        eclipse_token_amount,
+        penalty_amount,
+    } = amounts;
 
-    require!(mushi_token_amount <= ctx.accounts.mushi_token_vault.amount, MushiStakeVaultError::InsufficientMushiTokenAmount);
-    
+    let main_state_key = ctx.accounts.main_state.key();
     let bump = *ctx.bumps.get("staking_program_token_vault_owner").unwrap();
-    let signer_seeds: &[&[&[u8]]] = &[&[VAULT_OWNER_SEED, &[bump]]];
+    let signer_seeds: &[&[&[u8]]] = &[&[VAULT_OWNER_SEED, main_state_key.as_ref(), &[bump]]];
+
+    if penalty_amount > 0 && ctx.accounts.main_state.penalty_destination == PenaltyDestination::Treasury {
+        let treasury_mushi_token_ata = ctx.accounts.treasury_mushi_token_ata.as_ref()
+            .ok_or(MushiStakeVaultError::InvalidTreasuryAccount)?;
+        transfer_tokens(
+            TransferTokenInput {
+                from: ctx.accounts.mushi_token_vault.to_account_info(),
+                to: treasury_mushi_token_ata.to_account_info(),
+                authority: ctx.accounts.staking_program_token_vault_owner.to_account_info(),
+                mint: ctx.accounts.mushi_token_mint.to_account_info(),
+                token_program: ctx.accounts.token_program.to_account_info(),
+                amount: penalty_amount,
+                decimals: ctx.accounts.mushi_token_mint.decimals,
+            },
+            Some(signer_seeds),
+        )?;
+    }
 
     transfer_tokens(
         TransferTokenInput {
             from: ctx.accounts.mushi_token_vault.to_account_info(),
             to: ctx.accounts.user_mushi_token_ata.to_account_info(),
             authority: ctx.accounts.staking_program_token_vault_owner.to_account_info(),
+            mint: ctx.accounts.mushi_token_mint.to_account_info(),
             token_program: ctx.accounts.token_program.to_account_info(),
             amount: mushi_token_amount,
+            decimals: ctx.accounts.mushi_token_mint.decimals,
         },
         Some(signer_seeds),
     )?;
 
-    transfer_token_2022(
-        TransferToken2022Input {
-            from: ctx.accounts.eclipse_token_staking_program_vault.to_account_info(),
-            to: ctx.accounts.eclipse_token_mushi_program_vault.to_account_info(),
-            authority: ctx.accounts.staking_program_token_vault_owner.to_account_info(),
-            mint: ctx.accounts.eclipse_token_mint.to_account_info(),
-            token_program: ctx.accounts.token2022_program.to_account_info(),
-            amount: eclipse_token_amount,
-            decimals: ctx.accounts.eclipse_token_mint.decimals,
-        },
-        Some(signer_seeds),
-    )?;
+    if eclipse_token_amount > 0 {
+        let eclipse_token_source = ctx.accounts.eclipse_token_source.as_ref()
+            .ok_or(MushiStakeVaultError::MissingEclipseTokenAccount)?;
+        transfer_tokens(
+            TransferTokenInput {
+                from: ctx.accounts.eclipse_token_staking_program_vault.to_account_info(),
+                to: eclipse_token_source.to_account_info(),
+                authority: ctx.accounts.staking_program_token_vault_owner.to_account_info(),
+                mint: ctx.accounts.eclipse_token_mint.to_account_info(),
+                token_program: ctx.accounts.token2022_program.to_account_info(),
+                amount: eclipse_token_amount,
+                decimals: ctx.accounts.eclipse_token_mint.decimals,
+            },
+            Some(signer_seeds),
+        )?;
+    }
 
+    let stake_token_account = StakeTokenAccount {
+        account: ctx.accounts.user_stake_token_ata.to_account_info(),
+        mint: ctx.accounts.stake_token_mint.to_account_info(),
+        main_state: ctx.accounts.main_state.to_account_info(),
+        token_program: ctx.accounts.token_program.to_account_info(),
+        pool_id: ctx.accounts.main_state.pool_id,
+        main_state_bump: *ctx.bumps.get("main_state").unwrap(),
+    };
+    if ctx.accounts.user_stake_token_ata.is_frozen() {
+        stake_token_account.thaw()?;
+    }
     burn_tokens(
         ctx.accounts.user_stake_token_ata.to_account_info(),
         ctx.accounts.stake_token_mint.to_account_info(),
@@ -82,13 +223,26 @@ pub fn handler(ctx: Context<Unstake>, input: UnstakeInput) -> Result<()> {
         stake_token_amount,
         None,
     )?;
+    // An emptied account is left thawed so the user can close it.
+    if ctx.accounts.user_stake_token_ata.amount > stake_token_amount {
+        stake_token_account.freeze()?;
+    }
 
-    
-    let main_state = &mut ctx.accounts.main_state;
-    main_state.mushi_token_amount -= mushi_token_amount;
-    main_state.eclipse_token_amount -= eclipse_token_amount;
-    main_state.staking_token_total_supply -= stake_token_amount;
-    Ok(())
+    let main_state = &ctx.accounts.main_state;
+    emit!(Unstaked {
+        main_state: main_state.key(),
+        user: ctx.accounts.user.key(),
+        mushi_token_amount,
+        eclipse_token_amount,
+        stake_token_amount,
+        penalty_amount,
+        unlock_time: None,
+        total_mushi_token_amount: main_state.mushi_token_amount,
+        total_eclipse_token_amount: main_state.eclipse_token_amount,
+        staking_token_total_supply: main_state.staking_token_total_supply,
+        timestamp: now,
+    });
+    Ok(unstake_result(main_state, stake_token_amount, &amounts))
 }
 
 #[derive(Accounts)]
@@ -97,35 +251,45 @@ pub struct Unstake<'info> {
     pub user: Signer<'info>,
     #[account(    
         mut,
-        seeds = [MainState::PREFIX_SEED],
+        seeds = [MainState::PREFIX_SEED, main_state.pool_id.to_le_bytes().as_ref()],
         bump,
     )]
     pub main_state: Box<Account<'info, MainState>>,
-    /// CHECK: This is the Solana instructions sysvar
-    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
-    pub instruction_sysvar: AccountInfo<'info>,
     #[account(
         mut,
-        associated_token::mint = mushi_token_mint,
-        associated_token::authority = user,
-        associated_token::token_program = token_program,
+        seeds = [STAKE_ENTRY_SEED, main_state.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub stake_entry: Box<Account<'info, StakeEntry>>,
+    #[account(
+        mut,
+        token::mint = mushi_token_mint,
+        token::authority = user,
+        token::token_program = token_program,
     )]
     pub user_mushi_token_ata: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
     #[account(
-        init_if_needed,
-        payer = user,
-        associated_token::mint = stake_token_mint,
-        associated_token::authority = user,
-        associated_token::token_program = token_program,
+        mut,
+        token::mint = stake_token_mint,
+        token::authority = user,
+        token::token_program = token_program,
     )]
     pub user_stake_token_ata: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
     #[account(
         mut,
-        associated_token::mint = mushi_token_mint,
-        associated_token::authority = staking_program_token_vault_owner,
-        associated_token::token_program = token_program,
+        token::mint = mushi_token_mint,
+        token::authority = staking_program_token_vault_owner,
+        token::token_program = token_program,
+        address = get_associated_token_address_with_program_id(&staking_program_token_vault_owner.key(), &mushi_token_mint.key(), &token_program.key()),
     )]
     pub mushi_token_vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    #[account(
+        mut,
+        token::mint = mushi_token_mint,
+        token::token_program = token_program,
+        constraint = treasury_mushi_token_ata.owner == main_state.treasury @ MushiStakeVaultError::InvalidTreasuryAccount,
+    )]
+    pub treasury_mushi_token_ata: Option<Box<InterfaceAccount<'info, token_interface::TokenAccount>>>,
     #[account(
         mut,
         mint::token_program = token_program,
@@ -137,15 +301,17 @@ pub struct Unstake<'info> {
         token::mint = eclipse_token_mint,
         token::authority = staking_program_token_vault_owner,
         token::token_program = token2022_program,
+        address = get_associated_token_address_with_program_id(&staking_program_token_vault_owner.key(), &eclipse_token_mint.key(), &token2022_program.key()),
     )]
     pub eclipse_token_staking_program_vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    /// `stake_entry.eclipse_token_source`; required when Eclipse is returned.
     #[account(
         mut,
+        address = stake_entry.eclipse_token_source @ MushiStakeVaultError::EclipseSourceMismatch,
         token::mint = eclipse_token_mint,
-        token::authority = mushi_program_token_vault_owner,
         token::token_program = token2022_program,
     )]
-    pub eclipse_token_mushi_program_vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    pub eclipse_token_source: Option<Box<InterfaceAccount<'info, token_interface::TokenAccount>>>,
     #[account(
         mut,
         mint::token_program = token2022_program,
@@ -160,17 +326,10 @@ pub struct Unstake<'info> {
     pub stake_token_mint: InterfaceAccount<'info, token_interface::Mint>,
     #[account(
         mut,
-        seeds = [VAULT_OWNER_SEED],
+        seeds = [VAULT_OWNER_SEED, main_state.key().as_ref()],
         bump,
     )]
     pub staking_program_token_vault_owner: SystemAccount<'info>,
-    #[account(
-        mut,
-        seeds = [MUSHIPROGRAM_VAULT_SEED],
-        bump,
-        seeds::program = main_state.mushi_program,
-    )]
-    pub mushi_program_token_vault_owner: SystemAccount<'info>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub token_program: Interface<'info, token_interface::TokenInterface>,
     pub token2022_program: Interface<'info, token_interface::TokenInterface>,
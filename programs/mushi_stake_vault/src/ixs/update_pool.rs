@@ -1,14 +1,86 @@
 use anchor_lang::prelude::*;
-use crate::{state::MainState, errors::MushiStakeVaultError};
+use crate::{state::{AccessMode, MainState, EclipseDepositMode, PenaltyCurve, PenaltyDestination, BASIS_POINTS_DIVISOR, PAUSE_ADMIN}, errors::MushiStakeVaultError, events::PoolUpdated};
 
 #[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone)]
 pub struct UpdatePoolInput {
     pub mushi_program: Pubkey,
+    pub access_mode: Option<AccessMode>,
+    /// Key allowed to pause alongside the admin.
+    pub guardian: Option<Pubkey>,
+    /// Seconds between `request_unstake` and `withdraw_unstaked`; `None` keeps the current value.
+    pub unstake_cooldown: Option<i64>,
+    /// Penalty charged on an unstake made right after staking, in basis points.
+    pub early_unstake_penalty_bps: Option<u16>,
+    /// Seconds after `StakeEntry::last_staked` until the penalty reaches zero.
+    pub penalty_decay_period: Option<i64>,
+    pub penalty_curve: Option<PenaltyCurve>,
+    pub penalty_destination: Option<PenaltyDestination>,
+    pub treasury: Option<Pubkey>,
+    pub eclipse_deposit_mode: Option<EclipseDepositMode>,
+    /// Whole Eclipse tokens required per whole MUSHI token on `stake`, in basis points.
+    pub eclipse_per_mushi_bps: Option<u64>,
 }
 
 pub fn handler(ctx: Context<UpdatePool>, input: UpdatePoolInput) -> Result<()> {
+    ctx.accounts.main_state.require_not_paused(PAUSE_ADMIN)?;
+
     let main_state = &mut ctx.accounts.main_state;
     main_state.mushi_program = input.mushi_program;
+    if let Some(access_mode) = input.access_mode {
+        main_state.access_mode = access_mode;
+    }
+    if let Some(guardian) = input.guardian {
+        main_state.guardian = guardian;
+    }
+    if let Some(unstake_cooldown) = input.unstake_cooldown {
+        require!(unstake_cooldown >= 0, MushiStakeVaultError::InvalidUnstakeCooldown);
+        main_state.unstake_cooldown = unstake_cooldown;
+    }
+    if let Some(early_unstake_penalty_bps) = input.early_unstake_penalty_bps {
+        require!(early_unstake_penalty_bps as u64 <= BASIS_POINTS_DIVISOR, MushiStakeVaultError::InvalidPenaltyConfig);
+        main_state.early_unstake_penalty_bps = early_unstake_penalty_bps;
+    }
+    if let Some(penalty_decay_period) = input.penalty_decay_period {
+        require!(penalty_decay_period >= 0, MushiStakeVaultError::InvalidPenaltyConfig);
+        main_state.penalty_decay_period = penalty_decay_period;
+    }
+    if let Some(penalty_curve) = input.penalty_curve {
+        main_state.penalty_curve = penalty_curve;
+    }
+    if let Some(penalty_destination) = input.penalty_destination {
+        main_state.penalty_destination = penalty_destination;
+    }
+    if let Some(treasury) = input.treasury {
+        main_state.treasury = treasury;
+    }
+    if let Some(eclipse_deposit_mode) = input.eclipse_deposit_mode {
+        main_state.eclipse_deposit_mode = eclipse_deposit_mode;
+    }
+    if let Some(eclipse_per_mushi_bps) = input.eclipse_per_mushi_bps {
+        require!(eclipse_per_mushi_bps > 0, MushiStakeVaultError::InvalidDepositRatio);
+        main_state.eclipse_per_mushi_bps = eclipse_per_mushi_bps;
+    }
+    require!(
+        main_state.penalty_destination != PenaltyDestination::Treasury || main_state.treasury != Pubkey::default(),
+        MushiStakeVaultError::InvalidTreasuryAccount
+    );
+
+    emit!(PoolUpdated {
+        main_state: main_state.key(),
+        admin: main_state.admin,
+        mushi_program: main_state.mushi_program,
+        access_mode: main_state.access_mode,
+        guardian: main_state.guardian,
+        unstake_cooldown: main_state.unstake_cooldown,
+        early_unstake_penalty_bps: main_state.early_unstake_penalty_bps,
+        penalty_decay_period: main_state.penalty_decay_period,
+        penalty_curve: main_state.penalty_curve,
+        penalty_destination: main_state.penalty_destination,
+        treasury: main_state.treasury,
+        eclipse_deposit_mode: main_state.eclipse_deposit_mode,
+        eclipse_per_mushi_bps: main_state.eclipse_per_mushi_bps,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
     Ok(())
 }
 
@@ -21,7 +93,7 @@ pub struct UpdatePool<'info> {
     pub admin: Signer<'info>,
     #[account(
         mut,
-        seeds = [MainState::PREFIX_SEED],
+        seeds = [MainState::PREFIX_SEED, main_state.pool_id.to_le_bytes().as_ref()],
         bump,
     )]
     pub main_state: Box<Account<'info, MainState>>,
@@ -0,0 +1,132 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{associated_token::get_associated_token_address_with_program_id, token_interface};
+use crate::{
+    errors::MushiStakeVaultError,
+    events::UnstakeWithdrawn, state::{MainState, UnstakeRequest, PAUSE_UNSTAKE, UNSTAKE_REQUEST_SEED, VAULT_OWNER_SEED}, utils::{transfer_tokens, TransferTokenInput}
+};
+
+pub fn handler(ctx: Context<WithdrawUnstaked>) -> Result<()> {
+    ctx.accounts.main_state.require_not_paused(PAUSE_UNSTAKE)?;
+
+    let unstake_request = &ctx.accounts.unstake_request;
+    let mushi_token_amount = unstake_request.mushi_token_amount;
+    let eclipse_token_amount = unstake_request.eclipse_token_amount;
+    require!(mushi_token_amount > 0 || eclipse_token_amount > 0, MushiStakeVaultError::NoPendingUnstake);
+    let now = Clock::get()?.unix_timestamp;
+    require!(now >= unstake_request.unlock_time, MushiStakeVaultError::UnstakeStillLocked);
+
+    let main_state_key = ctx.accounts.main_state.key();
+    let bump = *ctx.bumps.get("staking_program_token_vault_owner").unwrap();
+    let signer_seeds: &[&[&[u8]]] = &[&[VAULT_OWNER_SEED, main_state_key.as_ref(), &[bump]]];
+
+    transfer_tokens(
+        TransferTokenInput {
+            from: ctx.accounts.mushi_token_vault.to_account_info(),
+            to: ctx.accounts.user_mushi_token_ata.to_account_info(),
+            authority: ctx.accounts.staking_program_token_vault_owner.to_account_info(),
+            mint: ctx.accounts.mushi_token_mint.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+            amount: mushi_token_amount,
+            decimals: ctx.accounts.mushi_token_mint.decimals,
+        },
+        Some(signer_seeds),
+    )?;
+
+    if eclipse_token_amount > 0 {
+        let eclipse_token_source = ctx.accounts.eclipse_token_source.as_ref()
+            .ok_or(MushiStakeVaultError::MissingEclipseTokenAccount)?;
+        transfer_tokens(
+            TransferTokenInput {
+                from: ctx.accounts.eclipse_token_staking_program_vault.to_account_info(),
+                to: eclipse_token_source.to_account_info(),
+                authority: ctx.accounts.staking_program_token_vault_owner.to_account_info(),
+                mint: ctx.accounts.eclipse_token_mint.to_account_info(),
+                token_program: ctx.accounts.token2022_program.to_account_info(),
+                amount: eclipse_token_amount,
+                decimals: ctx.accounts.eclipse_token_mint.decimals,
+            },
+            Some(signer_seeds),
+        )?;
+    }
+
+    let main_state = &mut ctx.accounts.main_state;
+    main_state.pending_unstake_mushi_amount = main_state.pending_unstake_mushi_amount.checked_sub(mushi_token_amount).ok_or(MushiStakeVaultError::MathOverflow)?;
+    main_state.pending_unstake_eclipse_amount = main_state.pending_unstake_eclipse_amount.checked_sub(eclipse_token_amount).ok_or(MushiStakeVaultError::MathOverflow)?;
+
+    emit!(UnstakeWithdrawn {
+        main_state: main_state.key(),
+        user: ctx.accounts.user.key(),
+        mushi_token_amount,
+        eclipse_token_amount,
+        timestamp: now,
+    });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct WithdrawUnstaked<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [MainState::PREFIX_SEED, main_state.pool_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub main_state: Box<Account<'info, MainState>>,
+    // Paid out in full, so the request is closed and its rent returned.
+    #[account(
+        mut,
+        seeds = [UNSTAKE_REQUEST_SEED, main_state.key().as_ref(), user.key().as_ref()],
+        bump,
+        close = user,
+    )]
+    pub unstake_request: Box<Account<'info, UnstakeRequest>>,
+    #[account(
+        mut,
+        token::mint = mushi_token_mint,
+        token::authority = user,
+        token::token_program = token_program,
+    )]
+    pub user_mushi_token_ata: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    #[account(
+        mut,
+        token::mint = mushi_token_mint,
+        token::authority = staking_program_token_vault_owner,
+        token::token_program = token_program,
+        address = get_associated_token_address_with_program_id(&staking_program_token_vault_owner.key(), &mushi_token_mint.key(), &token_program.key()),
+    )]
+    pub mushi_token_vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    #[account(
+        mint::token_program = token_program,
+        address = main_state.mushi_token_mint,
+    )]
+    pub mushi_token_mint: InterfaceAccount<'info, token_interface::Mint>,
+    #[account(
+        mut,
+        token::mint = eclipse_token_mint,
+        token::authority = staking_program_token_vault_owner,
+        token::token_program = token2022_program,
+        address = get_associated_token_address_with_program_id(&staking_program_token_vault_owner.key(), &eclipse_token_mint.key(), &token2022_program.key()),
+    )]
+    pub eclipse_token_staking_program_vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    /// `unstake_request.eclipse_token_source`; required when Eclipse is withdrawn.
+    #[account(
+        mut,
+        address = unstake_request.eclipse_token_source @ MushiStakeVaultError::EclipseSourceMismatch,
+        token::mint = eclipse_token_mint,
+        token::token_program = token2022_program,
+    )]
+    pub eclipse_token_source: Option<Box<InterfaceAccount<'info, token_interface::TokenAccount>>>,
+    #[account(
+        mint::token_program = token2022_program,
+        address = main_state.eclipse_token_mint,
+    )]
+    pub eclipse_token_mint: InterfaceAccount<'info, token_interface::Mint>,
+    #[account(
+        seeds = [VAULT_OWNER_SEED, main_state.key().as_ref()],
+        bump,
+    )]
+    pub staking_program_token_vault_owner: SystemAccount<'info>,
+    pub token_program: Interface<'info, token_interface::TokenInterface>,
+    pub token2022_program: Interface<'info, token_interface::TokenInterface>,
+}
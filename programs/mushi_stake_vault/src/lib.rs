@@ -1,13 +1,26 @@
+#![allow(clippy::result_large_err)]
+
 pub mod state;
 pub mod ixs;
 pub mod utils;
 pub mod errors;
+pub mod events;
+#[cfg(feature = "cpi")]
+pub mod return_data;
 
 use anchor_lang::prelude::*;
-use ixs::{init_pool::*, stake::*, unstake::*, update_pool::*};
-pub use ixs::stake::StakeInput;
-pub use ixs::unstake::UnstakeInput;
+use ixs::{accept_admin::*, add_to_allowlist::*, audit_pool::*, cancel_admin_transfer::*, claim_rewards::*, fund_rewards::*, get_pool_stats::*, get_user_position::*, init_pool::*, preview_stake::*, preview_unstake::*, propose_admin::*, reconcile_pool::*, remove_from_allowlist::*, request_unstake::*, set_pause::*, set_reward_rate::*, stake::*, unstake::*, update_pool::*, withdraw_unstaked::*};
+pub use ixs::stake::{StakeInput, StakeResult};
+pub use ixs::unstake::{UnstakeInput, UnstakeResult};
 pub use ixs::update_pool::UpdatePoolInput;
+pub use ixs::fund_rewards::FundRewardsInput;
+pub use ixs::set_reward_rate::SetRewardRateInput;
+pub use ixs::propose_admin::ProposeAdminInput;
+pub use ixs::set_pause::SetPauseInput;
+pub use ixs::reconcile_pool::ReconcilePoolInput;
+pub use ixs::get_pool_stats::PoolStats;
+pub use ixs::get_user_position::UserPosition;
+pub use ixs::preview_unstake::UnstakePreview;
 declare_id!("Bne2XHWW1HaMVHp6jXmCcmX3dVrtFMoYV5n2eyrvFw46");
 
 #[program]
@@ -18,15 +31,83 @@ pub mod mushi_stake_vault {
         ixs::init_pool(ctx, input)
     }
 
-    pub fn stake(ctx: Context<Stake>, input: StakeInput) -> Result<()> {
+    pub fn stake(ctx: Context<Stake>, input: StakeInput) -> Result<StakeResult> {
         ixs::stake::handler(ctx, input)
     }
 
-    pub fn unstake(ctx: Context<Unstake>, input: UnstakeInput) -> Result<()> {
+    pub fn unstake(ctx: Context<Unstake>, input: UnstakeInput) -> Result<UnstakeResult> {
         ixs::unstake::handler(ctx, input)
     }
     
     pub fn update_pool(ctx: Context<UpdatePool>, input: UpdatePoolInput) -> Result<()> {
         ixs::update_pool::handler(ctx, input)
     }
+
+    pub fn claim_rewards(ctx: Context<ClaimRewards>) -> Result<()> {
+        ixs::claim_rewards::handler(ctx)
+    }
+
+    pub fn fund_rewards(ctx: Context<FundRewards>, input: FundRewardsInput) -> Result<()> {
+        ixs::fund_rewards::handler(ctx, input)
+    }
+
+    pub fn set_reward_rate(ctx: Context<SetRewardRate>, input: SetRewardRateInput) -> Result<()> {
+        ixs::set_reward_rate::handler(ctx, input)
+    }
+
+    pub fn request_unstake(ctx: Context<RequestUnstake>, input: UnstakeInput) -> Result<()> {
+        ixs::request_unstake::handler(ctx, input)
+    }
+
+    pub fn withdraw_unstaked(ctx: Context<WithdrawUnstaked>) -> Result<()> {
+        ixs::withdraw_unstaked::handler(ctx)
+    }
+
+    pub fn add_to_allowlist(ctx: Context<AddToAllowlist>) -> Result<()> {
+        ixs::add_to_allowlist::handler(ctx)
+    }
+
+    pub fn remove_from_allowlist(ctx: Context<RemoveFromAllowlist>) -> Result<()> {
+        ixs::remove_from_allowlist::handler(ctx)
+    }
+
+    pub fn propose_admin(ctx: Context<ProposeAdmin>, input: ProposeAdminInput) -> Result<()> {
+        ixs::propose_admin::handler(ctx, input)
+    }
+
+    pub fn accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
+        ixs::accept_admin::handler(ctx)
+    }
+
+    pub fn cancel_admin_transfer(ctx: Context<CancelAdminTransfer>) -> Result<()> {
+        ixs::cancel_admin_transfer::handler(ctx)
+    }
+
+    pub fn set_pause(ctx: Context<SetPause>, input: SetPauseInput) -> Result<()> {
+        ixs::set_pause::handler(ctx, input)
+    }
+
+    pub fn audit_pool(ctx: Context<AuditPool>) -> Result<()> {
+        ixs::audit_pool::handler(ctx)
+    }
+
+    pub fn reconcile_pool(ctx: Context<ReconcilePool>, input: ReconcilePoolInput) -> Result<()> {
+        ixs::reconcile_pool::handler(ctx, input)
+    }
+
+    pub fn get_pool_stats(ctx: Context<GetPoolStats>) -> Result<PoolStats> {
+        ixs::get_pool_stats::handler(ctx)
+    }
+
+    pub fn get_user_position(ctx: Context<GetUserPosition>) -> Result<UserPosition> {
+        ixs::get_user_position::handler(ctx)
+    }
+
+    pub fn preview_stake(ctx: Context<PreviewStake>, input: StakeInput) -> Result<StakeResult> {
+        ixs::preview_stake::handler(ctx, input)
+    }
+
+    pub fn preview_unstake(ctx: Context<PreviewUnstake>, input: UnstakeInput) -> Result<UnstakePreview> {
+        ixs::preview_unstake::handler(ctx, input)
+    }
 }
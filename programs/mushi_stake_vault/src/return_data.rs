@@ -0,0 +1,22 @@
+//! Decoders for the return data `stake` and `unstake` leave behind, for
+//! callers that invoke them with a hand-built instruction instead of
+//! `cpi::stake` / `cpi::unstake`.
+use anchor_lang::{prelude::*, solana_program::program::get_return_data};
+
+use crate::{errors::MushiStakeVaultError, StakeResult, UnstakeResult};
+
+fn decode<T: AnchorDeserialize>() -> Result<T> {
+    let (program_id, data) = get_return_data().ok_or(MushiStakeVaultError::MissingReturnData)?;
+    require_keys_eq!(program_id, crate::ID, MushiStakeVaultError::MissingReturnData);
+    T::try_from_slice(&data).map_err(|_| error!(MushiStakeVaultError::MissingReturnData))
+}
+
+/// Result of a `stake` CPI; call straight after it, before any other invoke.
+pub fn stake_result() -> Result<StakeResult> {
+    decode()
+}
+
+/// Result of an `unstake` CPI; call straight after it, before any other invoke.
+pub fn unstake_result() -> Result<UnstakeResult> {
+    decode()
+}
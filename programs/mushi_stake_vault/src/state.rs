@@ -1,13 +1,106 @@
 use anchor_lang::prelude::*;
-pub const VAULT_OWNER_SEED: &'static [u8] = b"vault_owner";
-pub const VAULT_AUTH_SEED: &'static [u8] = b"vault_authority";
-pub const STAKE_ENTRY_SEED: &'static [u8] = b"stake_entry";
 
-pub const MUSHIPROGRAM_VAULT_SEED: &'static [u8] = b"vault";
+use crate::errors::MushiStakeVaultError;
+
+pub const VAULT_OWNER_SEED: &[u8] = b"vault_owner";
+pub const VAULT_AUTH_SEED: &[u8] = b"vault_authority";
+pub const STAKE_ENTRY_SEED: &[u8] = b"stake_entry";
+pub const REWARD_VAULT_SEED: &[u8] = b"reward_vault";
+pub const UNSTAKE_REQUEST_SEED: &[u8] = b"unstake_request";
+pub const ALLOWLIST_SEED: &[u8] = b"allowlist";
+
+pub const MUSHIPROGRAM_VAULT_SEED: &[u8] = b"vault";
+
+/// Virtual stake tokens and virtual MUSHI seeded into the exchange rate at pool
+/// creation so the first depositor gets 1:1 and the rate cannot be inflated by
+/// donating into an empty vault.
+pub const INITIAL_VIRTUAL_SHARES: u64 = 1_000_000_000;
+
+/// Bits of `MainState::paused`.
+pub const PAUSE_STAKE: u8 = 1 << 0;
+pub const PAUSE_UNSTAKE: u8 = 1 << 1;
+pub const PAUSE_CLAIM: u8 = 1 << 2;
+pub const PAUSE_ADMIN: u8 = 1 << 3;
+pub const PAUSE_ALL: u8 = PAUSE_STAKE | PAUSE_UNSTAKE | PAUSE_CLAIM | PAUSE_ADMIN;
+
+pub const BASIS_POINTS_DIVISOR: u64 = 10_000;
+
+/// Scale applied to `MainState::acc_reward_per_share` to keep precision on small rates.
+pub const REWARD_PRECISION: u128 = 1_000_000_000_000;
+
+/// Stake tokens quoted by `PoolStats::exchange_rate`.
+pub const EXCHANGE_RATE_PRECISION: u64 = 1_000_000_000;
+
+/// Narrows a `u128` intermediate back to a token amount.
+pub fn to_u64(value: u128) -> Result<u64> {
+    u64::try_from(value).map_err(|_| error!(MushiStakeVaultError::MathOverflow))
+}
+
+/// Who may open or grow a position through `stake`. Exits stay open to the
+/// position's owner in every mode, so changing the mode never traps funds.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AccessMode {
+    /// Anyone, signing for themselves.
+    Open,
+    /// Only `MainState::mushi_program`, via CPI signed by its vault PDA.
+    #[default]
+    ProgramGated,
+    /// Users holding an `AllowlistEntry` for the pool.
+    Allowlist,
+}
+
+/// Shape of the early-unstake penalty between `last_staked` and the end of
+/// `MainState::penalty_decay_period`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PenaltyCurve {
+    /// Falls linearly from the full penalty to zero.
+    #[default]
+    Linear,
+    /// Full penalty for the whole period, then zero.
+    Cliff,
+}
+
+/// Where early-unstake penalties end up.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PenaltyDestination {
+    /// Left in `mushi_token_vault`, raising the exchange rate for remaining stakers.
+    #[default]
+    Vault,
+    /// Sent to the MUSHI token account owned by `MainState::treasury`.
+    Treasury,
+}
+
+/// Where the Eclipse leg of a `stake` comes from.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EclipseDepositMode {
+    /// The user deposits Eclipse alongside MUSHI.
+    #[default]
+    Required,
+    /// The user may deposit Eclipse; without a user Eclipse account the stake is MUSHI only.
+    Optional,
+    /// Eclipse is pulled from the `MainState::mushi_program` vault, which must sign.
+    MushiProgramVault,
+}
+
+/// How `reconcile_pool` resolves MUSHI held by the vault beyond the counters,
+/// e.g. tokens sent to it directly. Other drift is left alone, and a vault or
+/// stake mint holding less than the counters fails the reconcile.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReconcileMode {
+    /// Add the surplus to `mushi_token_amount`, so it accrues to stakers.
+    CorrectCounters,
+    /// Move the surplus into the reward vault.
+    DonateSurplusToRewards,
+}
 
 #[account]
+#[derive(Default)]
 pub struct MainState {
+    pub pool_id: u64,
     pub admin: Pubkey,
+    pub pending_admin: Pubkey,
+    pub guardian: Pubkey,
+    pub paused: u8,
     pub mushi_token_amount: u64,
     pub eclipse_token_amount: u64,
     pub staking_token_total_supply: u64,
@@ -15,23 +108,188 @@ pub struct MainState {
     pub eclipse_token_mint: Pubkey,
     pub stake_token_mint: Pubkey,
     pub mushi_program: Pubkey,
+    pub access_mode: AccessMode,
+    pub virtual_shares: u64,
+    pub unstake_cooldown: i64,
+    pub pending_unstake_mushi_amount: u64,
+    pub pending_unstake_eclipse_amount: u64,
+    pub early_unstake_penalty_bps: u16,
+    pub penalty_decay_period: i64,
+    pub penalty_curve: PenaltyCurve,
+    pub penalty_destination: PenaltyDestination,
+    pub treasury: Pubkey,
+    pub eclipse_deposit_mode: EclipseDepositMode,
+    /// Whole Eclipse tokens deposited per whole MUSHI token, in basis points.
+    pub eclipse_per_mushi_bps: u64,
+    pub reward_rate: u64,
+    pub reward_start_time: i64,
+    pub reward_end_time: i64,
+    pub last_reward_update: i64,
+    pub acc_reward_per_share: u128,
 }
 
 impl MainState {
-    pub const PREFIX_SEED: &'static [u8] = b"main_state";
+    pub const PREFIX_SEED: &[u8] = b"main_state";
     pub const MAX_SIZE: usize = 8 + std::mem::size_of::<MainState>();
+
+    pub fn require_not_paused(&self, flag: u8) -> Result<()> {
+        require!(self.paused & flag == 0, MushiStakeVaultError::ProgramPaused);
+        Ok(())
+    }
+
+    /// Accrues `reward_rate` per second for the part of the emission window
+    /// `[reward_start_time, reward_end_time)` elapsed since the last update into
+    /// the global reward-per-stake-token accumulator. Emissions are spread over
+    /// the supply padded with `virtual_shares`, like the exchange rate, so a
+    /// dust-sized supply cannot inflate the accumulator; the virtual shares'
+    /// cut stays in the reward vault. Saturates instead of failing so reward
+    /// bookkeeping can never block an unstake.
+    pub fn update_rewards(&mut self, now: i64) {
+        if now <= self.last_reward_update {
+            return;
+        }
+        let from = self.last_reward_update.max(self.reward_start_time);
+        let to = now.min(self.reward_end_time);
+        let (total_supply, _) = self.exchange_rate_totals();
+        if to > from && total_supply > 0 {
+            let elapsed = (to as i128 - from as i128) as u128;
+            let accrued_per_share = elapsed
+                .saturating_mul(self.reward_rate as u128)
+                .saturating_mul(REWARD_PRECISION)
+                / total_supply;
+            self.acc_reward_per_share = self.acc_reward_per_share.saturating_add(accrued_per_share);
+        }
+        self.last_reward_update = now;
+    }
+
+    /// `staking_token_total_supply` and `mushi_token_amount`, each padded with
+    /// `virtual_shares`.
+    fn exchange_rate_totals(&self) -> (u128, u128) {
+        (
+            self.staking_token_total_supply as u128 + self.virtual_shares as u128,
+            self.mushi_token_amount as u128 + self.virtual_shares as u128,
+        )
+    }
+
+    /// Stake tokens minted for a MUSHI deposit at the current exchange rate,
+    /// `deposit * total_supply / total_underlying`, rounded down.
+    pub fn stake_tokens_for_deposit(&self, mushi_token_amount: u64) -> Result<u64> {
+        let (total_supply, total_underlying) = self.exchange_rate_totals();
+        require!(total_underlying > 0, MushiStakeVaultError::MathOverflow);
+        let stake_token_amount = (mushi_token_amount as u128)
+            .checked_mul(total_supply)
+            .ok_or(MushiStakeVaultError::MathOverflow)?;
+        to_u64(stake_token_amount / total_underlying)
+    }
+
+    /// MUSHI redeemed for burning `stake_token_amount` at the current exchange
+    /// rate, rounded down.
+    pub fn mushi_tokens_for_stake_tokens(&self, stake_token_amount: u64) -> Result<u64> {
+        let (total_supply, total_underlying) = self.exchange_rate_totals();
+        require!(total_supply > 0, MushiStakeVaultError::MathOverflow);
+        let mushi_token_amount = (stake_token_amount as u128)
+            .checked_mul(total_underlying)
+            .ok_or(MushiStakeVaultError::MathOverflow)?;
+        to_u64(mushi_token_amount / total_supply)
+    }
+
+    /// Eclipse that must accompany a `mushi_token_amount` deposit at
+    /// `eclipse_per_mushi_bps`, normalized from MUSHI to Eclipse decimals and
+    /// rounded down.
+    pub fn eclipse_tokens_for_deposit(&self, mushi_token_amount: u64, mushi_decimals: u8, eclipse_decimals: u8) -> Result<u64> {
+        let numerator = (mushi_token_amount as u128 * self.eclipse_per_mushi_bps as u128)
+            .checked_mul(10u128.checked_pow(eclipse_decimals as u32).ok_or(MushiStakeVaultError::MathOverflow)?)
+            .ok_or(MushiStakeVaultError::MathOverflow)?;
+        let denominator = 10u128
+            .checked_pow(mushi_decimals as u32)
+            .and_then(|scale| scale.checked_mul(BASIS_POINTS_DIVISOR as u128))
+            .ok_or(MushiStakeVaultError::MathOverflow)?;
+        to_u64(numerator / denominator)
+    }
+
+    /// Penalty charged on `amount` when unstaking at `now` a position last
+    /// topped up at `last_staked`.
+    pub fn early_unstake_penalty(&self, amount: u64, last_staked: i64, now: i64) -> Result<u64> {
+        let elapsed = now.saturating_sub(last_staked).max(0);
+        if self.early_unstake_penalty_bps == 0 || elapsed >= self.penalty_decay_period {
+            return Ok(0);
+        }
+        let penalty_bps = match self.penalty_curve {
+            PenaltyCurve::Cliff => self.early_unstake_penalty_bps as u128,
+            PenaltyCurve::Linear => {
+                let remaining = (self.penalty_decay_period - elapsed) as u128;
+                self.early_unstake_penalty_bps as u128 * remaining / self.penalty_decay_period as u128
+            }
+        };
+        to_u64(amount as u128 * penalty_bps / BASIS_POINTS_DIVISOR as u128)
+    }
 }
 
 #[account]
+#[derive(Default)]
 pub struct StakeEntry {
+    pub user: Pubkey,
     pub last_staked: i64,
     pub mushi_token_staked_amount: u64,
     pub eclipse_token_staked_amount: u64,
     pub stake_token_balance: u64,
     pub rewards_amount: u64,
+    /// `MainState::acc_reward_per_share` as of the entry's last settlement.
+    pub reward_per_share_paid: u128,
+    /// Token account the entry's Eclipse was staked from and is returned to:
+    /// the user's Eclipse account, or the `mushi_program` vault in
+    /// `EclipseDepositMode::MushiProgramVault`. Default until Eclipse is staked.
+    pub eclipse_token_source: Pubkey,
 }
 
 impl StakeEntry {
-    pub const PREFIX_SEED: &'static [u8] = b"stake_entry";
+    pub const PREFIX_SEED: &[u8] = b"stake_entry";
     pub const MAX_SIZE: usize = 8 + std::mem::size_of::<StakeEntry>();
+
+    /// Moves rewards earned on the current balance since the last settlement
+    /// into `rewards_amount`. Must run before `stake_token_balance` changes.
+    /// Only the accumulator's growth since then is multiplied by the balance,
+    /// and the result is capped at `u64::MAX` rather than erroring.
+    pub fn settle_rewards(&mut self, acc_reward_per_share: u128) {
+        let pending = (self.stake_token_balance as u128)
+            .saturating_mul(acc_reward_per_share.saturating_sub(self.reward_per_share_paid))
+            / REWARD_PRECISION;
+        self.rewards_amount = self.rewards_amount.saturating_add(u64::try_from(pending).unwrap_or(u64::MAX));
+        self.reward_per_share_paid = acc_reward_per_share;
+    }
+
+    /// Portion of `amount` attributable to `stake_token_amount` out of this
+    /// entry's `stake_token_balance`, rounded down.
+    pub fn pro_rata(&self, amount: u64, stake_token_amount: u64) -> Result<u64> {
+        if self.stake_token_balance == 0 {
+            return Ok(0);
+        }
+        to_u64(amount as u128 * stake_token_amount as u128 / self.stake_token_balance as u128)
+    }
+}
+
+/// Tokens released by `request_unstake` that become withdrawable at `unlock_time`.
+/// Closed by `withdraw_unstaked` once paid out.
+#[account]
+pub struct UnstakeRequest {
+    pub user: Pubkey,
+    pub mushi_token_amount: u64,
+    pub eclipse_token_amount: u64,
+    pub unlock_time: i64,
+    /// `StakeEntry::eclipse_token_source` of the entry the Eclipse was released from.
+    pub eclipse_token_source: Pubkey,
+}
+
+impl UnstakeRequest {
+    pub const MAX_SIZE: usize = 8 + std::mem::size_of::<UnstakeRequest>();
+}
+
+/// Marks `user` as allowed to stake while the pool is in `AccessMode::Allowlist`.
+#[account]
+pub struct AllowlistEntry {
+    pub user: Pubkey,
+}
+
+impl AllowlistEntry {
+    pub const MAX_SIZE: usize = 8 + std::mem::size_of::<AllowlistEntry>();
 }
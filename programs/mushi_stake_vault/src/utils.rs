@@ -1,19 +1,61 @@
-use crate::state::MainState;
+use crate::{
+    errors::MushiStakeVaultError,
+    state::{AccessMode, AllowlistEntry, MainState},
+};
 
 use anchor_lang::{
     prelude::*,
 };
 use anchor_spl::{
-    token::{self, Burn, MintTo, Transfer},
-    token_2022::{self, TransferChecked},
+    token_2022::{
+        self,
+        spl_token_2022::{
+            extension::{transfer_fee::TransferFeeConfig, BaseStateWithExtensions, ExtensionType, StateWithExtensions},
+            pod::pod_from_bytes,
+            state::{Account as TokenAccount, AccountState, Mint},
+        },
+        Burn, FreezeAccount, MintTo, ThawAccount, TransferChecked,
+    },
 };
 
+/// Verifies the call carries a signature from `mushi_program`'s
+/// `MUSHIPROGRAM_VAULT_SEED` PDA, which the accounts struct pins with
+/// `seeds::program = main_state.mushi_program`. Only `mushi_program` can
+/// produce it, through `invoke_signed`, so direct calls and calls merely placed
+/// next to a `mushi_program` instruction in the same transaction are rejected.
+pub fn verify_mushi_program_caller(mushi_program_authority: &AccountInfo) -> Result<()> {
+    require!(mushi_program_authority.is_signer, MushiStakeVaultError::UnauthorizedProgramCall);
+    Ok(())
+}
+
+/// Enforces `MainState::access_mode` for `stake`. The allowlist entry, when
+/// given, must already be constrained to `user`.
+pub fn verify_access(
+    main_state: &MainState,
+    user: &Pubkey,
+    mushi_program_authority: &AccountInfo,
+    allowlist_entry: Option<&AllowlistEntry>,
+) -> Result<()> {
+    match main_state.access_mode {
+        AccessMode::Open => Ok(()),
+        AccessMode::ProgramGated => verify_mushi_program_caller(mushi_program_authority),
+        AccessMode::Allowlist => {
+            require!(
+                allowlist_entry.is_some_and(|entry| entry.user == *user),
+                MushiStakeVaultError::NotAllowlisted
+            );
+            Ok(())
+        }
+    }
+}
+
 pub fn mint_to_tokens_by_main_state<'info>(
     mint: AccountInfo<'info>,
     authority: AccountInfo<'info>,
     receiver_ata: AccountInfo<'info>,
     token_program: AccountInfo<'info>,
     amount: u64,
+    pool_id: u64,
     bump: u8,
 ) -> Result<()> {
     let accounts = MintTo {
@@ -21,16 +63,62 @@ pub fn mint_to_tokens_by_main_state<'info>(
         mint,
         to: receiver_ata,
     };
-    token::mint_to(
+    token_2022::mint_to(
         CpiContext::new_with_signer(
             token_program,
             accounts,
-            &[&[MainState::PREFIX_SEED, &[bump]]],
+            &[&[MainState::PREFIX_SEED, &pool_id.to_le_bytes(), &[bump]]],
         ),
         amount,
     )
 }
 
+/// A user's stake token account, frozen by `main_state` as the stake mint's
+/// freeze authority whenever it holds stake tokens. Stake tokens are therefore
+/// non-transferable: `StakeEntry::stake_token_balance` always matches what the
+/// user holds, and only this program's mint and burn move it.
+pub struct StakeTokenAccount<'info> {
+    pub account: AccountInfo<'info>,
+    pub mint: AccountInfo<'info>,
+    pub main_state: AccountInfo<'info>,
+    pub token_program: AccountInfo<'info>,
+    pub pool_id: u64,
+    pub main_state_bump: u8,
+}
+
+impl<'info> StakeTokenAccount<'info> {
+    pub fn is_frozen(&self) -> Result<bool> {
+        let data = self.account.try_borrow_data()?;
+        Ok(StateWithExtensions::<TokenAccount>::unpack(&data)?.base.is_frozen())
+    }
+
+    pub fn freeze(&self) -> Result<()> {
+        let accounts = FreezeAccount {
+            account: self.account.clone(),
+            mint: self.mint.clone(),
+            authority: self.main_state.clone(),
+        };
+        token_2022::freeze_account(CpiContext::new_with_signer(
+            self.token_program.clone(),
+            accounts,
+            &[&[MainState::PREFIX_SEED, &self.pool_id.to_le_bytes(), &[self.main_state_bump]]],
+        ))
+    }
+
+    pub fn thaw(&self) -> Result<()> {
+        let accounts = ThawAccount {
+            account: self.account.clone(),
+            mint: self.mint.clone(),
+            authority: self.main_state.clone(),
+        };
+        token_2022::thaw_account(CpiContext::new_with_signer(
+            self.token_program.clone(),
+            accounts,
+            &[&[MainState::PREFIX_SEED, &self.pool_id.to_le_bytes(), &[self.main_state_bump]]],
+        ))
+    }
+}
+
 pub fn burn_tokens<'info>(
     token_account: AccountInfo<'info>,
     mint: AccountInfo<'info>,
@@ -45,51 +133,16 @@ pub fn burn_tokens<'info>(
         mint,
     };
     if let Some(signer_seeds) = signer_seeds {
-        token::burn(
+        token_2022::burn(
             CpiContext::new_with_signer(token_program, accounts, signer_seeds),
             amount,
         )
     } else {
-        token::burn(CpiContext::new(token_program, accounts), amount)
+        token_2022::burn(CpiContext::new(token_program, accounts), amount)
     }
 }
 
 pub struct TransferTokenInput<'info> {
-    pub from: AccountInfo<'info>,
-    pub to: AccountInfo<'info>,
-    pub authority: AccountInfo<'info>,
-    pub token_program: AccountInfo<'info>,
-    pub amount: u64,
-}
-
-pub fn transfer_tokens(
-    input: TransferTokenInput<'_>,
-    signer_seeds: Option<&[&[&[u8]]]>,
-) -> Result<()> {
-    let token_transfer_accounts = Transfer {
-        from: input.from,
-        to: input.to,
-        authority: input.authority,
-    };
-    if let Some(signer_seeds) = signer_seeds {
-        token::transfer(
-            CpiContext::new_with_signer(
-                input.token_program.clone(),
-                token_transfer_accounts,
-                signer_seeds,
-            ),
-            input.amount,
-        )?;
-    } else {
-        token::transfer(
-            CpiContext::new(input.token_program.clone(), token_transfer_accounts),
-            input.amount,
-        )?;
-    }
-    Ok(())
-}
-
-pub struct TransferToken2022Input<'info> {
     pub from: AccountInfo<'info>,
     pub to: AccountInfo<'info>,
     pub authority: AccountInfo<'info>,
@@ -99,8 +152,10 @@ pub struct TransferToken2022Input<'info> {
     pub decimals: u8,
 }
 
-pub fn transfer_token_2022(
-    input: TransferToken2022Input<'_>,
+/// `transfer_checked` through whichever token program owns the mint, legacy
+/// SPL Token or Token-2022.
+pub fn transfer_tokens(
+    input: TransferTokenInput<'_>,
     signer_seeds: Option<&[&[&[u8]]]>,
 ) -> Result<()> {
     let token_transfer_accounts = TransferChecked {
@@ -123,4 +178,69 @@ pub fn transfer_token_2022(
         )?;
     }
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Token-2022 mint extensions the vaults can hold, by type number. The
+/// metadata and group extensions (18-23) postdate the linked spl-token-2022,
+/// so mint TLV entries are matched by number instead of as `ExtensionType`.
+const SUPPORTED_MINT_EXTENSIONS: &[u16] = &[
+    ExtensionType::TransferFeeConfig as u16,
+    ExtensionType::MintCloseAuthority as u16,
+    ExtensionType::DefaultAccountState as u16,
+    ExtensionType::InterestBearingConfig as u16,
+    18, // MetadataPointer
+    19, // TokenMetadata
+    20, // GroupPointer
+    21, // TokenGroup
+    22, // GroupMemberPointer
+    23, // TokenGroupMember
+];
+
+/// `(type, value)` of each TLV entry of a mint; empty for legacy SPL mints.
+fn mint_extensions(mint_data: &[u8]) -> Result<Vec<(u16, &[u8])>> {
+    let mint_state = StateWithExtensions::<Mint>::unpack(mint_data)?;
+    let mut tlv_data = &mint_data[mint_data.len() - mint_state.get_tlv_data().len()..];
+    let mut extensions = Vec::new();
+    while tlv_data.len() >= 4 {
+        let extension_type = u16::from_le_bytes([tlv_data[0], tlv_data[1]]);
+        if extension_type == ExtensionType::Uninitialized as u16 {
+            break;
+        }
+        let length = usize::from(u16::from_le_bytes([tlv_data[2], tlv_data[3]]));
+        let value = tlv_data.get(4..4 + length).ok_or(ProgramError::InvalidAccountData)?;
+        extensions.push((extension_type, value));
+        tlv_data = &tlv_data[4 + length..];
+    }
+    Ok(extensions)
+}
+
+/// Accepts only mints whose extensions are all in `SUPPORTED_MINT_EXTENSIONS`
+/// and, with `DefaultAccountState`, leave new accounts unfrozen. Anything else
+/// (non-transferable, permanent delegate, transfer hook, pausable, ...) can
+/// freeze tokens in place or move them out of the vaults without this program.
+pub fn verify_supported_mint_extensions(mint: &AccountInfo) -> Result<()> {
+    let mint_data = mint.try_borrow_data()?;
+    for (extension_type, value) in mint_extensions(&mint_data)? {
+        require!(SUPPORTED_MINT_EXTENSIONS.contains(&extension_type), MushiStakeVaultError::UnsupportedMintExtension);
+        if extension_type == ExtensionType::DefaultAccountState as u16 {
+            require!(value == [AccountState::Initialized as u8], MushiStakeVaultError::UnsupportedMintExtension);
+        }
+    }
+    Ok(())
+}
+
+/// Fee withheld by the mint's `TransferFeeConfig` from a transfer of `amount`
+/// in the current epoch; zero when the mint has no transfer fee.
+pub fn transfer_fee(mint: &AccountInfo, amount: u64) -> Result<u64> {
+    let mint_data = mint.try_borrow_data()?;
+    let Some((_, value)) = mint_extensions(&mint_data)?
+        .into_iter()
+        .find(|(extension_type, _)| *extension_type == ExtensionType::TransferFeeConfig as u16)
+    else {
+        return Ok(0);
+    };
+    let fee = pod_from_bytes::<TransferFeeConfig>(value)?
+        .calculate_epoch_fee(Clock::get()?.epoch, amount)
+        .ok_or(MushiStakeVaultError::InvalidTransferFee)?;
+    Ok(fee)
+}
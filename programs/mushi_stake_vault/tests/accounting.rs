@@ -0,0 +1,274 @@
+#![allow(clippy::result_large_err)]
+
+use anchor_lang::prelude::*;
+use mushi_stake_vault::{
+    errors::MushiStakeVaultError,
+    ixs::{stake::quote_stake, unstake::{quote_unstake, settle_unstake}},
+    state::{
+        AccessMode, MainState, PenaltyCurve, PenaltyDestination, StakeEntry,
+        BASIS_POINTS_DIVISOR, INITIAL_VIRTUAL_SHARES, REWARD_PRECISION,
+    },
+    StakeInput, UnstakeInput,
+};
+use proptest::prelude::*;
+
+fn pool(mushi_token_amount: u64, staking_token_total_supply: u64) -> MainState {
+    MainState {
+        mushi_token_amount,
+        staking_token_total_supply,
+        access_mode: AccessMode::Open,
+        virtual_shares: INITIAL_VIRTUAL_SHARES,
+        eclipse_per_mushi_bps: BASIS_POINTS_DIVISOR,
+        ..Default::default()
+    }
+}
+
+fn entry(stake_token_balance: u64) -> StakeEntry {
+    StakeEntry { stake_token_balance, ..Default::default() }
+}
+
+fn assert_overflow<T: std::fmt::Debug>(result: Result<T>) {
+    match result {
+        Err(Error::AnchorError(err)) => assert_eq!(
+            err.error_code_number,
+            u32::from(MushiStakeVaultError::MathOverflow)
+        ),
+        other => panic!("expected MathOverflow, got {:?}", other),
+    }
+}
+
+#[test]
+fn first_deposit_of_u64_max_mints_one_to_one() {
+    assert_eq!(pool(0, 0).stake_tokens_for_deposit(u64::MAX).unwrap(), u64::MAX);
+}
+
+#[test]
+fn deposit_exceeding_u64_stake_tokens_overflows() {
+    // A drained vault with outstanding supply prices stake tokens far below
+    // one MUSHI, so a large deposit would mint more than u64::MAX.
+    assert_overflow(pool(0, u64::MAX - INITIAL_VIRTUAL_SHARES).stake_tokens_for_deposit(u64::MAX));
+}
+
+#[test]
+fn redeeming_more_than_u64_mushi_overflows() {
+    assert_overflow(pool(u64::MAX - INITIAL_VIRTUAL_SHARES, 0).mushi_tokens_for_stake_tokens(u64::MAX));
+}
+
+#[test]
+fn eclipse_ratio_with_extreme_decimals_overflows() {
+    let main_state = pool(0, 0);
+    assert_overflow(main_state.eclipse_tokens_for_deposit(1, 0, u8::MAX));
+    assert_overflow(main_state.eclipse_tokens_for_deposit(1, u8::MAX, 0));
+    assert_eq!(main_state.eclipse_tokens_for_deposit(u64::MAX, 9, 9).unwrap(), u64::MAX);
+}
+
+#[test]
+fn eclipse_ratio_normalizes_decimals() {
+    let mut main_state = pool(0, 0);
+    main_state.eclipse_per_mushi_bps = 2 * BASIS_POINTS_DIVISOR;
+    // 1 MUSHI at 9 decimals needs 2 Eclipse at 6 decimals.
+    assert_eq!(main_state.eclipse_tokens_for_deposit(1_000_000_000, 9, 6).unwrap(), 2_000_000);
+    assert_overflow(main_state.eclipse_tokens_for_deposit(u64::MAX, 9, 9));
+}
+
+#[test]
+fn reward_accumulator_saturates_instead_of_failing() {
+    let mut main_state = pool(0, 1);
+    main_state.reward_rate = u64::MAX;
+    main_state.reward_end_time = i64::MAX;
+    main_state.acc_reward_per_share = u128::MAX - 1;
+    main_state.update_rewards(1);
+    assert_eq!((main_state.acc_reward_per_share, main_state.last_reward_update), (u128::MAX, 1));
+}
+
+#[test]
+fn reward_window_spanning_the_whole_i64_range_accrues() {
+    let mut main_state = pool(0, u64::MAX);
+    main_state.reward_rate = 1;
+    main_state.reward_start_time = i64::MIN;
+    main_state.reward_end_time = i64::MAX;
+    main_state.last_reward_update = i64::MIN;
+    main_state.update_rewards(i64::MAX);
+    assert_eq!(main_state.last_reward_update, i64::MAX);
+}
+
+#[test]
+fn rewards_are_spread_over_virtual_shares() {
+    // A single base unit staked alone earns only its share of the padded supply.
+    let mut main_state = pool(0, 1);
+    main_state.reward_rate = 1_000_000_000;
+    main_state.reward_end_time = i64::MAX;
+    main_state.update_rewards(86_400);
+    let expected = 86_400 * 1_000_000_000 * REWARD_PRECISION / (1 + INITIAL_VIRTUAL_SHARES as u128);
+    assert_eq!(main_state.acc_reward_per_share, expected);
+}
+
+#[test]
+fn settling_counts_only_accumulator_growth_since_the_last_settlement() {
+    let mut stake_entry = entry(u64::MAX);
+    stake_entry.reward_per_share_paid = u128::MAX - REWARD_PRECISION;
+    stake_entry.settle_rewards(u128::MAX);
+    assert_eq!((stake_entry.rewards_amount, stake_entry.reward_per_share_paid), (u64::MAX, u128::MAX));
+}
+
+#[test]
+fn settling_rewards_saturates_instead_of_failing() {
+    let mut stake_entry = entry(u64::MAX);
+    stake_entry.settle_rewards(u128::MAX);
+    assert_eq!((stake_entry.rewards_amount, stake_entry.reward_per_share_paid), (u64::MAX, u128::MAX));
+
+    let mut stake_entry = entry(u64::MAX);
+    stake_entry.rewards_amount = u64::MAX;
+    stake_entry.settle_rewards(REWARD_PRECISION);
+    assert_eq!(stake_entry.rewards_amount, u64::MAX);
+}
+
+#[test]
+fn settlement_requires_the_vault_to_cover_pending_unstakes_and_treasury_penalty() {
+    let mut main_state = pool(100, 100);
+    main_state.early_unstake_penalty_bps = 1_000;
+    main_state.penalty_decay_period = 3_600;
+    main_state.penalty_curve = PenaltyCurve::Cliff;
+    main_state.penalty_destination = PenaltyDestination::Treasury;
+    main_state.pending_unstake_mushi_amount = 50;
+    let mut stake_entry = entry(100);
+    stake_entry.mushi_token_staked_amount = 100;
+    let input = UnstakeInput { amount: 100, min_mushi_token_amount: 0, min_eclipse_token_amount: 0 };
+
+    // 90 to the user, 10 to the treasury, 50 already pending.
+    match settle_unstake(&mut main_state.clone(), &mut stake_entry.clone(), &input, 149, 0) {
+        Err(Error::AnchorError(err)) => assert_eq!(
+            err.error_code_number,
+            u32::from(MushiStakeVaultError::InsufficientMushiTokenAmount)
+        ),
+        other => panic!("expected InsufficientMushiTokenAmount, got {:?}", other.map(|amounts| amounts.mushi_token_amount)),
+    }
+    let amounts = settle_unstake(&mut main_state, &mut stake_entry, &input, 150, 0).unwrap();
+    assert_eq!((amounts.mushi_token_amount, amounts.penalty_amount), (90, 10));
+}
+
+proptest! {
+    #[test]
+    fn round_trip_never_returns_more_than_deposited(
+        mushi_token_amount in 0..=u64::MAX / 2,
+        staking_token_total_supply in 0..=u64::MAX / 2,
+        deposit in 1..=u64::MAX / 4,
+    ) {
+        let mut main_state = pool(mushi_token_amount, staking_token_total_supply);
+        let Ok(minted) = main_state.stake_tokens_for_deposit(deposit) else {
+            return Ok(());
+        };
+        let Some(staking_token_total_supply) = main_state.staking_token_total_supply.checked_add(minted) else {
+            return Ok(());
+        };
+        main_state.mushi_token_amount += deposit;
+        main_state.staking_token_total_supply = staking_token_total_supply;
+        prop_assert!(main_state.mushi_tokens_for_stake_tokens(minted).unwrap() <= deposit);
+    }
+
+    #[test]
+    fn exchange_rate_math_never_panics(
+        mushi_token_amount in any::<u64>(),
+        staking_token_total_supply in any::<u64>(),
+        virtual_shares in any::<u64>(),
+        amount in any::<u64>(),
+    ) {
+        let mut main_state = pool(mushi_token_amount, staking_token_total_supply);
+        main_state.virtual_shares = virtual_shares;
+        let _ = main_state.stake_tokens_for_deposit(amount);
+        let _ = main_state.mushi_tokens_for_stake_tokens(amount);
+    }
+
+    #[test]
+    fn penalty_never_exceeds_amount(
+        amount in any::<u64>(),
+        early_unstake_penalty_bps in 0..=BASIS_POINTS_DIVISOR as u16,
+        penalty_decay_period in 0..=i64::MAX,
+        last_staked in any::<i64>(),
+        now in any::<i64>(),
+        cliff in any::<bool>(),
+    ) {
+        let mut main_state = pool(0, 0);
+        main_state.early_unstake_penalty_bps = early_unstake_penalty_bps;
+        main_state.penalty_decay_period = penalty_decay_period;
+        main_state.penalty_curve = if cliff { PenaltyCurve::Cliff } else { PenaltyCurve::Linear };
+        prop_assert!(main_state.early_unstake_penalty(amount, last_staked, now).unwrap() <= amount);
+    }
+
+    #[test]
+    fn pro_rata_never_exceeds_amount(
+        amount in any::<u64>(),
+        stake_token_balance in 1..=u64::MAX,
+        fraction in 0.0..=1.0f64,
+    ) {
+        let stake_token_amount = (stake_token_balance as f64 * fraction) as u64;
+        let stake_entry = entry(stake_token_balance);
+        prop_assert!(stake_entry.pro_rata(amount, stake_token_amount.min(stake_token_balance)).unwrap() <= amount);
+    }
+
+    #[test]
+    fn reward_accrual_never_panics(
+        reward_rate in any::<u64>(),
+        staking_token_total_supply in any::<u64>(),
+        acc_reward_per_share in any::<u128>(),
+        last_reward_update in any::<i64>(),
+        now in any::<i64>(),
+    ) {
+        let mut main_state = pool(0, staking_token_total_supply);
+        main_state.reward_rate = reward_rate;
+        main_state.reward_start_time = i64::MIN;
+        main_state.reward_end_time = i64::MAX;
+        main_state.last_reward_update = last_reward_update;
+        main_state.acc_reward_per_share = acc_reward_per_share;
+        main_state.update_rewards(now);
+
+        let mut stake_entry = entry(staking_token_total_supply);
+        stake_entry.settle_rewards(acc_reward_per_share);
+    }
+
+    #[test]
+    fn stake_quote_credits_deposit_net_of_fees(
+        mushi_token_amount in 0..=u64::MAX / 2,
+        staking_token_total_supply in 0..=u64::MAX / 2,
+        deposit in 1..=u64::MAX / 4,
+        mushi_transfer_fee in 0..=1_000_000u64,
+    ) {
+        let main_state = pool(mushi_token_amount, staking_token_total_supply);
+        let input = StakeInput { mushi_token_amount: deposit, eclipse_token_amount: deposit };
+        let Ok(quote) = quote_stake(&main_state, &input, 9, 9, mushi_transfer_fee.min(deposit), Some(0)) else {
+            return Ok(());
+        };
+        let credited = deposit - mushi_transfer_fee.min(deposit);
+        prop_assert_eq!(quote.mushi_token_amount, credited);
+        prop_assert_eq!(quote.stake_token_amount, main_state.stake_tokens_for_deposit(credited).unwrap());
+        prop_assert_eq!(quote.total_mushi_token_amount, mushi_token_amount + credited);
+        prop_assert_eq!(quote.staking_token_total_supply, staking_token_total_supply + quote.stake_token_amount);
+    }
+
+    #[test]
+    fn unstake_quote_matches_settlement(
+        mushi_token_amount in 0..=u64::MAX / 2,
+        stake_token_balance in 1..=u64::MAX / 4,
+        amount in 1..=u64::MAX / 4,
+        early_unstake_penalty_bps in 0..=BASIS_POINTS_DIVISOR as u16,
+        elapsed in 0..=7_200i64,
+    ) {
+        let mut main_state = pool(mushi_token_amount, stake_token_balance);
+        main_state.early_unstake_penalty_bps = early_unstake_penalty_bps;
+        main_state.penalty_decay_period = 3_600;
+        let mut stake_entry = entry(stake_token_balance);
+        stake_entry.mushi_token_staked_amount = stake_token_balance;
+        let input = UnstakeInput { amount: amount.min(stake_token_balance), min_mushi_token_amount: 0, min_eclipse_token_amount: 0 };
+
+        let quote = quote_unstake(&main_state, &stake_entry, &input, u64::MAX, elapsed);
+        let Ok(amounts) = settle_unstake(&mut main_state, &mut stake_entry, &input, u64::MAX, elapsed) else {
+            prop_assert!(quote.is_err());
+            return Ok(());
+        };
+        let quote = quote.unwrap();
+        prop_assert_eq!(quote.mushi_token_amount, amounts.mushi_token_amount);
+        prop_assert_eq!(quote.penalty_amount, amounts.penalty_amount);
+        prop_assert_eq!(quote.total_mushi_token_amount, main_state.mushi_token_amount);
+        prop_assert_eq!(quote.staking_token_total_supply, main_state.staking_token_total_supply);
+    }
+}
@@ -0,0 +1,47 @@
+#![allow(clippy::result_large_err)]
+
+use anchor_lang::{prelude::*, solana_program::system_program};
+use mushi_stake_vault::{
+    errors::MushiStakeVaultError, state::MUSHIPROGRAM_VAULT_SEED, utils::verify_mushi_program_caller,
+};
+
+fn mushi_program() -> Pubkey {
+    Pubkey::new_unique()
+}
+
+fn mushi_program_authority(mushi_program: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[MUSHIPROGRAM_VAULT_SEED], mushi_program).0
+}
+
+fn check(key: Pubkey, is_signer: bool) -> Result<()> {
+    let mut lamports = 0;
+    let mut data = vec![];
+    let owner = system_program::ID;
+    let account = AccountInfo::new(&key, is_signer, false, &mut lamports, &mut data, &owner, false, 0);
+    verify_mushi_program_caller(&account)
+}
+
+fn assert_unauthorized(result: Result<()>) {
+    match result {
+        Err(Error::AnchorError(err)) => assert_eq!(
+            err.error_code_number,
+            u32::from(MushiStakeVaultError::UnauthorizedProgramCall)
+        ),
+        other => panic!("expected UnauthorizedProgramCall, got {:?}", other),
+    }
+}
+
+#[test]
+fn accepts_cpi_signed_by_mushi_program_pda() {
+    let mushi_program = mushi_program();
+    check(mushi_program_authority(&mushi_program), true).unwrap();
+}
+
+#[test]
+fn rejects_unsigned_mushi_program_pda() {
+    // A top-level instruction can name the PDA but can never carry its
+    // signature; `program_gated_stake_cannot_ride_on_a_mushi_program_instruction`
+    // covers the sandwiched transaction end to end.
+    let mushi_program = mushi_program();
+    assert_unauthorized(check(mushi_program_authority(&mushi_program), false));
+}
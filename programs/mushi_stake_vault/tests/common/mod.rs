@@ -0,0 +1,554 @@
+//! In-process stand-in for `solana-program-test`.
+//!
+//! `solana-program-test` cannot be resolved against the pinned solana-program
+//! 1.16 toolchain, so instructions run here by calling each program's
+//! processor directly: this program through its Anchor `entry`, SPL Token,
+//! Token-2022 and the associated token program through their crates, the
+//! system program through a minimal re-implementation, and `mushi_program`
+//! through a mock that forwards an instruction to this program signed by its
+//! vault PDA. Cross-program invocations are routed through the syscall stubs,
+//! which enforce signer privileges the way the runtime does. Token Metadata
+//! ships no processor crate, so `CreateMetadataAccountV3` runs against a
+//! stand-in that applies the program's account and signer checks and writes
+//! the resulting `Metadata` account.
+#![allow(dead_code)]
+
+use std::{cell::RefCell, collections::HashMap, sync::Once};
+
+use anchor_lang::{
+    prelude::*,
+    solana_program::{
+        bpf_loader, entrypoint::ProgramResult, instruction::Instruction, program::invoke_signed,
+        program_option::COption, program_pack::Pack, program_stubs::{self, SyscallStubs},
+        system_instruction::SystemInstruction, system_program, sysvar,
+    },
+    Discriminator, Event, InstructionData, ToAccountMetas,
+};
+use anchor_spl::{
+    associated_token::{self, get_associated_token_address_with_program_id},
+    token::spl_token,
+    token_2022::spl_token_2022::{
+        self,
+        extension::{ExtensionType, StateWithExtensions, StateWithExtensionsMut},
+        state::{Account as TokenAccount, Mint},
+    },
+};
+use mpl_token_metadata::{
+    accounts::Metadata,
+    instructions::CreateMetadataAccountV3InstructionArgs,
+    types::{DataV2, Key},
+    MAX_NAME_LENGTH, MAX_SYMBOL_LENGTH, MAX_URI_LENGTH,
+};
+use mushi_stake_vault::state::MUSHIPROGRAM_VAULT_SEED;
+use spl_associated_token_account::instruction::create_associated_token_account;
+
+/// Instruction discriminator of Token Metadata's `CreateMetadataAccountV3`.
+const CREATE_METADATA_ACCOUNT_V3: u8 = 33;
+
+/// Program id of the mock `mushi_program`.
+pub const MOCK_MUSHI_PROGRAM_ID: Pubkey = Pubkey::new_from_array([7; 32]);
+
+thread_local! {
+    static CLOCK: RefCell<Clock> = RefCell::new(Clock::default());
+    static PROGRAM_STACK: RefCell<Vec<Pubkey>> = const { RefCell::new(Vec::new()) };
+    static RETURN_DATA: RefCell<Option<(Pubkey, Vec<u8>)>> = const { RefCell::new(None) };
+    static EVENTS: RefCell<Vec<Vec<u8>>> = const { RefCell::new(Vec::new()) };
+}
+
+struct Stubs;
+
+impl SyscallStubs for Stubs {
+    fn sol_log(&self, message: &str) {
+        println!("Program log: {message}");
+    }
+
+    fn sol_log_data(&self, fields: &[&[u8]]) {
+        EVENTS.with(|events| events.borrow_mut().push(fields.concat()));
+    }
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        CLOCK.with(|clock| unsafe { *(var_addr as *mut Clock) = clock.borrow().clone() });
+        0
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { *(var_addr as *mut Rent) = Rent::default() };
+        0
+    }
+
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        RETURN_DATA.with(|data| data.borrow().clone())
+    }
+
+    fn sol_set_return_data(&self, data: &[u8]) {
+        let program_id = PROGRAM_STACK.with(|stack| *stack.borrow().last().unwrap());
+        RETURN_DATA.with(|return_data| {
+            *return_data.borrow_mut() = (!data.is_empty()).then(|| (program_id, data.to_vec()));
+        });
+    }
+
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        let caller = PROGRAM_STACK.with(|stack| *stack.borrow().last().unwrap());
+        let signers = signers_seeds
+            .iter()
+            .map(|seeds| Pubkey::create_program_address(seeds, &caller))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        let mut callee_infos = Vec::with_capacity(instruction.accounts.len());
+        for meta in &instruction.accounts {
+            let info = account_infos
+                .iter()
+                .find(|info| info.key == &meta.pubkey)
+                .ok_or(ProgramError::NotEnoughAccountKeys)?;
+            if meta.is_signer && !info.is_signer && !signers.contains(&meta.pubkey) {
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+            let mut info = info.clone();
+            info.is_signer = meta.is_signer;
+            info.is_writable = meta.is_writable;
+            callee_infos.push(info);
+        }
+        process(&instruction.program_id, &callee_infos, &instruction.data)
+    }
+}
+
+fn process(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    PROGRAM_STACK.with(|stack| stack.borrow_mut().push(*program_id));
+    let result = if *program_id == mushi_stake_vault::ID {
+        mushi_stake_vault::entry(program_id, accounts, data)
+    } else if *program_id == spl_token::ID {
+        spl_token::processor::Processor::process(program_id, accounts, data)
+    } else if *program_id == spl_token_2022::ID {
+        ::spl_token_2022::processor::Processor::process(program_id, accounts, data)
+    } else if *program_id == associated_token::ID {
+        spl_associated_token_account::processor::process_instruction(program_id, accounts, data)
+    } else if *program_id == system_program::ID {
+        process_system_instruction(accounts, data)
+    } else if *program_id == MOCK_MUSHI_PROGRAM_ID {
+        process_mock_mushi_program(accounts, data)
+    } else if *program_id == mpl_token_metadata::ID {
+        process_mock_token_metadata(accounts, data)
+    } else {
+        Err(ProgramError::IncorrectProgramId)
+    };
+    PROGRAM_STACK.with(|stack| stack.borrow_mut().pop());
+    result
+}
+
+fn resize(info: &AccountInfo, space: u64) {
+    *info.data.borrow_mut() = Box::leak(vec![0u8; space as usize].into_boxed_slice());
+}
+
+fn move_lamports(from: &AccountInfo, to: &AccountInfo, lamports: u64) -> ProgramResult {
+    if !from.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    let from_lamports = from.lamports().checked_sub(lamports).ok_or(ProgramError::InsufficientFunds)?;
+    **from.lamports.borrow_mut() = from_lamports;
+    **to.lamports.borrow_mut() += lamports;
+    Ok(())
+}
+
+fn process_system_instruction(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    match bincode::deserialize(data).map_err(|_| ProgramError::InvalidInstructionData)? {
+        SystemInstruction::CreateAccount { lamports, space, owner } => {
+            let (from, to) = (&accounts[0], &accounts[1]);
+            if !to.is_signer {
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+            if to.lamports() > 0 || !to.data_is_empty() {
+                return Err(ProgramError::AccountAlreadyInitialized);
+            }
+            move_lamports(from, to, lamports)?;
+            resize(to, space);
+            to.assign(&owner);
+        }
+        SystemInstruction::Transfer { lamports } => move_lamports(&accounts[0], &accounts[1], lamports)?,
+        SystemInstruction::Allocate { space } => {
+            if !accounts[0].is_signer {
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+            resize(&accounts[0], space);
+        }
+        SystemInstruction::Assign { owner } => {
+            if !accounts[0].is_signer {
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+            accounts[0].assign(&owner);
+        }
+        _ => return Err(ProgramError::InvalidInstructionData),
+    }
+    Ok(())
+}
+
+/// Forwards `data` to this program with `accounts[1..]`, signed by the mock's
+/// `MUSHIPROGRAM_VAULT_SEED` PDA, like `mushi_program` does on-chain.
+fn process_mock_mushi_program(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let (vault, bump) = Pubkey::find_program_address(&[MUSHIPROGRAM_VAULT_SEED], &MOCK_MUSHI_PROGRAM_ID);
+    let forwarded = &accounts[1..];
+    let instruction = Instruction {
+        program_id: *accounts[0].key,
+        accounts: forwarded
+            .iter()
+            .map(|info| AccountMeta {
+                pubkey: *info.key,
+                is_signer: info.is_signer || *info.key == vault,
+                is_writable: info.is_writable,
+            })
+            .collect(),
+        data: data.to_vec(),
+    };
+    invoke_signed(&instruction, accounts, &[&[MUSHIPROGRAM_VAULT_SEED, &[bump]]])
+}
+
+/// `CreateMetadataAccountV3`, checked the way Token Metadata checks it: the
+/// metadata PDA, the mint's authority and the payer and update authority
+/// signatures, field lengths, creator shares and verified creators. Rent is
+/// not charged.
+fn process_mock_token_metadata(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [metadata, mint, mint_authority, payer, update_authority, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    let (&discriminator, args) = data.split_first().ok_or(ProgramError::InvalidInstructionData)?;
+    if discriminator != CREATE_METADATA_ACCOUNT_V3 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let args = CreateMetadataAccountV3InstructionArgs::try_from_slice(args).map_err(|_| ProgramError::InvalidInstructionData)?;
+
+    if *metadata.key != Metadata::find_pda(mint.key).0 || !metadata.is_writable {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if !metadata.data_is_empty() {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    if *mint.owner != spl_token::ID && *mint.owner != spl_token_2022::ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let mint_state = StateWithExtensions::<Mint>::unpack(&mint.data.borrow())?.base;
+    if mint_state.mint_authority != COption::Some(*mint_authority.key) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if !mint_authority.is_signer || !payer.is_signer || !update_authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let DataV2 { name, symbol, uri, seller_fee_basis_points, creators, collection, uses } = args.data;
+    if name.len() > MAX_NAME_LENGTH || symbol.len() > MAX_SYMBOL_LENGTH || uri.len() > MAX_URI_LENGTH {
+        return Err(ProgramError::InvalidArgument);
+    }
+    if seller_fee_basis_points > 10_000 {
+        return Err(ProgramError::InvalidArgument);
+    }
+    if let Some(creators) = &creators {
+        if creators.iter().map(|creator| u16::from(creator.share)).sum::<u16>() != 100 {
+            return Err(ProgramError::InvalidArgument);
+        }
+        let signed = |address: &Pubkey| accounts.iter().any(|info| info.key == address && info.is_signer);
+        if creators.iter().any(|creator| creator.verified && !signed(&creator.address)) {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+    }
+
+    let data = Metadata {
+        key: Key::MetadataV1,
+        update_authority: *update_authority.key,
+        mint: *mint.key,
+        name,
+        symbol,
+        uri,
+        seller_fee_basis_points,
+        creators,
+        primary_sale_happened: false,
+        is_mutable: args.is_mutable,
+        edition_nonce: None,
+        token_standard: None,
+        collection,
+        uses,
+        collection_details: args.collection_details,
+        programmable_config: None,
+    }
+    .try_to_vec()?;
+    resize(metadata, data.len() as u64);
+    metadata.data.borrow_mut().copy_from_slice(&data);
+    metadata.assign(&mpl_token_metadata::ID);
+    Ok(())
+}
+
+#[derive(Clone, Debug)]
+pub struct StoredAccount {
+    pub lamports: u64,
+    pub data: Vec<u8>,
+    pub owner: Pubkey,
+    pub executable: bool,
+}
+
+/// Accounts plus the clock; every `process` call is an atomic transaction.
+pub struct Bank {
+    pub accounts: HashMap<Pubkey, StoredAccount>,
+    /// Funded fee payer for fixture accounts.
+    pub payer: Pubkey,
+    /// Mint authority of every mint created by `create_mint`.
+    pub mint_authority: Pubkey,
+}
+
+impl Default for Bank {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Bank {
+    pub fn new() -> Self {
+        static STUBS: Once = Once::new();
+        STUBS.call_once(|| {
+            program_stubs::set_syscall_stubs(Box::new(Stubs));
+        });
+        CLOCK.with(|clock| *clock.borrow_mut() = Clock { unix_timestamp: 1_700_000_000, ..Clock::default() });
+
+        let mut bank = Self { accounts: HashMap::new(), payer: Pubkey::new_unique(), mint_authority: Pubkey::new_unique() };
+        bank.airdrop(bank.payer, 1_000_000_000_000);
+        for program_id in [
+            mushi_stake_vault::ID,
+            spl_token::ID,
+            spl_token_2022::ID,
+            associated_token::ID,
+            system_program::ID,
+            MOCK_MUSHI_PROGRAM_ID,
+            mpl_token_metadata::ID,
+        ] {
+            bank.set(program_id, StoredAccount { lamports: 1, data: vec![], owner: bpf_loader::ID, executable: true });
+        }
+        bank.set(
+            sysvar::rent::ID,
+            StoredAccount {
+                lamports: 1,
+                data: bincode::serialize(&Rent::default()).unwrap(),
+                owner: sysvar::ID,
+                executable: false,
+            },
+        );
+        bank
+    }
+
+    pub fn set(&mut self, key: Pubkey, account: StoredAccount) {
+        self.accounts.insert(key, account);
+    }
+
+    pub fn get(&self, key: &Pubkey) -> Option<&StoredAccount> {
+        self.accounts.get(key)
+    }
+
+    pub fn now(&self) -> i64 {
+        CLOCK.with(|clock| clock.borrow().unix_timestamp)
+    }
+
+    pub fn warp(&mut self, seconds: i64) {
+        CLOCK.with(|clock| clock.borrow_mut().unix_timestamp += seconds);
+    }
+
+    pub fn airdrop(&mut self, key: Pubkey, lamports: u64) {
+        self.accounts
+            .entry(key)
+            .or_insert(StoredAccount { lamports: 0, data: vec![], owner: system_program::ID, executable: false })
+            .lamports += lamports;
+    }
+
+    /// Runs `instruction` as a transaction signed by every account it marks
+    /// as a signer. State changes are kept only on success.
+    pub fn process(&mut self, instruction: &Instruction) -> ProgramResult {
+        RETURN_DATA.with(|data| *data.borrow_mut() = None);
+        EVENTS.with(|events| events.borrow_mut().clear());
+        let mut infos: HashMap<Pubkey, AccountInfo<'static>> = HashMap::new();
+        for key in instruction.accounts.iter().map(|meta| meta.pubkey).chain([instruction.program_id]) {
+            if infos.contains_key(&key) {
+                continue;
+            }
+            let stored = self.accounts.get(&key).cloned().unwrap_or(StoredAccount {
+                lamports: 0,
+                data: vec![],
+                owner: system_program::ID,
+                executable: false,
+            });
+            infos.insert(
+                key,
+                AccountInfo::new(
+                    Box::leak(Box::new(key)),
+                    false,
+                    false,
+                    Box::leak(Box::new(stored.lamports)),
+                    Box::leak(stored.data.into_boxed_slice()),
+                    Box::leak(Box::new(stored.owner)),
+                    stored.executable,
+                    0,
+                ),
+            );
+        }
+        let mut account_infos: Vec<AccountInfo> = instruction
+            .accounts
+            .iter()
+            .map(|meta| {
+                let mut info = infos[&meta.pubkey].clone();
+                info.is_signer = meta.is_signer;
+                info.is_writable = meta.is_writable;
+                info
+            })
+            .collect();
+        account_infos.push(infos[&instruction.program_id].clone());
+
+        process(&instruction.program_id, &account_infos, &instruction.data)?;
+
+        for (key, info) in infos {
+            self.accounts.insert(
+                key,
+                StoredAccount {
+                    lamports: info.lamports(),
+                    data: info.data.borrow().to_vec(),
+                    owner: *info.owner,
+                    executable: info.executable,
+                },
+            );
+        }
+        Ok(())
+    }
+
+    /// Processes `instructions` in order as one atomic transaction.
+    pub fn process_transaction(&mut self, instructions: &[Instruction]) -> ProgramResult {
+        let snapshot = self.accounts.clone();
+        let result = instructions.iter().try_for_each(|instruction| self.process(instruction));
+        if result.is_err() {
+            self.accounts = snapshot;
+        }
+        result
+    }
+
+    /// Return data set by the last successful `process` call.
+    pub fn return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        RETURN_DATA.with(|data| data.borrow().clone())
+    }
+
+    /// Events of type `T` emitted by the last `process` call.
+    pub fn events<T: Event + Discriminator>(&self) -> Vec<T> {
+        EVENTS.with(|events| {
+            events
+                .borrow()
+                .iter()
+                .filter_map(|data| data.strip_prefix(T::DISCRIMINATOR.as_slice()))
+                .map(|data| T::try_from_slice(data).unwrap())
+                .collect()
+        })
+    }
+
+    pub fn create_mint(&mut self, mint: Pubkey, decimals: u8, token_program: Pubkey) {
+        self.create_mint_with_extensions(mint, decimals, token_program, &[], |_| {});
+    }
+
+    /// Creates a Token-2022 mint with room for `extensions`, which `configure`
+    /// initializes before the base state is written.
+    pub fn create_mint_with_extensions(
+        &mut self,
+        mint: Pubkey,
+        decimals: u8,
+        token_program: Pubkey,
+        extensions: &[ExtensionType],
+        configure: impl FnOnce(&mut StateWithExtensionsMut<Mint>),
+    ) {
+        let state = Mint {
+            mint_authority: COption::Some(self.mint_authority),
+            supply: 0,
+            decimals,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        };
+        let data = if extensions.is_empty() {
+            let mut data = vec![0u8; Mint::LEN];
+            Mint::pack(state, &mut data).unwrap();
+            data
+        } else {
+            let mut data = vec![0u8; ExtensionType::get_account_len::<Mint>(extensions)];
+            let mut mint_state = StateWithExtensionsMut::<Mint>::unpack_uninitialized(&mut data).unwrap();
+            configure(&mut mint_state);
+            mint_state.base = state;
+            mint_state.pack_base();
+            mint_state.init_account_type().unwrap();
+            data
+        };
+        self.set(
+            mint,
+            StoredAccount { lamports: Rent::default().minimum_balance(data.len()), data, owner: token_program, executable: false },
+        );
+    }
+
+    /// Creates `owner`'s associated token account for `mint` through the
+    /// associated token program and mints `amount` into it.
+    pub fn create_ata(&mut self, owner: Pubkey, mint: Pubkey, amount: u64) -> Pubkey {
+        let token_program = self.accounts[&mint].owner;
+        self.process(&create_associated_token_account(&self.payer, &owner, &mint, &token_program))
+            .unwrap();
+        let address = get_associated_token_address_with_program_id(&owner, &mint, &token_program);
+        if amount > 0 {
+            self.mint_to(mint, address, amount);
+        }
+        address
+    }
+
+    pub fn mint_to(&mut self, mint: Pubkey, destination: Pubkey, amount: u64) {
+        let token_program = self.accounts[&mint].owner;
+        self.process(
+            &spl_token_2022::instruction::mint_to(&token_program, &mint, &destination, &self.mint_authority, &[], amount)
+                .unwrap(),
+        )
+        .unwrap();
+    }
+
+    pub fn token_balance(&self, address: &Pubkey) -> u64 {
+        TokenAccount::unpack_from_slice(&self.accounts[address].data[..TokenAccount::LEN]).unwrap().amount
+    }
+
+    pub fn token_account_state(&self, address: &Pubkey) -> spl_token_2022::state::AccountState {
+        TokenAccount::unpack_from_slice(&self.accounts[address].data[..TokenAccount::LEN]).unwrap().state
+    }
+
+    /// Overwrites a token account's balance, e.g. to simulate a vault shortfall.
+    pub fn set_token_balance(&mut self, address: &Pubkey, amount: u64) {
+        let data = &mut self.accounts.get_mut(address).unwrap().data[..TokenAccount::LEN];
+        let mut token_account = TokenAccount::unpack_from_slice(data).unwrap();
+        token_account.amount = amount;
+        token_account.pack_into_slice(data);
+    }
+
+    pub fn mint_supply(&self, mint: &Pubkey) -> u64 {
+        Mint::unpack_from_slice(&self.accounts[mint].data[..Mint::LEN]).unwrap().supply
+    }
+
+    /// The Token Metadata account of `mint`.
+    pub fn metadata(&self, mint: &Pubkey) -> Metadata {
+        Metadata::from_bytes(&self.accounts[&Metadata::find_pda(mint).0].data).unwrap()
+    }
+
+    pub fn account<T: AccountDeserialize>(&self, address: &Pubkey) -> T {
+        T::try_deserialize(&mut self.accounts[address].data.as_slice()).unwrap()
+    }
+}
+
+/// Builds an instruction for this program from its Anchor client structs.
+pub fn instruction(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction { program_id: mushi_stake_vault::ID, accounts: accounts.to_account_metas(None), data: data.data() }
+}
+
+/// Wraps `instruction` in a call to the mock `mushi_program`, which CPIs it
+/// into this program signed by its vault PDA.
+pub fn via_mushi_program(instruction: Instruction) -> Instruction {
+    let mut accounts = vec![AccountMeta::new_readonly(instruction.program_id, false)];
+    accounts.extend(instruction.accounts);
+    Instruction { program_id: MOCK_MUSHI_PROGRAM_ID, accounts, data: instruction.data }
+}
+
+/// The `ProgramError` an Anchor error surfaces as.
+pub fn program_error(error: impl Into<u32>) -> ProgramError {
+    ProgramError::Custom(error.into())
+}
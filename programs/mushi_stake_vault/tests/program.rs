@@ -0,0 +1,1694 @@
+#![allow(clippy::result_large_err)]
+
+mod common;
+
+use anchor_lang::{
+    prelude::*,
+    solana_program::{entrypoint::ProgramResult, instruction::Instruction, program_pack::Pack, system_program, sysvar},
+    InstructionData,
+};
+use anchor_spl::{
+    associated_token::{self, get_associated_token_address_with_program_id},
+    token::spl_token,
+    token_2022::spl_token_2022::{
+        self,
+        extension::{
+            default_account_state::DefaultAccountState,
+            mint_close_authority::MintCloseAuthority,
+            transfer_fee::{TransferFee, TransferFeeConfig},
+            non_transferable::NonTransferable,
+            permanent_delegate::PermanentDelegate,
+            ExtensionType,
+        },
+        state::AccountState,
+    },
+};
+use common::{instruction, program_error, via_mushi_program, Bank, MOCK_MUSHI_PROGRAM_ID};
+use mushi_stake_vault::{
+    accounts, errors::MushiStakeVaultError, events::{AdminTransferAccepted, PoolAudited, RewardRateSet, RewardsClaimed, RewardsFunded}, ixs::init_pool::InitPoolInput, return_data,
+    state::{
+        AccessMode, AllowlistEntry, EclipseDepositMode, MainState, PenaltyCurve, PenaltyDestination, ReconcileMode, StakeEntry, UnstakeRequest,
+        ALLOWLIST_SEED, MUSHIPROGRAM_VAULT_SEED, PAUSE_ALL, PAUSE_ADMIN, PAUSE_STAKE, PAUSE_UNSTAKE, REWARD_VAULT_SEED,
+        EXCHANGE_RATE_PRECISION, STAKE_ENTRY_SEED, UNSTAKE_REQUEST_SEED, VAULT_OWNER_SEED,
+    },
+    FundRewardsInput, PoolStats, ProposeAdminInput, ReconcilePoolInput, SetPauseInput, SetRewardRateInput, StakeInput, StakeResult, UnstakeInput, UnstakePreview, UnstakeResult,
+    UpdatePoolInput, UserPosition,
+    utils::verify_supported_mint_extensions,
+};
+
+const ONE_MUSHI: u64 = 1_000_000_000;
+
+struct Pool {
+    bank: Bank,
+    admin: Pubkey,
+    main_state: Pubkey,
+    mushi_token_mint: Pubkey,
+    eclipse_token_mint: Pubkey,
+    stake_token_mint: Pubkey,
+    token_program: Pubkey,
+    token_vault_owner: Pubkey,
+    mushi_token_vault: Pubkey,
+    eclipse_token_vault: Pubkey,
+    reward_token_vault: Pubkey,
+    mushi_program_token_vault_owner: Pubkey,
+    eclipse_token_mushi_program_vault: Pubkey,
+}
+
+struct User {
+    key: Pubkey,
+    mushi_token_ata: Pubkey,
+    eclipse_token_ata: Pubkey,
+    stake_token_ata: Pubkey,
+    stake_entry: Pubkey,
+    unstake_request: Pubkey,
+}
+
+fn init_instruction(bank: &Bank, admin: Pubkey, mushi_token_mint: Pubkey, eclipse_token_mint: Pubkey, stake_token_mint: Pubkey) -> Instruction {
+    init_pool_instruction(bank, admin, mushi_token_mint, eclipse_token_mint, stake_token_mint, 0)
+}
+
+fn init_pool_instruction(
+    bank: &Bank,
+    admin: Pubkey,
+    mushi_token_mint: Pubkey,
+    eclipse_token_mint: Pubkey,
+    stake_token_mint: Pubkey,
+    pool_id: u64,
+) -> Instruction {
+    let token_program = bank.get(&mushi_token_mint).unwrap().owner;
+    let main_state = Pubkey::find_program_address(&[MainState::PREFIX_SEED, &pool_id.to_le_bytes()], &mushi_stake_vault::ID).0;
+    let token_vault_owner = Pubkey::find_program_address(&[VAULT_OWNER_SEED, main_state.as_ref()], &mushi_stake_vault::ID).0;
+    instruction(
+        accounts::InitializeStakePool {
+            admin,
+            main_state,
+            mushi_token_mint,
+            eclipse_token_mint,
+            stake_token_mint,
+            stake_token_metadata_account: Pubkey::find_program_address(
+                &[b"metadata", mpl_token_metadata::ID.as_ref(), stake_token_mint.as_ref()],
+                &mpl_token_metadata::ID,
+            )
+            .0,
+            token_vault_owner,
+            mushi_token_vault: get_associated_token_address_with_program_id(&token_vault_owner, &mushi_token_mint, &token_program),
+            eclipse_token_vault: get_associated_token_address_with_program_id(&token_vault_owner, &eclipse_token_mint, &spl_token_2022::ID),
+            stake_token_vault: get_associated_token_address_with_program_id(&token_vault_owner, &stake_token_mint, &token_program),
+            reward_token_vault: Pubkey::find_program_address(&[REWARD_VAULT_SEED, main_state.as_ref()], &mushi_stake_vault::ID).0,
+            token_program,
+            token2022_program: spl_token_2022::ID,
+            mpl_program: mpl_token_metadata::ID,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+        },
+        mushi_stake_vault::instruction::Initialize {
+            input: InitPoolInput {
+                pool_id,
+                stake_token_name: "Staked MUSHI".to_string(),
+                stake_token_symbol: "sMUSHI".to_string(),
+                stake_token_uri: String::new(),
+                mushi_program: MOCK_MUSHI_PROGRAM_ID,
+            },
+        },
+    )
+}
+
+impl Pool {
+    /// A pool over a 9-decimal MUSHI mint owned by `token_program` and a
+    /// 9-decimal Token-2022 Eclipse mint, gated on the mock `mushi_program`.
+    fn new(token_program: Pubkey) -> Self {
+        let mut bank = Bank::new();
+        let mushi_token_mint = Pubkey::new_unique();
+        bank.create_mint(mushi_token_mint, 9, token_program);
+        Self::with_mushi_mint(bank, mushi_token_mint)
+    }
+
+    fn with_mushi_mint(bank: Bank, mushi_token_mint: Pubkey) -> Self {
+        Self::with_pool_id(bank, mushi_token_mint, 0)
+    }
+
+    /// Initializes pool `pool_id` over `mushi_token_mint` in `bank`, which may
+    /// already host other pools.
+    fn with_pool_id(mut bank: Bank, mushi_token_mint: Pubkey, pool_id: u64) -> Self {
+        let admin = Pubkey::new_unique();
+        bank.airdrop(admin, 10_000_000_000);
+        let eclipse_token_mint = Pubkey::new_unique();
+        bank.create_mint(eclipse_token_mint, 9, spl_token_2022::ID);
+        let stake_token_mint = Pubkey::new_unique();
+        let init = init_pool_instruction(&bank, admin, mushi_token_mint, eclipse_token_mint, stake_token_mint, pool_id);
+        bank.process(&init).unwrap();
+
+        let token_program = bank.get(&mushi_token_mint).unwrap().owner;
+        let main_state = init.accounts[1].pubkey;
+        let token_vault_owner = init.accounts[6].pubkey;
+        let mushi_program_token_vault_owner = Pubkey::find_program_address(&[MUSHIPROGRAM_VAULT_SEED], &MOCK_MUSHI_PROGRAM_ID).0;
+        let eclipse_token_mushi_program_vault = bank.create_ata(mushi_program_token_vault_owner, eclipse_token_mint, 100 * ONE_MUSHI);
+        Self {
+            bank,
+            admin,
+            main_state,
+            mushi_token_mint,
+            eclipse_token_mint,
+            stake_token_mint,
+            token_program,
+            token_vault_owner,
+            mushi_token_vault: init.accounts[7].pubkey,
+            eclipse_token_vault: init.accounts[8].pubkey,
+            reward_token_vault: init.accounts[10].pubkey,
+            mushi_program_token_vault_owner,
+            eclipse_token_mushi_program_vault,
+        }
+    }
+
+    /// An open pool over a Token-2022 MUSHI mint charging `basis_points` on transfers.
+    fn with_mushi_transfer_fee(basis_points: u16) -> Self {
+        let mut bank = Bank::new();
+        let mushi_token_mint = Pubkey::new_unique();
+        bank.create_mint_with_extensions(mushi_token_mint, 9, spl_token_2022::ID, &[ExtensionType::TransferFeeConfig], |mint| {
+            let fee = TransferFee {
+                epoch: 0.into(),
+                maximum_fee: u64::MAX.into(),
+                transfer_fee_basis_points: basis_points.into(),
+            };
+            let config = mint.init_extension::<TransferFeeConfig>(true).unwrap();
+            config.older_transfer_fee = fee;
+            config.newer_transfer_fee = fee;
+        });
+        let mut pool = Self::with_mushi_mint(bank, mushi_token_mint);
+        pool.update(UpdatePoolInput { access_mode: Some(AccessMode::Open), ..pool.no_updates() }).unwrap();
+        pool
+    }
+
+    /// Same as `new`, with the pool opened to direct calls.
+    fn open(token_program: Pubkey) -> Self {
+        let mut pool = Self::new(token_program);
+        pool.update(UpdatePoolInput { access_mode: Some(AccessMode::Open), ..pool.no_updates() }).unwrap();
+        pool
+    }
+
+    fn user(&mut self, mushi_token_amount: u64, eclipse_token_amount: u64) -> User {
+        let key = Pubkey::new_unique();
+        self.bank.airdrop(key, 1_000_000_000);
+        User {
+            key,
+            mushi_token_ata: self.bank.create_ata(key, self.mushi_token_mint, mushi_token_amount),
+            eclipse_token_ata: self.bank.create_ata(key, self.eclipse_token_mint, eclipse_token_amount),
+            stake_token_ata: get_associated_token_address_with_program_id(&key, &self.stake_token_mint, &self.token_program),
+            stake_entry: Pubkey::find_program_address(
+                &[STAKE_ENTRY_SEED, self.main_state.as_ref(), key.as_ref()],
+                &mushi_stake_vault::ID,
+            )
+            .0,
+            unstake_request: Pubkey::find_program_address(
+                &[UNSTAKE_REQUEST_SEED, self.main_state.as_ref(), key.as_ref()],
+                &mushi_stake_vault::ID,
+            )
+            .0,
+        }
+    }
+
+    fn main_state(&self) -> MainState {
+        self.bank.account(&self.main_state)
+    }
+
+    fn allowlist_entry(&self, user: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(&[ALLOWLIST_SEED, self.main_state.as_ref(), user.as_ref()], &mushi_stake_vault::ID).0
+    }
+
+    fn stake_accounts(&self, user: &User) -> accounts::Stake {
+        accounts::Stake {
+            user: user.key,
+            main_state: self.main_state,
+            allowlist_entry: None,
+            stake_entry: user.stake_entry,
+            mushi_program_token_vault_owner: self.mushi_program_token_vault_owner,
+            user_mushi_token_ata: user.mushi_token_ata,
+            user_stake_token_ata: user.stake_token_ata,
+            mushi_token_vault: self.mushi_token_vault,
+            mushi_token_mint: self.mushi_token_mint,
+            user_eclipse_token_ata: Some(user.eclipse_token_ata),
+            eclipse_token_mushi_program_vault: None,
+            eclipse_token_vault: self.eclipse_token_vault,
+            eclipse_token_mint: self.eclipse_token_mint,
+            stake_token_mint: self.stake_token_mint,
+            token_vault_owner: self.token_vault_owner,
+            associated_token_program: associated_token::ID,
+            token_program: self.token_program,
+            token2022_program: spl_token_2022::ID,
+            system_program: system_program::ID,
+        }
+    }
+
+    fn stake_instruction(&self, accounts: accounts::Stake, mushi_token_amount: u64, eclipse_token_amount: u64) -> Instruction {
+        instruction(
+            accounts,
+            mushi_stake_vault::instruction::Stake { input: StakeInput { mushi_token_amount, eclipse_token_amount } },
+        )
+    }
+
+    fn stake(&mut self, user: &User, mushi_token_amount: u64, eclipse_token_amount: u64) -> ProgramResult {
+        let stake = self.stake_instruction(self.stake_accounts(user), mushi_token_amount, eclipse_token_amount);
+        self.bank.process(&stake)
+    }
+
+    fn unstake_accounts(&self, user: &User) -> accounts::Unstake {
+        accounts::Unstake {
+            user: user.key,
+            main_state: self.main_state,
+            stake_entry: user.stake_entry,
+            user_mushi_token_ata: user.mushi_token_ata,
+            user_stake_token_ata: user.stake_token_ata,
+            mushi_token_vault: self.mushi_token_vault,
+            treasury_mushi_token_ata: None,
+            mushi_token_mint: self.mushi_token_mint,
+            eclipse_token_staking_program_vault: self.eclipse_token_vault,
+            eclipse_token_source: Some(user.eclipse_token_ata),
+            eclipse_token_mint: self.eclipse_token_mint,
+            stake_token_mint: self.stake_token_mint,
+            staking_program_token_vault_owner: self.token_vault_owner,
+            associated_token_program: associated_token::ID,
+            token_program: self.token_program,
+            token2022_program: spl_token_2022::ID,
+            system_program: system_program::ID,
+        }
+    }
+
+    fn unstake_instruction(&self, accounts: accounts::Unstake, input: UnstakeInput) -> Instruction {
+        instruction(accounts, mushi_stake_vault::instruction::Unstake { input })
+    }
+
+    fn unstake(&mut self, user: &User, amount: u64) -> ProgramResult {
+        let unstake = self.unstake_instruction(
+            self.unstake_accounts(user),
+            UnstakeInput { amount, min_mushi_token_amount: 0, min_eclipse_token_amount: 0 },
+        );
+        self.bank.process(&unstake)
+    }
+
+    fn request_unstake_accounts(&self, user: &User) -> accounts::RequestUnstake {
+        accounts::RequestUnstake {
+            user: user.key,
+            main_state: self.main_state,
+            stake_entry: user.stake_entry,
+            unstake_request: user.unstake_request,
+            user_stake_token_ata: user.stake_token_ata,
+            stake_token_mint: self.stake_token_mint,
+            mushi_token_vault: self.mushi_token_vault,
+            treasury_mushi_token_ata: None,
+            mushi_token_mint: self.mushi_token_mint,
+            staking_program_token_vault_owner: self.token_vault_owner,
+            token_program: self.token_program,
+            system_program: system_program::ID,
+        }
+    }
+
+    fn request_unstake(&mut self, user: &User, amount: u64) -> ProgramResult {
+        let input = UnstakeInput { amount, min_mushi_token_amount: 0, min_eclipse_token_amount: 0 };
+        self.bank
+            .process(&instruction(self.request_unstake_accounts(user), mushi_stake_vault::instruction::RequestUnstake { input }))
+    }
+
+    fn withdraw_unstaked(&mut self, user: &User) -> ProgramResult {
+        self.bank.process(&instruction(
+            accounts::WithdrawUnstaked {
+                user: user.key,
+                main_state: self.main_state,
+                unstake_request: user.unstake_request,
+                user_mushi_token_ata: user.mushi_token_ata,
+                mushi_token_vault: self.mushi_token_vault,
+                mushi_token_mint: self.mushi_token_mint,
+                eclipse_token_staking_program_vault: self.eclipse_token_vault,
+                eclipse_token_source: Some(user.eclipse_token_ata),
+                eclipse_token_mint: self.eclipse_token_mint,
+                staking_program_token_vault_owner: self.token_vault_owner,
+                token_program: self.token_program,
+                token2022_program: spl_token_2022::ID,
+            },
+            mushi_stake_vault::instruction::WithdrawUnstaked {},
+        ))
+    }
+
+    fn no_updates(&self) -> UpdatePoolInput {
+        UpdatePoolInput {
+            mushi_program: MOCK_MUSHI_PROGRAM_ID,
+            access_mode: None,
+            guardian: None,
+            unstake_cooldown: None,
+            early_unstake_penalty_bps: None,
+            penalty_decay_period: None,
+            penalty_curve: None,
+            penalty_destination: None,
+            treasury: None,
+            eclipse_deposit_mode: None,
+            eclipse_per_mushi_bps: None,
+        }
+    }
+
+    fn update_as(&mut self, admin: Pubkey, input: UpdatePoolInput) -> ProgramResult {
+        self.bank.process(&instruction(
+            accounts::UpdatePool { admin, main_state: self.main_state, system_program: system_program::ID },
+            mushi_stake_vault::instruction::UpdatePool { input },
+        ))
+    }
+
+    fn update(&mut self, input: UpdatePoolInput) -> ProgramResult {
+        self.update_as(self.admin, input)
+    }
+
+    fn propose_admin_as(&mut self, admin: Pubkey, new_admin: Pubkey) -> ProgramResult {
+        self.bank.process(&instruction(
+            accounts::ProposeAdmin { admin, main_state: self.main_state, system_program: system_program::ID },
+            mushi_stake_vault::instruction::ProposeAdmin { input: ProposeAdminInput { new_admin } },
+        ))
+    }
+
+    fn accept_admin_as(&mut self, pending_admin: Pubkey) -> ProgramResult {
+        self.bank.process(&instruction(
+            accounts::AcceptAdmin { pending_admin, main_state: self.main_state, system_program: system_program::ID },
+            mushi_stake_vault::instruction::AcceptAdmin {},
+        ))
+    }
+
+    fn cancel_admin_transfer_as(&mut self, admin: Pubkey) -> ProgramResult {
+        self.bank.process(&instruction(
+            accounts::CancelAdminTransfer { admin, main_state: self.main_state, system_program: system_program::ID },
+            mushi_stake_vault::instruction::CancelAdminTransfer {},
+        ))
+    }
+
+    fn set_pause_as(&mut self, authority: Pubkey, paused: u8) -> ProgramResult {
+        self.bank.process(&instruction(
+            accounts::SetPause { authority, main_state: self.main_state, system_program: system_program::ID },
+            mushi_stake_vault::instruction::SetPause { input: SetPauseInput { paused } },
+        ))
+    }
+
+    /// Mints `amount` MUSHI to the admin and moves it into the reward vault.
+    fn fund_rewards(&mut self, amount: u64) -> ProgramResult {
+        let admin_mushi_token_ata = get_associated_token_address_with_program_id(&self.admin, &self.mushi_token_mint, &self.token_program);
+        if self.bank.get(&admin_mushi_token_ata).is_none() {
+            self.bank.create_ata(self.admin, self.mushi_token_mint, 0);
+        }
+        self.bank.mint_to(self.mushi_token_mint, admin_mushi_token_ata, amount);
+        self.bank.process(&instruction(
+            accounts::FundRewards {
+                admin: self.admin,
+                main_state: self.main_state,
+                admin_mushi_token_ata,
+                reward_token_vault: self.reward_token_vault,
+                mushi_token_mint: self.mushi_token_mint,
+                token_vault_owner: self.token_vault_owner,
+                token_program: self.token_program,
+            },
+            mushi_stake_vault::instruction::FundRewards { input: FundRewardsInput { amount } },
+        ))
+    }
+
+    /// Emits `reward_rate` per second from now for `duration` seconds.
+    fn set_reward_rate(&mut self, reward_rate: u64, duration: i64) -> ProgramResult {
+        let start_time = self.bank.now();
+        self.bank.process(&instruction(
+            accounts::SetRewardRate {
+                admin: self.admin,
+                main_state: self.main_state,
+                reward_token_vault: self.reward_token_vault,
+                system_program: system_program::ID,
+            },
+            mushi_stake_vault::instruction::SetRewardRate {
+                input: SetRewardRateInput { reward_rate, start_time, end_time: start_time + duration },
+            },
+        ))
+    }
+
+    fn claim_rewards(&mut self, user: &User) -> ProgramResult {
+        self.bank.process(&instruction(
+            accounts::ClaimRewards {
+                user: user.key,
+                main_state: self.main_state,
+                stake_entry: user.stake_entry,
+                user_mushi_token_ata: user.mushi_token_ata,
+                reward_token_vault: self.reward_token_vault,
+                mushi_token_mint: self.mushi_token_mint,
+                token_vault_owner: self.token_vault_owner,
+                token_program: self.token_program,
+            },
+            mushi_stake_vault::instruction::ClaimRewards {},
+        ))
+    }
+
+    fn audit(&mut self) -> PoolAudited {
+        self.bank
+            .process(&instruction(
+                accounts::AuditPool {
+                    main_state: self.main_state,
+                    mushi_token_vault: self.mushi_token_vault,
+                    mushi_token_mint: self.mushi_token_mint,
+                    eclipse_token_vault: self.eclipse_token_vault,
+                    eclipse_token_mint: self.eclipse_token_mint,
+                    stake_token_mint: self.stake_token_mint,
+                    token_vault_owner: self.token_vault_owner,
+                    associated_token_program: associated_token::ID,
+                    token_program: self.token_program,
+                    token2022_program: spl_token_2022::ID,
+                },
+                mushi_stake_vault::instruction::AuditPool {},
+            ))
+            .unwrap();
+        self.bank.events::<PoolAudited>().remove(0)
+    }
+
+    fn reconcile_as(&mut self, admin: Pubkey, mode: ReconcileMode) -> ProgramResult {
+        self.bank.process(&instruction(
+            accounts::ReconcilePool {
+                admin,
+                main_state: self.main_state,
+                mushi_token_vault: self.mushi_token_vault,
+                reward_token_vault: self.reward_token_vault,
+                mushi_token_mint: self.mushi_token_mint,
+                eclipse_token_vault: self.eclipse_token_vault,
+                eclipse_token_mint: self.eclipse_token_mint,
+                stake_token_mint: self.stake_token_mint,
+                token_vault_owner: self.token_vault_owner,
+                associated_token_program: associated_token::ID,
+                token_program: self.token_program,
+                token2022_program: spl_token_2022::ID,
+            },
+            mushi_stake_vault::instruction::ReconcilePool { input: ReconcilePoolInput { mode } },
+        ))
+    }
+
+    fn reconcile(&mut self, mode: ReconcileMode) -> ProgramResult {
+        self.reconcile_as(self.admin, mode)
+    }
+
+    fn preview_stake_instruction(&self, user: &User, mushi_token_amount: u64, eclipse_token_amount: u64) -> Instruction {
+        instruction(
+            accounts::PreviewStake {
+                user: user.key,
+                main_state: self.main_state,
+                allowlist_entry: None,
+                mushi_program_token_vault_owner: self.mushi_program_token_vault_owner,
+                mushi_token_mint: self.mushi_token_mint,
+                eclipse_token_mint: self.eclipse_token_mint,
+            },
+            mushi_stake_vault::instruction::PreviewStake { input: StakeInput { mushi_token_amount, eclipse_token_amount } },
+        )
+    }
+
+    fn preview_stake(&mut self, user: &User, mushi_token_amount: u64, eclipse_token_amount: u64) -> std::result::Result<StakeResult, ProgramError> {
+        self.bank.process(&self.preview_stake_instruction(user, mushi_token_amount, eclipse_token_amount))?;
+        Ok(StakeResult::try_from_slice(&self.bank.return_data().unwrap().1).unwrap())
+    }
+
+    fn preview_unstake(&mut self, user: &User, input: UnstakeInput) -> std::result::Result<UnstakePreview, ProgramError> {
+        self.bank.process(&instruction(
+            accounts::PreviewUnstake {
+                user: user.key,
+                main_state: self.main_state,
+                stake_entry: user.stake_entry,
+                mushi_token_vault: self.mushi_token_vault,
+                mushi_token_mint: self.mushi_token_mint,
+                eclipse_token_mint: self.eclipse_token_mint,
+                token_vault_owner: self.token_vault_owner,
+                token_program: self.token_program,
+            },
+            mushi_stake_vault::instruction::PreviewUnstake { input },
+        ))?;
+        Ok(UnstakePreview::try_from_slice(&self.bank.return_data().unwrap().1).unwrap())
+    }
+
+    fn pool_stats(&mut self) -> PoolStats {
+        self.bank
+            .process(&instruction(
+                accounts::GetPoolStats { main_state: self.main_state },
+                mushi_stake_vault::instruction::GetPoolStats {},
+            ))
+            .unwrap();
+        let (program_id, data) = self.bank.return_data().unwrap();
+        assert_eq!(program_id, mushi_stake_vault::ID);
+        PoolStats::try_from_slice(&data).unwrap()
+    }
+
+    fn user_position(&mut self, user: &Pubkey) -> UserPosition {
+        let stake_entry = Pubkey::find_program_address(
+            &[STAKE_ENTRY_SEED, self.main_state.as_ref(), user.as_ref()],
+            &mushi_stake_vault::ID,
+        )
+        .0;
+        self.bank
+            .process(&instruction(
+                accounts::GetUserPosition { user: *user, main_state: self.main_state, stake_entry },
+                mushi_stake_vault::instruction::GetUserPosition {},
+            ))
+            .unwrap();
+        let (program_id, data) = self.bank.return_data().unwrap();
+        assert_eq!(program_id, mushi_stake_vault::ID);
+        UserPosition::try_from_slice(&data).unwrap()
+    }
+}
+
+fn assert_error(result: ProgramResult, error: MushiStakeVaultError) {
+    assert_eq!(result, Err(program_error(error)));
+}
+
+#[test]
+fn initialize_creates_pool_vaults_and_stake_mint() {
+    let pool = Pool::new(spl_token::ID);
+    let main_state = pool.main_state();
+    assert_eq!(main_state.admin, pool.admin);
+    assert_eq!(main_state.guardian, pool.admin);
+    assert_eq!(main_state.mushi_program, MOCK_MUSHI_PROGRAM_ID);
+    assert_eq!(main_state.stake_token_mint, pool.stake_token_mint);
+    assert_eq!(main_state.access_mode, AccessMode::ProgramGated);
+    assert_eq!(main_state.eclipse_deposit_mode, EclipseDepositMode::Required);
+    assert_eq!(pool.bank.get(&pool.stake_token_mint).unwrap().owner, spl_token::ID);
+    assert_eq!(pool.bank.token_balance(&pool.mushi_token_vault), 0);
+    assert_eq!(pool.bank.token_balance(&pool.eclipse_token_vault), 0);
+    assert_eq!(pool.bank.mint_supply(&pool.stake_token_mint), 0);
+}
+
+#[test]
+fn initialize_with_token_2022_mushi_creates_token_2022_stake_mint() {
+    let pool = Pool::new(spl_token_2022::ID);
+    assert_eq!(pool.bank.get(&pool.stake_token_mint).unwrap().owner, spl_token_2022::ID);
+    assert_eq!(pool.bank.get(&pool.mushi_token_vault).unwrap().owner, spl_token_2022::ID);
+}
+
+#[test]
+fn token_2022_stake_mint_points_at_its_metadata() {
+    use ::spl_token_2022::{
+        extension::{metadata_pointer::MetadataPointer, BaseStateWithExtensions, StateWithExtensions},
+        state::Mint,
+    };
+    use anchor_lang::solana_program::program_option::COption;
+    use mpl_token_metadata::accounts::Metadata;
+
+    let pool = Pool::new(spl_token_2022::ID);
+    let account = pool.bank.get(&pool.stake_token_mint).unwrap();
+    let mint = StateWithExtensions::<Mint>::unpack(&account.data).unwrap();
+    assert_eq!(mint.base.mint_authority, COption::Some(pool.main_state));
+    assert_eq!(mint.base.freeze_authority, COption::Some(pool.main_state));
+    let pointer = mint.get_extension::<MetadataPointer>().unwrap();
+    assert_eq!(Option::<Pubkey>::from(pointer.authority), Some(pool.main_state));
+    assert_eq!(Option::<Pubkey>::from(pointer.metadata_address), Some(Metadata::find_pda(&pool.stake_token_mint).0));
+
+    let pool = Pool::new(spl_token::ID);
+    assert_eq!(pool.bank.get(&pool.stake_token_mint).unwrap().data.len(), spl_token::state::Mint::LEN);
+}
+
+#[test]
+fn initialize_creates_stake_token_metadata() {
+    for token_program in [spl_token::ID, spl_token_2022::ID] {
+        let pool = Pool::new(token_program);
+        let metadata = pool.bank.metadata(&pool.stake_token_mint);
+        assert_eq!((metadata.name.as_str(), metadata.symbol.as_str()), ("Staked MUSHI", "sMUSHI"));
+        assert_eq!(metadata.mint, pool.stake_token_mint);
+        assert_eq!(metadata.update_authority, pool.main_state);
+        assert!(!metadata.is_mutable);
+        let creators = metadata.creators.unwrap();
+        assert_eq!(creators.len(), 1);
+        assert_eq!((creators[0].address, creators[0].verified, creators[0].share), (pool.main_state, true, 100));
+    }
+}
+
+#[test]
+fn initialize_requires_token_metadata_program_and_valid_metadata() {
+    let mut bank = Bank::new();
+    let admin = Pubkey::new_unique();
+    bank.airdrop(admin, 10_000_000_000);
+    let (mushi_token_mint, eclipse_token_mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+    bank.create_mint(mushi_token_mint, 9, spl_token::ID);
+    bank.create_mint(eclipse_token_mint, 9, spl_token_2022::ID);
+    let init = |bank: &Bank| init_instruction(bank, admin, mushi_token_mint, eclipse_token_mint, Pubkey::new_unique());
+
+    // A look-alike program, with the metadata PDA derived under it.
+    let mut foreign = init(&bank);
+    let stake_token_mint = foreign.accounts[4].pubkey;
+    foreign.accounts[5].pubkey =
+        Pubkey::find_program_address(&[b"metadata", MOCK_MUSHI_PROGRAM_ID.as_ref(), stake_token_mint.as_ref()], &MOCK_MUSHI_PROGRAM_ID).0;
+    for meta in foreign.accounts.iter_mut().filter(|meta| meta.pubkey == mpl_token_metadata::ID) {
+        meta.pubkey = MOCK_MUSHI_PROGRAM_ID;
+    }
+    assert_eq!(bank.process(&foreign), Err(program_error(ErrorCode::ConstraintAddress)));
+
+    let mut oversized = init(&bank);
+    oversized.data = mushi_stake_vault::instruction::Initialize {
+        input: InitPoolInput {
+            pool_id: 0,
+            stake_token_name: "x".repeat(mpl_token_metadata::MAX_NAME_LENGTH + 1),
+            stake_token_symbol: "sMUSHI".to_string(),
+            stake_token_uri: String::new(),
+            mushi_program: MOCK_MUSHI_PROGRAM_ID,
+        },
+    }
+    .data();
+    assert_eq!(bank.process(&oversized), Err(ProgramError::InvalidArgument));
+
+    bank.process(&init(&bank)).unwrap();
+}
+
+#[test]
+fn initialize_twice_fails() {
+    let mut pool = Pool::new(spl_token::ID);
+    let init = init_instruction(&pool.bank, pool.admin, pool.mushi_token_mint, pool.eclipse_token_mint, Pubkey::new_unique());
+    assert!(pool.bank.process(&init).is_err());
+}
+
+#[test]
+fn initialize_rejects_unsupported_mint_extensions() {
+    let mut bank = Bank::new();
+    let admin = Pubkey::new_unique();
+    bank.airdrop(admin, 10_000_000_000);
+    let mushi_token_mint = Pubkey::new_unique();
+    bank.create_mint(mushi_token_mint, 9, spl_token::ID);
+
+    let non_transferable = Pubkey::new_unique();
+    bank.create_mint_with_extensions(non_transferable, 9, spl_token_2022::ID, &[ExtensionType::NonTransferable], |mint| {
+        mint.init_extension::<NonTransferable>(true).unwrap();
+    });
+    let init = init_instruction(&bank, admin, mushi_token_mint, non_transferable, Pubkey::new_unique());
+    assert_error(bank.process(&init), MushiStakeVaultError::UnsupportedMintExtension);
+
+    let eclipse_token_mint = Pubkey::new_unique();
+    bank.create_mint(eclipse_token_mint, 9, spl_token_2022::ID);
+    let permanent_delegate = Pubkey::new_unique();
+    bank.create_mint_with_extensions(permanent_delegate, 9, spl_token_2022::ID, &[ExtensionType::PermanentDelegate], |mint| {
+        mint.init_extension::<PermanentDelegate>(true).unwrap().delegate = Some(admin).try_into().unwrap();
+    });
+    let init = init_instruction(&bank, admin, permanent_delegate, eclipse_token_mint, Pubkey::new_unique());
+    assert_error(bank.process(&init), MushiStakeVaultError::UnsupportedMintExtension);
+
+    let frozen_by_default = Pubkey::new_unique();
+    bank.create_mint_with_extensions(frozen_by_default, 9, spl_token_2022::ID, &[ExtensionType::DefaultAccountState], |mint| {
+        mint.init_extension::<DefaultAccountState>(true).unwrap().state = AccountState::Frozen as u8;
+    });
+    let init = init_instruction(&bank, admin, mushi_token_mint, frozen_by_default, Pubkey::new_unique());
+    assert_error(bank.process(&init), MushiStakeVaultError::UnsupportedMintExtension);
+
+}
+
+#[test]
+fn mint_extensions_unknown_to_linked_token_2022_are_rejected() {
+    // 14 is TransferHook and 26 Pausable; the linked token program cannot
+    // create vaults for such mints, so the check is exercised directly.
+    for extension_type in [14u16, 26] {
+        let mut bank = Bank::new();
+        let mint = Pubkey::new_unique();
+        bank.create_mint_with_extensions(mint, 9, spl_token_2022::ID, &[ExtensionType::MintCloseAuthority], |mint| {
+            mint.init_extension::<MintCloseAuthority>(true).unwrap();
+        });
+        let account = bank.accounts.get_mut(&mint).unwrap();
+        let tlv_start = spl_token_2022::state::Account::LEN + 1;
+        account.data[tlv_start..tlv_start + 2].copy_from_slice(&extension_type.to_le_bytes());
+        let mint_info =
+            AccountInfo::new(&mint, false, false, &mut account.lamports, &mut account.data, &account.owner, false, 0);
+        assert_eq!(
+            verify_supported_mint_extensions(&mint_info),
+            Err(MushiStakeVaultError::UnsupportedMintExtension.into())
+        );
+    }
+}
+
+#[test]
+fn initialize_accepts_supported_mint_extensions() {
+    let mut bank = Bank::new();
+    let admin = Pubkey::new_unique();
+    bank.airdrop(admin, 10_000_000_000);
+    let mushi_token_mint = Pubkey::new_unique();
+    bank.create_mint(mushi_token_mint, 9, spl_token::ID);
+    let eclipse_token_mint = Pubkey::new_unique();
+    bank.create_mint_with_extensions(
+        eclipse_token_mint,
+        9,
+        spl_token_2022::ID,
+        &[ExtensionType::MintCloseAuthority, ExtensionType::DefaultAccountState],
+        |mint| {
+            mint.init_extension::<MintCloseAuthority>(true).unwrap();
+            mint.init_extension::<DefaultAccountState>(true).unwrap().state = AccountState::Initialized as u8;
+        },
+    );
+    let init = init_instruction(&bank, admin, mushi_token_mint, eclipse_token_mint, Pubkey::new_unique());
+    bank.process(&init).unwrap();
+}
+
+#[test]
+fn stake_and_unstake_round_trip() {
+    for token_program in [spl_token::ID, spl_token_2022::ID] {
+        let mut pool = Pool::open(token_program);
+        let user = pool.user(10 * ONE_MUSHI, 10 * ONE_MUSHI);
+
+        pool.stake(&user, 4 * ONE_MUSHI, 4 * ONE_MUSHI).unwrap();
+        assert_eq!(pool.bank.token_balance(&user.mushi_token_ata), 6 * ONE_MUSHI);
+        assert_eq!(pool.bank.token_balance(&user.eclipse_token_ata), 6 * ONE_MUSHI);
+        assert_eq!(pool.bank.token_balance(&user.stake_token_ata), 4 * ONE_MUSHI);
+        assert_eq!(pool.bank.token_balance(&pool.mushi_token_vault), 4 * ONE_MUSHI);
+        assert_eq!(pool.bank.token_balance(&pool.eclipse_token_vault), 4 * ONE_MUSHI);
+        let main_state = pool.main_state();
+        assert_eq!(main_state.mushi_token_amount, 4 * ONE_MUSHI);
+        assert_eq!(main_state.eclipse_token_amount, 4 * ONE_MUSHI);
+        assert_eq!(main_state.staking_token_total_supply, 4 * ONE_MUSHI);
+        let stake_entry: StakeEntry = pool.bank.account(&user.stake_entry);
+        assert_eq!(stake_entry.user, user.key);
+        assert_eq!(stake_entry.stake_token_balance, 4 * ONE_MUSHI);
+        assert_eq!(stake_entry.last_staked, pool.bank.now());
+        assert_eq!(stake_entry.eclipse_token_source, user.eclipse_token_ata);
+
+        let mushi_program_vault_before = pool.bank.token_balance(&pool.eclipse_token_mushi_program_vault);
+        pool.unstake(&user, ONE_MUSHI).unwrap();
+        assert_eq!(pool.bank.token_balance(&user.mushi_token_ata), 7 * ONE_MUSHI);
+        assert_eq!(pool.bank.token_balance(&user.eclipse_token_ata), 7 * ONE_MUSHI);
+        assert_eq!(pool.bank.token_balance(&user.stake_token_ata), 3 * ONE_MUSHI);
+        assert_eq!(pool.bank.token_balance(&pool.eclipse_token_vault), 3 * ONE_MUSHI);
+        assert_eq!(pool.bank.token_balance(&pool.eclipse_token_mushi_program_vault), mushi_program_vault_before);
+        assert_eq!(pool.bank.mint_supply(&pool.stake_token_mint), 3 * ONE_MUSHI);
+        assert_eq!(pool.main_state().staking_token_total_supply, 3 * ONE_MUSHI);
+        assert_eq!(pool.bank.account::<StakeEntry>(&user.stake_entry).stake_token_balance, 3 * ONE_MUSHI);
+
+        // Staking again reuses the existing stake token account.
+        pool.stake(&user, ONE_MUSHI, ONE_MUSHI).unwrap();
+        assert_eq!(pool.bank.token_balance(&user.stake_token_ata), 4 * ONE_MUSHI);
+    }
+}
+
+#[test]
+fn stake_tokens_are_non_transferable() {
+    for token_program in [spl_token::ID, spl_token_2022::ID] {
+        let mut pool = Pool::open(token_program);
+        let user = pool.user(4 * ONE_MUSHI, 4 * ONE_MUSHI);
+        let other = Pubkey::new_unique();
+        let other_stake_token_ata = pool.bank.create_ata(other, pool.stake_token_mint, 0);
+
+        pool.stake(&user, 2 * ONE_MUSHI, 2 * ONE_MUSHI).unwrap();
+        assert_eq!(pool.bank.token_account_state(&user.stake_token_ata), AccountState::Frozen);
+        let transfer = spl_token_2022::instruction::transfer_checked(
+            &token_program,
+            &user.stake_token_ata,
+            &pool.stake_token_mint,
+            &other_stake_token_ata,
+            &user.key,
+            &[],
+            ONE_MUSHI,
+            9,
+        )
+        .unwrap();
+        assert!(pool.bank.process(&transfer).is_err());
+        assert_eq!(pool.bank.token_balance(&other_stake_token_ata), 0);
+
+        // Minting and burning thaw the account for the moment they need it.
+        pool.stake(&user, ONE_MUSHI, ONE_MUSHI).unwrap();
+        pool.unstake(&user, ONE_MUSHI).unwrap();
+        assert_eq!(pool.bank.token_account_state(&user.stake_token_ata), AccountState::Frozen);
+        assert_eq!(pool.bank.token_balance(&user.stake_token_ata), 2 * ONE_MUSHI);
+
+        // Once emptied the account stays thawed, so the user can close it.
+        pool.unstake(&user, 2 * ONE_MUSHI).unwrap();
+        assert_eq!(pool.bank.token_account_state(&user.stake_token_ata), AccountState::Initialized);
+        let close = spl_token_2022::instruction::close_account(&token_program, &user.stake_token_ata, &user.key, &user.key, &[]).unwrap();
+        pool.bank.process(&close).unwrap();
+    }
+}
+
+#[test]
+fn stake_credits_mushi_net_of_transfer_fee() {
+    let mut pool = Pool::with_mushi_transfer_fee(100);
+    let user = pool.user(ONE_MUSHI, ONE_MUSHI);
+
+    pool.stake(&user, ONE_MUSHI, ONE_MUSHI).unwrap();
+    let net = ONE_MUSHI - ONE_MUSHI / 100;
+    assert_eq!(pool.bank.token_balance(&pool.mushi_token_vault), net);
+    assert_eq!(pool.bank.token_balance(&user.stake_token_ata), net);
+    assert_eq!(pool.main_state().mushi_token_amount, net);
+    assert_eq!(return_data::stake_result().unwrap().mushi_token_amount, net);
+}
+
+#[test]
+fn stake_in_program_gated_mode_requires_mushi_program() {
+    let mut pool = Pool::new(spl_token::ID);
+    let user = pool.user(ONE_MUSHI, ONE_MUSHI);
+
+    assert_error(pool.stake(&user, ONE_MUSHI, ONE_MUSHI), MushiStakeVaultError::UnauthorizedProgramCall);
+
+    let stake = pool.stake_instruction(pool.stake_accounts(&user), ONE_MUSHI, ONE_MUSHI);
+    pool.bank.process(&via_mushi_program(stake)).unwrap();
+    assert_eq!(pool.bank.token_balance(&user.stake_token_ata), ONE_MUSHI);
+
+    // Neither the vault PDA of any other program nor another signer counts
+    // as the mushi_program.
+    for impostor in [Pubkey::find_program_address(&[MUSHIPROGRAM_VAULT_SEED], &mushi_stake_vault::ID).0, user.key] {
+        let mut accounts = pool.stake_accounts(&user);
+        accounts.mushi_program_token_vault_owner = impostor;
+        let stake = pool.stake_instruction(accounts, ONE_MUSHI, ONE_MUSHI);
+        assert_eq!(pool.bank.process(&via_mushi_program(stake)), Err(program_error(ErrorCode::ConstraintSeeds)));
+    }
+}
+
+#[test]
+fn program_gated_stake_cannot_ride_on_a_mushi_program_instruction() {
+    let mut pool = Pool::new(spl_token::ID);
+    let user = pool.user(2 * ONE_MUSHI, 2 * ONE_MUSHI);
+    let other = pool.user(ONE_MUSHI, ONE_MUSHI);
+
+    // A genuine mushi_program call earlier in the transaction does not
+    // authorize a direct stake after it, for the same or another user.
+    for sandwiched in [&user, &other] {
+        let transaction = [
+            via_mushi_program(pool.stake_instruction(pool.stake_accounts(&user), ONE_MUSHI, ONE_MUSHI)),
+            pool.stake_instruction(pool.stake_accounts(sandwiched), ONE_MUSHI, ONE_MUSHI),
+        ];
+        assert_error(pool.bank.process_transaction(&transaction), MushiStakeVaultError::UnauthorizedProgramCall);
+        assert_eq!(pool.bank.token_balance(&user.mushi_token_ata), 2 * ONE_MUSHI);
+        assert_eq!(pool.main_state().staking_token_total_supply, 0);
+    }
+
+    let transaction = [
+        via_mushi_program(pool.stake_instruction(pool.stake_accounts(&user), ONE_MUSHI, ONE_MUSHI)),
+        via_mushi_program(pool.stake_instruction(pool.stake_accounts(&other), ONE_MUSHI, ONE_MUSHI)),
+    ];
+    pool.bank.process_transaction(&transaction).unwrap();
+    assert_eq!(pool.main_state().staking_token_total_supply, 2 * ONE_MUSHI);
+}
+
+#[test]
+fn stake_in_allowlist_mode_requires_allowlist_entry() {
+    let mut pool = Pool::new(spl_token::ID);
+    pool.update(UpdatePoolInput { access_mode: Some(AccessMode::Allowlist), ..pool.no_updates() }).unwrap();
+    let user = pool.user(ONE_MUSHI, ONE_MUSHI);
+    assert_error(pool.stake(&user, ONE_MUSHI, ONE_MUSHI), MushiStakeVaultError::NotAllowlisted);
+
+    let allowlist_entry = pool.allowlist_entry(&user.key);
+    let add = instruction(
+        accounts::AddToAllowlist {
+            admin: pool.admin,
+            main_state: pool.main_state,
+            user: user.key,
+            allowlist_entry,
+            system_program: system_program::ID,
+        },
+        mushi_stake_vault::instruction::AddToAllowlist {},
+    );
+    pool.bank.process(&add).unwrap();
+    assert_eq!(pool.bank.account::<AllowlistEntry>(&allowlist_entry).user, user.key);
+
+    let mut accounts = pool.stake_accounts(&user);
+    accounts.allowlist_entry = Some(allowlist_entry);
+    let stake = pool.stake_instruction(accounts, ONE_MUSHI, ONE_MUSHI);
+    pool.bank.process(&stake).unwrap();
+
+    // Another user's entry does not satisfy the allowlist seeds.
+    let other = pool.user(ONE_MUSHI, ONE_MUSHI);
+    let mut accounts = pool.stake_accounts(&other);
+    accounts.allowlist_entry = Some(allowlist_entry);
+    let stake = pool.stake_instruction(accounts, ONE_MUSHI, ONE_MUSHI);
+    assert!(pool.bank.process(&stake).is_err());
+}
+
+#[test]
+fn stake_validates_amounts() {
+    let mut pool = Pool::open(spl_token::ID);
+    let user = pool.user(ONE_MUSHI, ONE_MUSHI);
+
+    assert_error(pool.stake(&user, 2 * ONE_MUSHI, 2 * ONE_MUSHI), MushiStakeVaultError::InsufficientMushiTokenAmount);
+    assert_error(pool.stake(&user, 0, 0), MushiStakeVaultError::InsufficientStakeTokenAmount);
+    assert_error(pool.stake(&user, ONE_MUSHI, ONE_MUSHI - 1), MushiStakeVaultError::InvalidDepositRatio);
+
+    pool.update(UpdatePoolInput { eclipse_per_mushi_bps: Some(20_000), ..pool.no_updates() }).unwrap();
+    assert_error(pool.stake(&user, ONE_MUSHI, 2 * ONE_MUSHI), MushiStakeVaultError::InsufficientEclipseTokenAmount);
+    pool.stake(&user, ONE_MUSHI / 2, ONE_MUSHI).unwrap();
+    assert_eq!(pool.bank.token_balance(&pool.eclipse_token_vault), ONE_MUSHI);
+}
+
+#[test]
+fn stake_eclipse_source_follows_deposit_mode() {
+    let mut pool = Pool::open(spl_token::ID);
+    let user = pool.user(10 * ONE_MUSHI, 10 * ONE_MUSHI);
+    let mushi_only = |pool: &Pool, user: &User, eclipse_token_mushi_program_vault: Option<Pubkey>| accounts::Stake {
+        user_eclipse_token_ata: None,
+        eclipse_token_mushi_program_vault,
+        ..pool.stake_accounts(user)
+    };
+
+    // Required: the user's Eclipse account must be present.
+    let stake = pool.stake_instruction(mushi_only(&pool, &user, None), ONE_MUSHI, 0);
+    assert_error(pool.bank.process(&stake), MushiStakeVaultError::MissingEclipseTokenAccount);
+
+    // Optional: MUSHI-only stakes are allowed, but cannot claim an Eclipse amount.
+    pool.update(UpdatePoolInput { eclipse_deposit_mode: Some(EclipseDepositMode::Optional), ..pool.no_updates() }).unwrap();
+    let stake = pool.stake_instruction(mushi_only(&pool, &user, None), ONE_MUSHI, ONE_MUSHI);
+    assert_error(pool.bank.process(&stake), MushiStakeVaultError::MissingEclipseTokenAccount);
+    let stake = pool.stake_instruction(mushi_only(&pool, &user, None), ONE_MUSHI, 0);
+    pool.bank.process(&stake).unwrap();
+    assert_eq!(pool.bank.token_balance(&pool.eclipse_token_vault), 0);
+    pool.stake(&user, ONE_MUSHI, ONE_MUSHI).unwrap();
+    assert_eq!(pool.bank.token_balance(&pool.eclipse_token_vault), ONE_MUSHI);
+
+    // MushiProgramVault: Eclipse comes from the mushi_program vault, which must sign.
+    pool.update(UpdatePoolInput {
+        eclipse_deposit_mode: Some(EclipseDepositMode::MushiProgramVault),
+        ..pool.no_updates()
+    })
+    .unwrap();
+    let vault_stake = |pool: &Pool, user: &User| {
+        pool.stake_instruction(mushi_only(pool, user, Some(pool.eclipse_token_mushi_program_vault)), ONE_MUSHI, ONE_MUSHI)
+    };
+    assert_error(pool.bank.process(&vault_stake(&pool, &user)), MushiStakeVaultError::UnauthorizedProgramCall);
+    // A position holds Eclipse from a single source.
+    assert_error(
+        pool.bank.process(&via_mushi_program(vault_stake(&pool, &user))),
+        MushiStakeVaultError::EclipseSourceMismatch,
+    );
+    let vault_user = pool.user(10 * ONE_MUSHI, 10 * ONE_MUSHI);
+    let mushi_program_vault_before = pool.bank.token_balance(&pool.eclipse_token_mushi_program_vault);
+    pool.bank.process(&via_mushi_program(vault_stake(&pool, &vault_user))).unwrap();
+    assert_eq!(
+        pool.bank.token_balance(&pool.eclipse_token_mushi_program_vault),
+        mushi_program_vault_before - ONE_MUSHI
+    );
+    assert_eq!(pool.bank.token_balance(&vault_user.eclipse_token_ata), 10 * ONE_MUSHI);
+
+    // Unstaking pays the Eclipse back to the vault it came from, never to the user.
+    assert_error(pool.unstake(&vault_user, ONE_MUSHI), MushiStakeVaultError::EclipseSourceMismatch);
+    let unstake = pool.unstake_instruction(
+        accounts::Unstake {
+            eclipse_token_source: Some(pool.eclipse_token_mushi_program_vault),
+            ..pool.unstake_accounts(&vault_user)
+        },
+        UnstakeInput { amount: ONE_MUSHI, min_mushi_token_amount: 0, min_eclipse_token_amount: 0 },
+    );
+    pool.bank.process(&unstake).unwrap();
+    assert_eq!(pool.bank.token_balance(&pool.eclipse_token_mushi_program_vault), mushi_program_vault_before);
+    assert_eq!(pool.bank.token_balance(&vault_user.eclipse_token_ata), 10 * ONE_MUSHI);
+
+    let stake = pool.stake_instruction(mushi_only(&pool, &user, None), ONE_MUSHI, ONE_MUSHI);
+    assert_error(pool.bank.process(&via_mushi_program(stake)), MushiStakeVaultError::MissingEclipseTokenAccount);
+}
+
+#[test]
+fn unstake_validates_amounts_and_slippage() {
+    let mut pool = Pool::open(spl_token::ID);
+    let user = pool.user(ONE_MUSHI, ONE_MUSHI);
+    pool.stake(&user, ONE_MUSHI, ONE_MUSHI).unwrap();
+
+    assert_error(pool.unstake(&user, 0), MushiStakeVaultError::InsufficientStakeTokenAmount);
+    assert_error(pool.unstake(&user, ONE_MUSHI + 1), MushiStakeVaultError::InsufficientStakeTokenAmount);
+
+    for (min_mushi_token_amount, min_eclipse_token_amount) in [(ONE_MUSHI + 1, 0), (0, ONE_MUSHI + 1)] {
+        let unstake = pool.unstake_instruction(
+            pool.unstake_accounts(&user),
+            UnstakeInput { amount: ONE_MUSHI, min_mushi_token_amount, min_eclipse_token_amount },
+        );
+        assert_error(pool.bank.process(&unstake), MushiStakeVaultError::UnstakeSlippageExceeded);
+    }
+    let unstake = pool.unstake_instruction(
+        pool.unstake_accounts(&user),
+        UnstakeInput { amount: ONE_MUSHI, min_mushi_token_amount: ONE_MUSHI, min_eclipse_token_amount: ONE_MUSHI },
+    );
+    pool.bank.process(&unstake).unwrap();
+    assert_eq!(pool.bank.token_balance(&user.mushi_token_ata), ONE_MUSHI);
+}
+
+#[test]
+fn unstake_is_disabled_while_a_cooldown_is_configured() {
+    let mut pool = Pool::open(spl_token::ID);
+    let user = pool.user(ONE_MUSHI, ONE_MUSHI);
+    pool.stake(&user, ONE_MUSHI, ONE_MUSHI).unwrap();
+    pool.update(UpdatePoolInput { unstake_cooldown: Some(60), ..pool.no_updates() }).unwrap();
+    assert_error(pool.unstake(&user, ONE_MUSHI), MushiStakeVaultError::UnstakeCooldownActive);
+}
+
+#[test]
+fn request_unstake_then_withdraw_after_the_cooldown() {
+    let mut pool = Pool::open(spl_token::ID);
+    let user = pool.user(2 * ONE_MUSHI, 2 * ONE_MUSHI);
+    pool.stake(&user, 2 * ONE_MUSHI, 2 * ONE_MUSHI).unwrap();
+    pool.update(UpdatePoolInput { unstake_cooldown: Some(60), ..pool.no_updates() }).unwrap();
+
+    pool.request_unstake(&user, ONE_MUSHI).unwrap();
+    assert_eq!(pool.bank.token_balance(&user.stake_token_ata), ONE_MUSHI);
+    assert_eq!(pool.bank.token_balance(&user.mushi_token_ata), 0);
+    let main_state = pool.main_state();
+    assert_eq!((main_state.pending_unstake_mushi_amount, main_state.pending_unstake_eclipse_amount), (ONE_MUSHI, ONE_MUSHI));
+
+    pool.bank.warp(59);
+    assert_error(pool.withdraw_unstaked(&user), MushiStakeVaultError::UnstakeStillLocked);
+    pool.bank.warp(1);
+    pool.withdraw_unstaked(&user).unwrap();
+    assert_eq!(pool.bank.token_balance(&user.mushi_token_ata), ONE_MUSHI);
+    assert_eq!(pool.bank.token_balance(&user.eclipse_token_ata), ONE_MUSHI);
+    let main_state = pool.main_state();
+    assert_eq!((main_state.pending_unstake_mushi_amount, main_state.pending_unstake_eclipse_amount), (0, 0));
+
+    // The paid-out request is closed, so it cannot be withdrawn twice but can be reopened.
+    let unstake_request = pool.bank.get(&user.unstake_request).unwrap();
+    assert_eq!((unstake_request.lamports, unstake_request.data.len()), (0, 0));
+    assert!(pool.withdraw_unstaked(&user).is_err());
+    pool.request_unstake(&user, ONE_MUSHI).unwrap();
+    pool.bank.warp(60);
+    pool.withdraw_unstaked(&user).unwrap();
+    assert_eq!(pool.bank.token_balance(&user.mushi_token_ata), 2 * ONE_MUSHI);
+}
+
+#[test]
+fn unstake_without_stake_entry_fails() {
+    let mut pool = Pool::open(spl_token::ID);
+    let user = pool.user(ONE_MUSHI, ONE_MUSHI);
+    assert!(pool.unstake(&user, ONE_MUSHI).is_err());
+}
+
+#[test]
+fn unstake_is_not_gated_by_access_mode() {
+    let mut pool = Pool::new(spl_token::ID);
+    let user = pool.user(2 * ONE_MUSHI, 2 * ONE_MUSHI);
+    let stake = pool.stake_instruction(pool.stake_accounts(&user), 2 * ONE_MUSHI, 2 * ONE_MUSHI);
+    pool.bank.process(&via_mushi_program(stake)).unwrap();
+
+    // A position opened through `mushi_program` can be exited directly, and
+    // narrowing the access mode afterwards does not trap it either.
+    pool.unstake(&user, ONE_MUSHI).unwrap();
+    pool.update(UpdatePoolInput { access_mode: Some(AccessMode::Allowlist), ..pool.no_updates() }).unwrap();
+    pool.unstake(&user, ONE_MUSHI).unwrap();
+    assert_eq!(pool.bank.token_balance(&user.mushi_token_ata), 2 * ONE_MUSHI);
+    assert_eq!(pool.bank.token_balance(&user.eclipse_token_ata), 2 * ONE_MUSHI);
+}
+
+#[test]
+fn stake_and_unstake_return_results_to_mushi_program() {
+    let mut pool = Pool::new(spl_token::ID);
+    let user = pool.user(10 * ONE_MUSHI, 10 * ONE_MUSHI);
+
+    let stake = pool.stake_instruction(pool.stake_accounts(&user), 4 * ONE_MUSHI, 4 * ONE_MUSHI);
+    pool.bank.process(&via_mushi_program(stake)).unwrap();
+    assert_eq!(
+        return_data::stake_result().unwrap(),
+        StakeResult {
+            mushi_token_amount: 4 * ONE_MUSHI,
+            eclipse_token_amount: 4 * ONE_MUSHI,
+            stake_token_amount: 4 * ONE_MUSHI,
+            total_mushi_token_amount: 4 * ONE_MUSHI,
+            total_eclipse_token_amount: 4 * ONE_MUSHI,
+            staking_token_total_supply: 4 * ONE_MUSHI,
+        }
+    );
+
+    let unstake = pool.unstake_instruction(
+        pool.unstake_accounts(&user),
+        UnstakeInput { amount: ONE_MUSHI, min_mushi_token_amount: 0, min_eclipse_token_amount: 0 },
+    );
+    pool.bank.process(&via_mushi_program(unstake)).unwrap();
+    assert_eq!(
+        return_data::unstake_result().unwrap(),
+        UnstakeResult {
+            stake_token_amount: ONE_MUSHI,
+            mushi_token_amount: ONE_MUSHI,
+            eclipse_token_amount: ONE_MUSHI,
+            penalty_amount: 0,
+            total_mushi_token_amount: 3 * ONE_MUSHI,
+            total_eclipse_token_amount: 3 * ONE_MUSHI,
+            staking_token_total_supply: 3 * ONE_MUSHI,
+        }
+    );
+
+    pool.update(pool.no_updates()).unwrap();
+    assert_eq!(return_data::unstake_result(), Err(MushiStakeVaultError::MissingReturnData.into()));
+}
+
+#[test]
+fn early_unstake_penalty_goes_to_configured_destination() {
+    let mut pool = Pool::open(spl_token::ID);
+    let staker = pool.user(ONE_MUSHI, ONE_MUSHI);
+    pool.stake(&staker, ONE_MUSHI, ONE_MUSHI).unwrap();
+    let user = pool.user(ONE_MUSHI, ONE_MUSHI);
+    pool.stake(&user, ONE_MUSHI, ONE_MUSHI).unwrap();
+    pool.update(UpdatePoolInput {
+        early_unstake_penalty_bps: Some(1_000),
+        penalty_decay_period: Some(3_600),
+        penalty_curve: Some(PenaltyCurve::Cliff),
+        ..pool.no_updates()
+    })
+    .unwrap();
+
+    // Vault: the penalty stays behind for the remaining stakers.
+    pool.unstake(&user, ONE_MUSHI / 2).unwrap();
+    assert_eq!(pool.bank.token_balance(&user.mushi_token_ata), ONE_MUSHI / 2 - ONE_MUSHI / 20);
+    assert_eq!(pool.main_state().mushi_token_amount, 3 * ONE_MUSHI / 2 + ONE_MUSHI / 20);
+    assert_eq!(return_data::unstake_result().unwrap().penalty_amount, ONE_MUSHI / 20);
+
+    // Treasury: the penalty is paid out and the treasury account is required.
+    let treasury = Pubkey::new_unique();
+    let treasury_mushi_token_ata = pool.bank.create_ata(treasury, pool.mushi_token_mint, 0);
+    pool.update(UpdatePoolInput {
+        penalty_destination: Some(PenaltyDestination::Treasury),
+        treasury: Some(treasury),
+        ..pool.no_updates()
+    })
+    .unwrap();
+    assert_error(pool.unstake(&user, ONE_MUSHI / 4), MushiStakeVaultError::InvalidTreasuryAccount);
+
+    for (treasury_account, result) in [
+        (user.mushi_token_ata, Err(program_error(MushiStakeVaultError::InvalidTreasuryAccount))),
+        (treasury_mushi_token_ata, Ok(())),
+    ] {
+        let unstake = pool.unstake_instruction(
+            accounts::Unstake { treasury_mushi_token_ata: Some(treasury_account), ..pool.unstake_accounts(&user) },
+            UnstakeInput { amount: ONE_MUSHI / 4, min_mushi_token_amount: 0, min_eclipse_token_amount: 0 },
+        );
+        assert_eq!(pool.bank.process(&unstake), result);
+    }
+    assert!(pool.bank.token_balance(&treasury_mushi_token_ata) > 0);
+
+    // Once the decay period has passed there is no penalty.
+    pool.bank.warp(3_600);
+    let before = pool.bank.token_balance(&treasury_mushi_token_ata);
+    pool.unstake(&user, ONE_MUSHI / 4).unwrap();
+    assert_eq!(pool.bank.token_balance(&treasury_mushi_token_ata), before);
+}
+
+#[test]
+fn vaults_are_pinned_to_the_pool_atas() {
+    let mut pool = Pool::open(spl_token::ID);
+    let user = pool.user(ONE_MUSHI, ONE_MUSHI);
+    pool.stake(&user, ONE_MUSHI, ONE_MUSHI).unwrap();
+    // The reward vault shares the MUSHI vault's mint and authority.
+    let reward_token_vault = pool.reward_token_vault;
+
+    let stake = pool.stake_instruction(
+        accounts::Stake { mushi_token_vault: reward_token_vault, ..pool.stake_accounts(&user) },
+        ONE_MUSHI,
+        ONE_MUSHI,
+    );
+    assert_eq!(pool.bank.process(&stake), Err(program_error(ErrorCode::ConstraintAddress)));
+    let unstake = pool.unstake_instruction(
+        accounts::Unstake { mushi_token_vault: reward_token_vault, ..pool.unstake_accounts(&user) },
+        UnstakeInput { amount: ONE_MUSHI, min_mushi_token_amount: 0, min_eclipse_token_amount: 0 },
+    );
+    assert_eq!(pool.bank.process(&unstake), Err(program_error(ErrorCode::ConstraintAddress)));
+}
+
+#[test]
+fn unstake_requires_vault_to_cover_treasury_penalty() {
+    let mut pool = Pool::open(spl_token::ID);
+    let user = pool.user(ONE_MUSHI, ONE_MUSHI);
+    pool.stake(&user, ONE_MUSHI, ONE_MUSHI).unwrap();
+    let treasury = Pubkey::new_unique();
+    let treasury_mushi_token_ata = pool.bank.create_ata(treasury, pool.mushi_token_mint, 0);
+    pool.update(UpdatePoolInput {
+        early_unstake_penalty_bps: Some(1_000),
+        penalty_decay_period: Some(3_600),
+        penalty_curve: Some(PenaltyCurve::Cliff),
+        penalty_destination: Some(PenaltyDestination::Treasury),
+        treasury: Some(treasury),
+        ..pool.no_updates()
+    })
+    .unwrap();
+
+    // The vault holds the user's share net of the penalty, but not the penalty too.
+    pool.bank.set_token_balance(&pool.mushi_token_vault, ONE_MUSHI - 1);
+    let unstake = pool.unstake_instruction(
+        accounts::Unstake { treasury_mushi_token_ata: Some(treasury_mushi_token_ata), ..pool.unstake_accounts(&user) },
+        UnstakeInput { amount: ONE_MUSHI, min_mushi_token_amount: 0, min_eclipse_token_amount: 0 },
+    );
+    assert_error(pool.bank.process(&unstake), MushiStakeVaultError::InsufficientMushiTokenAmount);
+
+    pool.bank.set_token_balance(&pool.mushi_token_vault, ONE_MUSHI);
+    pool.bank.process(&unstake).unwrap();
+    assert_eq!(pool.bank.token_balance(&treasury_mushi_token_ata), ONE_MUSHI / 10);
+    assert_eq!(pool.bank.token_balance(&pool.mushi_token_vault), 0);
+}
+
+#[test]
+fn request_unstake_requires_vault_to_cover_treasury_penalty() {
+    let mut pool = Pool::open(spl_token::ID);
+    let user = pool.user(ONE_MUSHI, ONE_MUSHI);
+    pool.stake(&user, ONE_MUSHI, ONE_MUSHI).unwrap();
+    let treasury = Pubkey::new_unique();
+    let treasury_mushi_token_ata = pool.bank.create_ata(treasury, pool.mushi_token_mint, 0);
+    pool.update(UpdatePoolInput {
+        unstake_cooldown: Some(60),
+        early_unstake_penalty_bps: Some(1_000),
+        penalty_decay_period: Some(3_600),
+        penalty_curve: Some(PenaltyCurve::Cliff),
+        penalty_destination: Some(PenaltyDestination::Treasury),
+        treasury: Some(treasury),
+        ..pool.no_updates()
+    })
+    .unwrap();
+
+    // The redeemed MUSHI stays in the vault as a pending unstake, so the
+    // penalty has to be covered on top of it.
+    pool.bank.set_token_balance(&pool.mushi_token_vault, ONE_MUSHI - 1);
+    let request = instruction(
+        accounts::RequestUnstake { treasury_mushi_token_ata: Some(treasury_mushi_token_ata), ..pool.request_unstake_accounts(&user) },
+        mushi_stake_vault::instruction::RequestUnstake {
+            input: UnstakeInput { amount: ONE_MUSHI, min_mushi_token_amount: 0, min_eclipse_token_amount: 0 },
+        },
+    );
+    assert_error(pool.bank.process(&request), MushiStakeVaultError::InsufficientMushiTokenAmount);
+
+    pool.bank.set_token_balance(&pool.mushi_token_vault, ONE_MUSHI);
+    pool.bank.process(&request).unwrap();
+    assert_eq!(pool.bank.token_balance(&treasury_mushi_token_ata), ONE_MUSHI / 10);
+    assert_eq!(pool.main_state().pending_unstake_mushi_amount, ONE_MUSHI - ONE_MUSHI / 10);
+}
+
+#[test]
+fn update_pool_applies_changes() {
+    let mut pool = Pool::new(spl_token::ID);
+    let guardian = Pubkey::new_unique();
+    let treasury = Pubkey::new_unique();
+    pool.update(UpdatePoolInput {
+        mushi_program: MOCK_MUSHI_PROGRAM_ID,
+        access_mode: Some(AccessMode::Allowlist),
+        guardian: Some(guardian),
+        unstake_cooldown: Some(60),
+        early_unstake_penalty_bps: Some(500),
+        penalty_decay_period: Some(120),
+        penalty_curve: Some(PenaltyCurve::Cliff),
+        penalty_destination: Some(PenaltyDestination::Treasury),
+        treasury: Some(treasury),
+        eclipse_deposit_mode: Some(EclipseDepositMode::Optional),
+        eclipse_per_mushi_bps: Some(5_000),
+    })
+    .unwrap();
+
+    let main_state = pool.main_state();
+    assert_eq!(main_state.access_mode, AccessMode::Allowlist);
+    assert_eq!(main_state.guardian, guardian);
+    assert_eq!(main_state.unstake_cooldown, 60);
+    assert_eq!(main_state.early_unstake_penalty_bps, 500);
+    assert_eq!(main_state.penalty_decay_period, 120);
+    assert_eq!(main_state.penalty_curve, PenaltyCurve::Cliff);
+    assert_eq!(main_state.penalty_destination, PenaltyDestination::Treasury);
+    assert_eq!(main_state.treasury, treasury);
+    assert_eq!(main_state.eclipse_deposit_mode, EclipseDepositMode::Optional);
+    assert_eq!(main_state.eclipse_per_mushi_bps, 5_000);
+
+    // `None` leaves every optional field unchanged.
+    pool.update(pool.no_updates()).unwrap();
+    assert_eq!(pool.main_state().unstake_cooldown, 60);
+    assert_eq!(pool.main_state().treasury, treasury);
+}
+
+#[test]
+fn update_pool_rejects_invalid_input() {
+    let mut pool = Pool::new(spl_token::ID);
+    let stranger = Pubkey::new_unique();
+    assert_error(pool.update_as(stranger, pool.no_updates()), MushiStakeVaultError::UnauthorizedAdminAction);
+
+    for (input, error) in [
+        (UpdatePoolInput { unstake_cooldown: Some(-1), ..pool.no_updates() }, MushiStakeVaultError::InvalidUnstakeCooldown),
+        (UpdatePoolInput { early_unstake_penalty_bps: Some(10_001), ..pool.no_updates() }, MushiStakeVaultError::InvalidPenaltyConfig),
+        (UpdatePoolInput { penalty_decay_period: Some(-1), ..pool.no_updates() }, MushiStakeVaultError::InvalidPenaltyConfig),
+        (
+            UpdatePoolInput { penalty_destination: Some(PenaltyDestination::Treasury), ..pool.no_updates() },
+            MushiStakeVaultError::InvalidTreasuryAccount,
+        ),
+        (UpdatePoolInput { eclipse_per_mushi_bps: Some(0), ..pool.no_updates() }, MushiStakeVaultError::InvalidDepositRatio),
+    ] {
+        assert_error(pool.update(input), error);
+    }
+}
+
+#[test]
+fn pause_blocks_flagged_instructions() {
+    let mut pool = Pool::open(spl_token::ID);
+    let user = pool.user(ONE_MUSHI, ONE_MUSHI);
+    let stranger = Pubkey::new_unique();
+
+    assert_error(pool.set_pause_as(stranger, PAUSE_STAKE), MushiStakeVaultError::UnauthorizedPauseAuthority);
+    assert_error(pool.set_pause_as(pool.admin, PAUSE_ALL + 1), MushiStakeVaultError::InvalidPauseFlags);
+
+    pool.set_pause_as(pool.admin, PAUSE_STAKE).unwrap();
+    assert_error(pool.stake(&user, ONE_MUSHI, ONE_MUSHI), MushiStakeVaultError::ProgramPaused);
+
+    pool.set_pause_as(pool.admin, PAUSE_ADMIN).unwrap();
+    assert_error(pool.update(pool.no_updates()), MushiStakeVaultError::ProgramPaused);
+    pool.stake(&user, ONE_MUSHI, ONE_MUSHI).unwrap();
+
+    pool.set_pause_as(pool.admin, 0).unwrap();
+    pool.update(pool.no_updates()).unwrap();
+}
+
+#[test]
+fn guardian_can_only_add_pause_flags() {
+    let mut pool = Pool::open(spl_token::ID);
+    let guardian = Pubkey::new_unique();
+    pool.update(UpdatePoolInput { guardian: Some(guardian), ..pool.no_updates() }).unwrap();
+
+    pool.set_pause_as(guardian, PAUSE_STAKE).unwrap();
+    pool.set_pause_as(guardian, PAUSE_STAKE | PAUSE_UNSTAKE).unwrap();
+    for paused in [0, PAUSE_UNSTAKE, PAUSE_UNSTAKE | PAUSE_ADMIN] {
+        assert_error(pool.set_pause_as(guardian, paused), MushiStakeVaultError::UnauthorizedPauseAuthority);
+    }
+    assert_eq!(pool.main_state().paused, PAUSE_STAKE | PAUSE_UNSTAKE);
+
+    pool.set_pause_as(pool.admin, PAUSE_STAKE).unwrap();
+    pool.set_pause_as(pool.admin, 0).unwrap();
+    assert_eq!(pool.main_state().paused, 0);
+}
+
+#[test]
+fn admin_transfer_requires_the_proposed_admin_to_accept() {
+    let mut pool = Pool::new(spl_token::ID);
+    let admin = pool.admin;
+    let new_admin = Pubkey::new_unique();
+    let stranger = Pubkey::new_unique();
+
+    assert_error(pool.propose_admin_as(stranger, new_admin), MushiStakeVaultError::UnauthorizedAdminAction);
+    assert_error(pool.propose_admin_as(admin, Pubkey::default()), MushiStakeVaultError::InvalidNewAdmin);
+    assert_error(pool.propose_admin_as(admin, admin), MushiStakeVaultError::InvalidNewAdmin);
+    assert_error(pool.accept_admin_as(new_admin), MushiStakeVaultError::NoPendingAdminTransfer);
+    assert_error(pool.cancel_admin_transfer_as(admin), MushiStakeVaultError::NoPendingAdminTransfer);
+
+    pool.propose_admin_as(admin, new_admin).unwrap();
+    assert_eq!((pool.main_state().admin, pool.main_state().pending_admin), (admin, new_admin));
+    assert_error(pool.accept_admin_as(stranger), MushiStakeVaultError::UnauthorizedPendingAdmin);
+    assert_error(pool.cancel_admin_transfer_as(new_admin), MushiStakeVaultError::UnauthorizedAdminAction);
+
+    pool.cancel_admin_transfer_as(admin).unwrap();
+    assert_eq!(pool.main_state().pending_admin, Pubkey::default());
+    assert_error(pool.accept_admin_as(new_admin), MushiStakeVaultError::NoPendingAdminTransfer);
+
+    pool.propose_admin_as(admin, new_admin).unwrap();
+    pool.accept_admin_as(new_admin).unwrap();
+    let accepted = pool.bank.events::<AdminTransferAccepted>().remove(0);
+    assert_eq!((accepted.previous_admin, accepted.admin), (admin, new_admin));
+    assert_eq!((pool.main_state().admin, pool.main_state().pending_admin), (new_admin, Pubkey::default()));
+
+    // Only the new admin holds admin rights from here on.
+    let input = pool.no_updates();
+    assert_error(pool.update_as(admin, input.clone()), MushiStakeVaultError::UnauthorizedAdminAction);
+    pool.update_as(new_admin, input).unwrap();
+}
+
+#[test]
+fn reconcile_absorbs_only_a_mushi_surplus() {
+    let mut pool = Pool::open(spl_token::ID);
+    let user = pool.user(4 * ONE_MUSHI, 4 * ONE_MUSHI);
+    pool.stake(&user, 4 * ONE_MUSHI, 4 * ONE_MUSHI).unwrap();
+    let audit = pool.audit();
+    assert_eq!((audit.mushi_token_delta, audit.eclipse_token_delta, audit.stake_token_supply_delta), (0, 0, 0));
+
+    // MUSHI sent straight to the vault shows up as a surplus.
+    let mushi_token_vault = pool.mushi_token_vault;
+    pool.bank.mint_to(pool.mushi_token_mint, mushi_token_vault, ONE_MUSHI);
+    let audit = pool.audit();
+    assert_eq!((audit.mushi_token_vault_amount, audit.expected_mushi_token_amount), (5 * ONE_MUSHI, 4 * ONE_MUSHI as u128));
+    assert_eq!(audit.mushi_token_delta, ONE_MUSHI as i128);
+
+    assert_error(pool.reconcile_as(user.key, ReconcileMode::DonateSurplusToRewards), MushiStakeVaultError::UnauthorizedAdminAction);
+    pool.reconcile(ReconcileMode::DonateSurplusToRewards).unwrap();
+    assert_eq!(pool.bank.token_balance(&pool.reward_token_vault), ONE_MUSHI);
+    assert_eq!(pool.bank.token_balance(&pool.mushi_token_vault), 4 * ONE_MUSHI);
+    assert_eq!(pool.main_state().mushi_token_amount, 4 * ONE_MUSHI);
+    assert_eq!(pool.audit().mushi_token_delta, 0);
+
+    pool.bank.mint_to(pool.mushi_token_mint, mushi_token_vault, ONE_MUSHI);
+    pool.reconcile(ReconcileMode::CorrectCounters).unwrap();
+    let main_state = pool.main_state();
+    assert_eq!(main_state.mushi_token_amount, 5 * ONE_MUSHI);
+    assert_eq!((main_state.eclipse_token_amount, main_state.staking_token_total_supply), (4 * ONE_MUSHI, 4 * ONE_MUSHI));
+    assert_eq!(pool.audit().mushi_token_delta, 0);
+}
+
+#[test]
+fn reconcile_rejects_a_shortfall() {
+    let mut pool = Pool::open(spl_token::ID);
+    let user = pool.user(4 * ONE_MUSHI, 4 * ONE_MUSHI);
+    pool.stake(&user, 4 * ONE_MUSHI, 4 * ONE_MUSHI).unwrap();
+
+    // Writing a shortfall into the counters would cut what stake entries can
+    // redeem, so the reconcile fails and leaves them alone.
+    for vault in [pool.mushi_token_vault, pool.eclipse_token_vault] {
+        pool.bank.set_token_balance(&vault, 3 * ONE_MUSHI);
+        assert!(pool.audit().mushi_token_delta < 0 || pool.audit().eclipse_token_delta < 0);
+        for mode in [ReconcileMode::CorrectCounters, ReconcileMode::DonateSurplusToRewards] {
+            assert_error(pool.reconcile(mode), MushiStakeVaultError::ReconcileShortfall);
+        }
+        pool.bank.set_token_balance(&vault, 4 * ONE_MUSHI);
+    }
+    let main_state = pool.main_state();
+    assert_eq!((main_state.mushi_token_amount, main_state.eclipse_token_amount), (4 * ONE_MUSHI, 4 * ONE_MUSHI));
+    pool.reconcile(ReconcileMode::CorrectCounters).unwrap();
+}
+
+#[test]
+fn pools_with_different_ids_are_isolated() {
+    let mut first = Pool::open(spl_token::ID);
+    let staker = first.user(4 * ONE_MUSHI, 4 * ONE_MUSHI);
+    first.stake(&staker, 4 * ONE_MUSHI, 4 * ONE_MUSHI).unwrap();
+    let first_main_state = first.main_state();
+    let (first_key, first_mushi_token_vault, first_stake_token_mint) = (first.main_state, first.mushi_token_vault, first.stake_token_mint);
+
+    // The second pool shares the bank and the MUSHI mint with the first.
+    let mut second = Pool::with_pool_id(first.bank, first.mushi_token_mint, 1);
+    second.update(UpdatePoolInput { access_mode: Some(AccessMode::Open), ..second.no_updates() }).unwrap();
+    assert_ne!(second.main_state, first_key);
+    assert_ne!(second.mushi_token_vault, first_mushi_token_vault);
+    assert_eq!(second.main_state().pool_id, 1);
+    assert_eq!(second.pool_stats().pool_id, 1);
+
+    let user = second.user(2 * ONE_MUSHI, 2 * ONE_MUSHI);
+    second.stake(&user, 2 * ONE_MUSHI, 2 * ONE_MUSHI).unwrap();
+    second.fund_rewards(1_000 * 100).unwrap();
+    second.set_reward_rate(1_000, 100).unwrap();
+    second.bank.warp(50);
+    second.unstake(&user, ONE_MUSHI).unwrap();
+
+    // Nothing done in the second pool touches the first one's state or vault.
+    let main_state: MainState = second.bank.account(&first_key);
+    assert_eq!(main_state.mushi_token_amount, first_main_state.mushi_token_amount);
+    assert_eq!(main_state.staking_token_total_supply, first_main_state.staking_token_total_supply);
+    assert_eq!((main_state.reward_rate, main_state.admin), (0, first_main_state.admin));
+    assert_eq!(second.bank.token_balance(&first_mushi_token_vault), 4 * ONE_MUSHI);
+    assert_eq!(second.bank.mint_supply(&first_stake_token_mint), 4 * ONE_MUSHI);
+    assert_eq!(second.main_state().mushi_token_amount, ONE_MUSHI);
+
+    // Nor can the second pool pay out of the first one's vault.
+    let unstake = second.unstake_instruction(
+        accounts::Unstake { mushi_token_vault: first_mushi_token_vault, ..second.unstake_accounts(&user) },
+        UnstakeInput { amount: ONE_MUSHI, min_mushi_token_amount: 0, min_eclipse_token_amount: 0 },
+    );
+    assert!(second.bank.process(&unstake).is_err());
+    assert_eq!(second.bank.token_balance(&first_mushi_token_vault), 4 * ONE_MUSHI);
+}
+
+#[test]
+fn view_instructions_return_pool_stats_and_user_position() {
+    let mut pool = Pool::open(spl_token::ID);
+    let user = pool.user(10 * ONE_MUSHI, 10 * ONE_MUSHI);
+
+    let stranger = Pubkey::new_unique();
+    let position = pool.user_position(&stranger);
+    assert_eq!((position.user, position.stake_token_balance, position.pending_rewards), (stranger, 0, 0));
+
+    pool.stake(&user, 4 * ONE_MUSHI, 4 * ONE_MUSHI).unwrap();
+    pool.fund_rewards(1_000 * 100).unwrap();
+    pool.set_reward_rate(1_000, 100).unwrap();
+    pool.bank.warp(10);
+
+    let stats = pool.pool_stats();
+    assert_eq!((stats.main_state, stats.pool_id, stats.access_mode), (pool.main_state, 0, AccessMode::Open));
+    assert_eq!(stats.mushi_token_amount, 4 * ONE_MUSHI);
+    assert_eq!(stats.eclipse_token_amount, 4 * ONE_MUSHI);
+    assert_eq!(stats.staking_token_total_supply, 4 * ONE_MUSHI);
+    assert_eq!(stats.exchange_rate, EXCHANGE_RATE_PRECISION);
+    assert_eq!(stats.timestamp, pool.bank.now());
+    // Rewards are accrued into the returned stats only.
+    assert_eq!(pool.main_state().acc_reward_per_share, 0);
+    assert!(stats.acc_reward_per_share > 0);
+
+    let position = pool.user_position(&user.key);
+    assert_eq!(position.stake_token_balance, 4 * ONE_MUSHI);
+    assert_eq!(position.mushi_token_value, 4 * ONE_MUSHI);
+    assert_eq!(position.mushi_token_staked_amount, 4 * ONE_MUSHI);
+    assert_eq!(position.eclipse_token_staked_amount, 4 * ONE_MUSHI);
+    // The sole staker earns its share of the 10 seconds' emissions; the cut
+    // of the virtual shares stays in the reward vault.
+    assert_eq!(position.pending_rewards, 10 * 1_000 * 4 / 5);
+    assert_eq!(pool.bank.account::<StakeEntry>(&user.stake_entry).rewards_amount, 0);
+}
+
+#[test]
+fn set_reward_rate_requires_the_vault_to_cover_the_schedule() {
+    let mut pool = Pool::open(spl_token::ID);
+    assert_error(pool.set_reward_rate(1_000, 100), MushiStakeVaultError::InsufficientRewardVaultBalance);
+    pool.fund_rewards(1_000 * 100 - 1).unwrap();
+    assert_error(pool.set_reward_rate(1_000, 100), MushiStakeVaultError::InsufficientRewardVaultBalance);
+
+    pool.fund_rewards(1).unwrap();
+    let funded = pool.bank.events::<RewardsFunded>().remove(0);
+    assert_eq!((funded.main_state, funded.admin, funded.amount), (pool.main_state, pool.admin, 1));
+    assert_eq!(pool.bank.token_balance(&pool.reward_token_vault), 1_000 * 100);
+
+    let start_time = pool.bank.now();
+    pool.set_reward_rate(1_000, 100).unwrap();
+    let set = pool.bank.events::<RewardRateSet>().remove(0);
+    assert_eq!((set.main_state, set.admin, set.reward_rate), (pool.main_state, pool.admin, 1_000));
+    assert_eq!((set.start_time, set.end_time, set.timestamp), (start_time, start_time + 100, start_time));
+}
+
+#[test]
+fn rewards_accrue_only_within_the_schedule() {
+    let mut pool = Pool::open(spl_token::ID);
+    let user = pool.user(4 * ONE_MUSHI, 4 * ONE_MUSHI);
+    pool.stake(&user, 4 * ONE_MUSHI, 4 * ONE_MUSHI).unwrap();
+    pool.fund_rewards(1_000 * 100).unwrap();
+    pool.set_reward_rate(1_000, 100).unwrap();
+
+    // The sole staker earns 4/5 of the emissions; the rest is the virtual shares' cut.
+    pool.bank.warp(40);
+    assert_eq!(pool.user_position(&user.key).pending_rewards, 40 * 1_000 * 4 / 5);
+    pool.bank.warp(100);
+    assert_eq!(pool.user_position(&user.key).pending_rewards, 100 * 1_000 * 4 / 5);
+
+    // A new schedule starts from the settled rewards of the old one.
+    pool.fund_rewards(2_000 * 10).unwrap();
+    pool.set_reward_rate(2_000, 10).unwrap();
+    pool.bank.warp(5);
+    assert_eq!(pool.user_position(&user.key).pending_rewards, (100 * 1_000 + 5 * 2_000) * 4 / 5);
+}
+
+#[test]
+fn claim_rewards_pays_out_everything_accrued() {
+    let mut pool = Pool::open(spl_token::ID);
+    let user = pool.user(4 * ONE_MUSHI, 4 * ONE_MUSHI);
+    pool.stake(&user, 4 * ONE_MUSHI, 4 * ONE_MUSHI).unwrap();
+    pool.fund_rewards(1_000 * 100).unwrap();
+    pool.set_reward_rate(1_000, 100).unwrap();
+    pool.bank.warp(100);
+
+    pool.claim_rewards(&user).unwrap();
+    let rewards_amount = 100 * 1_000 * 4 / 5;
+    assert_eq!(pool.bank.token_balance(&user.mushi_token_ata), rewards_amount);
+    assert_eq!(pool.bank.token_balance(&pool.reward_token_vault), 1_000 * 100 - rewards_amount);
+    assert_eq!(pool.bank.account::<StakeEntry>(&user.stake_entry).rewards_amount, 0);
+    let claimed = pool.bank.events::<RewardsClaimed>().remove(0);
+    assert_eq!((claimed.main_state, claimed.user, claimed.rewards_amount), (pool.main_state, user.key, rewards_amount));
+
+    // Nothing more accrues past the end of the schedule.
+    pool.bank.warp(100);
+    pool.claim_rewards(&user).unwrap();
+    assert_eq!(pool.bank.token_balance(&user.mushi_token_ata), rewards_amount);
+}
+
+#[test]
+fn claim_rewards_requires_the_vault_to_cover_the_full_amount() {
+    let mut pool = Pool::open(spl_token::ID);
+    let user = pool.user(4 * ONE_MUSHI, 4 * ONE_MUSHI);
+    pool.stake(&user, 4 * ONE_MUSHI, 4 * ONE_MUSHI).unwrap();
+    pool.fund_rewards(1_000 * 100).unwrap();
+    pool.set_reward_rate(1_000, 100).unwrap();
+    pool.bank.warp(100);
+
+    let rewards_amount = 100 * 1_000 * 4 / 5;
+    pool.bank.set_token_balance(&pool.reward_token_vault, rewards_amount - 1);
+    assert_error(pool.claim_rewards(&user), MushiStakeVaultError::InsufficientRewardVaultBalance);
+    assert_eq!(pool.bank.token_balance(&user.mushi_token_ata), 0);
+
+    pool.bank.set_token_balance(&pool.reward_token_vault, rewards_amount);
+    pool.claim_rewards(&user).unwrap();
+    assert_eq!(pool.bank.token_balance(&user.mushi_token_ata), rewards_amount);
+}
+
+#[test]
+fn dust_staker_does_not_block_a_large_staker() {
+    let mut pool = Pool::open(spl_token::ID);
+    let dust = pool.user(1, 1);
+    let whale = pool.user(1_000_000_000 * ONE_MUSHI, 1_000_000_000 * ONE_MUSHI);
+
+    // A single base unit staked alone through a long emission window.
+    pool.stake(&dust, 1, 1).unwrap();
+    pool.fund_rewards(1_000 * ONE_MUSHI * 1_000_000).unwrap();
+    pool.set_reward_rate(1_000 * ONE_MUSHI, 1_000_000).unwrap();
+    pool.bank.warp(500_000);
+
+    pool.stake(&whale, 1_000_000_000 * ONE_MUSHI, 1_000_000_000 * ONE_MUSHI).unwrap();
+    pool.bank.warp(1_000);
+    assert!(pool.user_position(&whale.key).pending_rewards > 0);
+    assert!(pool.user_position(&dust.key).pending_rewards > 0);
+
+    pool.unstake(&whale, 1_000_000_000 * ONE_MUSHI).unwrap();
+    assert_eq!(pool.bank.token_balance(&whale.mushi_token_ata), 1_000_000_000 * ONE_MUSHI);
+    assert!(pool.bank.account::<StakeEntry>(&whale.stake_entry).rewards_amount > 0);
+    pool.unstake(&dust, 1).unwrap();
+    assert_eq!(pool.bank.token_balance(&dust.mushi_token_ata), 1);
+}
+
+#[test]
+fn previews_match_stake_and_unstake() {
+    let mut pool = Pool::with_mushi_transfer_fee(100);
+    let staker = pool.user(ONE_MUSHI, ONE_MUSHI);
+    pool.stake(&staker, ONE_MUSHI, ONE_MUSHI).unwrap();
+    let user = pool.user(2 * ONE_MUSHI, 2 * ONE_MUSHI);
+    pool.update(UpdatePoolInput {
+        early_unstake_penalty_bps: Some(1_000),
+        penalty_decay_period: Some(3_600),
+        ..pool.no_updates()
+    })
+    .unwrap();
+
+    let preview = pool.preview_stake(&user, 2 * ONE_MUSHI, 2 * ONE_MUSHI).unwrap();
+    assert_eq!(pool.bank.token_balance(&user.mushi_token_ata), 2 * ONE_MUSHI);
+    pool.stake(&user, 2 * ONE_MUSHI, 2 * ONE_MUSHI).unwrap();
+    assert_eq!(return_data::stake_result().unwrap(), preview);
+    assert_eq!(pool.bank.token_balance(&user.stake_token_ata), preview.stake_token_amount);
+
+    pool.bank.warp(1_800);
+    let input = UnstakeInput { amount: ONE_MUSHI, min_mushi_token_amount: 0, min_eclipse_token_amount: 0 };
+    let preview = pool.preview_unstake(&user, input.clone()).unwrap();
+    assert!(preview.result.penalty_amount > 0);
+    assert_eq!(preview.mushi_token_received, preview.result.mushi_token_amount - preview.result.mushi_token_amount / 100);
+    assert_eq!(preview.eclipse_token_received, preview.result.eclipse_token_amount);
+
+    let mushi_before = pool.bank.token_balance(&user.mushi_token_ata);
+    pool.bank.process(&pool.unstake_instruction(pool.unstake_accounts(&user), input)).unwrap();
+    assert_eq!(return_data::unstake_result().unwrap(), preview.result);
+    assert_eq!(pool.bank.token_balance(&user.mushi_token_ata) - mushi_before, preview.mushi_token_received);
+
+    // Previews apply the same validation.
+    let input = UnstakeInput { amount: ONE_MUSHI / 2, min_mushi_token_amount: ONE_MUSHI, min_eclipse_token_amount: 0 };
+    assert_eq!(pool.preview_unstake(&user, input), Err(program_error(MushiStakeVaultError::UnstakeSlippageExceeded)));
+    assert_eq!(pool.preview_stake(&user, ONE_MUSHI, 0), Err(program_error(MushiStakeVaultError::InvalidDepositRatio)));
+    pool.update(UpdatePoolInput { eclipse_deposit_mode: Some(EclipseDepositMode::Optional), ..pool.no_updates() }).unwrap();
+    assert_eq!(pool.preview_stake(&user, ONE_MUSHI, 0).unwrap().eclipse_token_amount, 0);
+    let input = UnstakeInput { amount: ONE_MUSHI / 2, min_mushi_token_amount: 0, min_eclipse_token_amount: 0 };
+    assert_eq!(pool.preview_unstake(&user, input.clone()).unwrap().unlock_time, None);
+    pool.set_pause_as(pool.admin, PAUSE_STAKE | PAUSE_UNSTAKE).unwrap();
+    assert_eq!(pool.preview_stake(&user, ONE_MUSHI, 0), Err(program_error(MushiStakeVaultError::ProgramPaused)));
+    assert_eq!(pool.preview_unstake(&user, input), Err(program_error(MushiStakeVaultError::ProgramPaused)));
+}
+
+#[test]
+fn preview_unstake_quotes_request_unstake_during_a_cooldown() {
+    let mut pool = Pool::open(spl_token::ID);
+    let user = pool.user(2 * ONE_MUSHI, 2 * ONE_MUSHI);
+    pool.stake(&user, 2 * ONE_MUSHI, 2 * ONE_MUSHI).unwrap();
+    pool.update(UpdatePoolInput { unstake_cooldown: Some(60), ..pool.no_updates() }).unwrap();
+
+    let input = UnstakeInput { amount: ONE_MUSHI, min_mushi_token_amount: 0, min_eclipse_token_amount: 0 };
+    let preview = pool.preview_unstake(&user, input).unwrap();
+    assert_eq!(preview.unlock_time, Some(pool.bank.now() + 60));
+    assert_eq!((preview.result.mushi_token_amount, preview.result.eclipse_token_amount), (ONE_MUSHI, ONE_MUSHI));
+
+    pool.request_unstake(&user, ONE_MUSHI).unwrap();
+    let unstake_request: UnstakeRequest = pool.bank.account(&user.unstake_request);
+    assert_eq!(Some(unstake_request.unlock_time), preview.unlock_time);
+    assert_eq!(unstake_request.mushi_token_amount, preview.result.mushi_token_amount);
+    assert_eq!(unstake_request.eclipse_token_amount, preview.result.eclipse_token_amount);
+    let main_state = pool.main_state();
+    assert_eq!(main_state.mushi_token_amount, preview.result.total_mushi_token_amount);
+    assert_eq!(main_state.staking_token_total_supply, preview.result.staking_token_total_supply);
+}
+
+#[test]
+fn preview_stake_applies_the_access_mode() {
+    let mut pool = Pool::new(spl_token::ID);
+    let user = pool.user(ONE_MUSHI, ONE_MUSHI);
+    let preview = pool.preview_stake_instruction(&user, ONE_MUSHI, ONE_MUSHI);
+    assert_eq!(pool.bank.process(&preview), Err(program_error(MushiStakeVaultError::UnauthorizedProgramCall)));
+    pool.bank.process(&via_mushi_program(preview)).unwrap();
+    assert_eq!(StakeResult::try_from_slice(&pool.bank.return_data().unwrap().1).unwrap().stake_token_amount, ONE_MUSHI);
+
+    pool.update(UpdatePoolInput { access_mode: Some(AccessMode::Allowlist), ..pool.no_updates() }).unwrap();
+    assert_eq!(pool.preview_stake(&user, ONE_MUSHI, ONE_MUSHI), Err(program_error(MushiStakeVaultError::NotAllowlisted)));
+}
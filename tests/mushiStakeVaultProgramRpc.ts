@@ -16,6 +16,10 @@ import { safeAirdrop } from "./utils";
 const Seeds = {
   mainState: Buffer.from("main_state"),
   vaultOwner: Buffer.from("vault_owner"),
+  stakeEntry: Buffer.from("stake_entry"),
+  rewardVault: Buffer.from("reward_vault"),
+  unstakeRequest: Buffer.from("unstake_request"),
+  mushiProgramVault: Buffer.from("vault"),
 };
 
 const log = console.log;
@@ -48,25 +52,30 @@ export type MainStateInfo = {
   mushiTokenMint: web3.PublicKey;
   eclipseTokenMint: web3.PublicKey;
   stakeTokenMint: web3.PublicKey;
+  mushiProgram: web3.PublicKey;
 };
 
 export class MushiStakeVaultProgramRpc {
   private program: Program<MushiStakeVault>;
   private connection: web3.Connection;
   private programId: web3.PublicKey;
+  private poolId: BN;
   private mainState: web3.PublicKey;
   private globalState: web3.PublicKey;
   private vaultOwner: web3.PublicKey;
+  private rewardTokenVault: web3.PublicKey;
   private provider: AnchorProvider;
 
   constructor({
     rpc, 
     wallet,
     programId,
+    poolId = 0,
   }: {
     rpc: string;
     wallet: Wallet;
     programId: web3.PublicKey;
+    poolId?: number;
   }) {
     this.connection = new web3.Connection(rpc);
     const provider = new AnchorProvider(this.connection, wallet, {
@@ -75,12 +84,17 @@ export class MushiStakeVaultProgramRpc {
     this.provider = provider;
     this.programId = programId;
     this.program = new Program(IDL, programId, provider);
+    this.poolId = new BN(poolId);
     this.mainState = web3.PublicKey.findProgramAddressSync(
-      [Seeds.mainState],
+      [Seeds.mainState, this.poolId.toArrayLike(Buffer, "le", 8)],
       this.programId
     )[0];
     this.vaultOwner = web3.PublicKey.findProgramAddressSync(
-      [Seeds.vaultOwner],
+      [Seeds.vaultOwner, this.mainState.toBuffer()],
+      this.programId
+    )[0];
+    this.rewardTokenVault = web3.PublicKey.findProgramAddressSync(
+      [Seeds.rewardVault, this.mainState.toBuffer()],
       this.programId
     )[0];
   }
@@ -148,6 +162,13 @@ export class MushiStakeVaultProgramRpc {
     }
   }
 
+  // Token program owning `mint`, legacy SPL Token or Token-2022.
+  async getTokenProgram(mint: web3.PublicKey): Promise<web3.PublicKey> {
+    const mintInfo = await this.connection.getAccountInfo(mint);
+    if (!mintInfo) throw "mint account not found";
+    return mintInfo.owner;
+  }
+
   async getMainState(): Promise<MainStateInfo | null> {
     try {
       const mainState = await this.program.account.mainState.fetch(this.mainState);
@@ -159,6 +180,7 @@ export class MushiStakeVaultProgramRpc {
         mushiTokenMint: mainState.mushiTokenMint,
         eclipseTokenMint: mainState.eclipseTokenMint,
         stakeTokenMint: mainState.stakeTokenMint,
+        mushiProgram: mainState.mushiProgram,
       };
     } catch (error) {
       log({ error });
@@ -182,12 +204,11 @@ export class MushiStakeVaultProgramRpc {
       if (!mushiTokenMintInfo) {
         throw "mushiTokenMint account not found";
       }
-      const mushiTokenMintOwner = mushiTokenMintInfo.owner.toBase58();
-      console.log("Mushi Token Mint owner:", mushiTokenMintOwner);
-      console.log("Expected Token Program:", tokenProgram.toBase58());
-      console.log("Mushi Token Mint owner:", mushiTokenMintInfo);
-      if (mushiTokenMintOwner !== tokenProgram.toBase58()) {
-        throw "mushiTokenMint must be owned by the standard Token Program, not Token-2022 Program";
+      // MUSHI may be a legacy SPL Token or a Token-2022 mint; the stake mint follows it.
+      const mushiTokenProgram = mushiTokenMintInfo.owner;
+      console.log("Mushi Token Mint owner:", mushiTokenProgram.toBase58());
+      if (!mushiTokenProgram.equals(tokenProgram) && !mushiTokenProgram.equals(token2022Program)) {
+        throw "mushiTokenMint must be owned by the Token Program or the Token-2022 Program";
       }
 
       const eclipseTokenMintInfo = await this.connection.getAccountInfo(eclipseTokenMint);
@@ -206,9 +227,9 @@ export class MushiStakeVaultProgramRpc {
       const stakeTokenMint = stakeTokenKp.publicKey;
       const admin = this.provider.publicKey;
 
-      const mushiTokenVault = getAssociatedTokenAddressSync(mushiTokenMint, this.vaultOwner, true, tokenProgram);
+      const mushiTokenVault = getAssociatedTokenAddressSync(mushiTokenMint, this.vaultOwner, true, mushiTokenProgram);
       const eclipseTokenVault = getAssociatedTokenAddressSync(eclipseTokenMint, this.vaultOwner, true, token2022Program);
-      const stakeTokenVault = getAssociatedTokenAddressSync(stakeTokenMint, this.vaultOwner, true, tokenProgram);
+      const stakeTokenVault = getAssociatedTokenAddressSync(stakeTokenMint, this.vaultOwner, true, mushiTokenProgram);
 
       const stakeTokenMetadataAccount = web3.PublicKey.findProgramAddressSync(
         [Buffer.from("metadata"), mplProgram.toBuffer(), stakeTokenMint.toBuffer()],
@@ -217,6 +238,7 @@ export class MushiStakeVaultProgramRpc {
 
       const ix = await this.program.methods
         .initialize({
+          poolId: this.poolId,
           stakeTokenName: stakeTokenName, 
           stakeTokenSymbol: stakeTokenSymbol,
           stakeTokenUri: stakeTokenUri,
@@ -230,10 +252,11 @@ export class MushiStakeVaultProgramRpc {
           mushiTokenVault: mushiTokenVault,
           eclipseTokenVault: eclipseTokenVault,
           stakeTokenVault: stakeTokenVault,
+          rewardTokenVault: this.rewardTokenVault,
           tokenVaultOwner: this.vaultOwner,
           stakeTokenMetadataAccount,
           mplProgram,
-          tokenProgram,
+          tokenProgram: mushiTokenProgram,
           token2022Program,
           associatedTokenProgram,
           systemProgram,
@@ -257,18 +280,34 @@ export class MushiStakeVaultProgramRpc {
     if (!mainStateInfo) throw "mainStateInfo not found";
 
     console.log("mainStateInfo", mainStateInfo);
+    const mushiTokenProgram = await this.getTokenProgram(mainStateInfo.mushiTokenMint);
 
-    const mushiTokenVault = getAssociatedTokenAddressSync(mainStateInfo.mushiTokenMint, this.vaultOwner, true, tokenProgram);
+    const mushiTokenVault = getAssociatedTokenAddressSync(mainStateInfo.mushiTokenMint, this.vaultOwner, true, mushiTokenProgram);
     const eclipseTokenVault = getAssociatedTokenAddressSync(mainStateInfo.eclipseTokenMint, this.vaultOwner, true, token2022Program);
     // const stakeTokenVault = getAssociatedTokenAddressSync(mainStateInfo.stakeTokenMint, this.vaultOwner, true, tokenProgram);
 
-    const userMushiTokenAta = getAssociatedTokenAddressSync(mainStateInfo.mushiTokenMint, user, true, tokenProgram);
+    const userMushiTokenAta = getAssociatedTokenAddressSync(mainStateInfo.mushiTokenMint, user, true, mushiTokenProgram);
     const userEclipseTokenAta = getAssociatedTokenAddressSync(mainStateInfo.eclipseTokenMint, user, true, token2022Program);
-    const userStakeTokenAta = getAssociatedTokenAddressSync(mainStateInfo.stakeTokenMint, user, true, tokenProgram);
+    const userStakeTokenAta = getAssociatedTokenAddressSync(mainStateInfo.stakeTokenMint, user, true, mushiTokenProgram);
+    const stakeEntry = web3.PublicKey.findProgramAddressSync(
+      [Seeds.stakeEntry, this.mainState.toBuffer(), user.toBuffer()],
+      this.programId
+    )[0];
+    // Only the mushi program can make this PDA sign, by CPI-ing into us.
+    const mushiProgramTokenVaultOwner = web3.PublicKey.findProgramAddressSync(
+      [Seeds.mushiProgramVault],
+      mainStateInfo.mushiProgram
+    )[0];
+    const unstakeRequest = web3.PublicKey.findProgramAddressSync(
+      [Seeds.unstakeRequest, this.mainState.toBuffer(), user.toBuffer()],
+      this.programId
+    )[0];
 
     return {
       user,
       mainState: this.mainState,
+      stakeEntry,
+      unstakeRequest,
       mushiTokenMint: mainStateInfo.mushiTokenMint,
       eclipseTokenMint: mainStateInfo.eclipseTokenMint,
       stakeTokenMint: mainStateInfo.stakeTokenMint,
@@ -278,29 +317,37 @@ export class MushiStakeVaultProgramRpc {
       userMushiTokenAta,
       userEclipseTokenAta,
       userStakeTokenAta,
+      rewardTokenVault: this.rewardTokenVault,
+      treasuryMushiTokenAta: null,
+      allowlistEntry: null,
       tokenVaultOwner: this.vaultOwner,
       associatedTokenProgram,
-      tokenProgram,
+      tokenProgram: mushiTokenProgram,
       token2022Program,
       systemProgram,
-      instructionSysvar: web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+      mushiProgramTokenVaultOwner,
     };
   }
 
   async stake(input: {
     amount: number;
+    // Defaults to the pool's initial 1:1 ratio.
+    eclipseAmount?: number;
   }): Promise<SendTxResult> {
     try {
-      const { amount } = input;
+      const { amount, eclipseAmount = amount } = input;
       const baseContext = await this.getBaseContext();
 
       const rawAmount = Math.trunc(amount * SOL_DECIMALS_HELPER)
+      const rawEclipseAmount = Math.trunc(eclipseAmount * TOKEN_DECIMALS_HELPER)
 
       const ix = await this.program.methods
         .stake({
-          amount: new BN(rawAmount),
+          mushiTokenAmount: new BN(rawAmount),
+          eclipseTokenAmount: new BN(rawEclipseAmount),
         }).accounts({
           ...baseContext,
+          eclipseTokenMushiProgramVault: null,
         }).instruction();
 
       const ixs = [ix];
@@ -315,9 +362,11 @@ export class MushiStakeVaultProgramRpc {
 
   async unstake(input: {
     amount: number;
+    minMushiAmount?: number;
+    minEclipseAmount?: number;
   }): Promise<SendTxResult> {
     try {
-      const { amount } = input;
+      const { amount, minMushiAmount = 0, minEclipseAmount = 0 } = input;
       const baseContext = await this.getBaseContext();
 
       const rawAmount = Math.trunc(amount * SOL_DECIMALS_HELPER)
@@ -325,6 +374,127 @@ export class MushiStakeVaultProgramRpc {
       const ix = await this.program.methods
         .unstake({
           amount: new BN(rawAmount),
+          minMushiTokenAmount: new BN(Math.trunc(minMushiAmount * SOL_DECIMALS_HELPER)),
+          minEclipseTokenAmount: new BN(Math.trunc(minEclipseAmount * TOKEN_DECIMALS_HELPER)),
+        }).accounts({
+          ...baseContext,
+          // `stake` always deposits Eclipse from the user's own account.
+          eclipseTokenSource: baseContext.userEclipseTokenAta,
+        }).instruction();
+
+      const ixs = [ix];
+      const txSignature = await this.sendTx(ixs);
+      if (!txSignature) throw "tx failed";
+      return { isPass: true, info: { txSignature } };
+    } catch (error) {
+      log({ error });
+      return { isPass: false, info: error };
+    }
+  }
+
+  async claimRewards(): Promise<SendTxResult> {
+    try {
+      const baseContext = await this.getBaseContext();
+
+      const ix = await this.program.methods
+        .claimRewards()
+        .accounts({
+          ...baseContext,
+        }).instruction();
+
+      const ixs = [ix];
+      const txSignature = await this.sendTx(ixs);
+      if (!txSignature) throw "tx failed";
+      return { isPass: true, info: { txSignature } };
+    } catch (error) {
+      log({ error });
+      return { isPass: false, info: error };
+    }
+  }
+
+  async fundRewards(input: {
+    amount: number;
+  }): Promise<SendTxResult> {
+    try {
+      const { amount } = input;
+      const admin = this.provider.publicKey;
+      const mainStateInfo = await this.getMainState();
+      if (!mainStateInfo) throw "mainStateInfo not found";
+
+      const rawAmount = Math.trunc(amount * TOKEN_DECIMALS_HELPER)
+      const mushiTokenProgram = await this.getTokenProgram(mainStateInfo.mushiTokenMint);
+      const adminMushiTokenAta = getAssociatedTokenAddressSync(mainStateInfo.mushiTokenMint, admin, true, mushiTokenProgram);
+
+      const ix = await this.program.methods
+        .fundRewards({
+          amount: new BN(rawAmount),
+        }).accounts({
+          admin,
+          mainState: this.mainState,
+          adminMushiTokenAta,
+          rewardTokenVault: this.rewardTokenVault,
+          mushiTokenMint: mainStateInfo.mushiTokenMint,
+          tokenVaultOwner: this.vaultOwner,
+          tokenProgram: mushiTokenProgram,
+        }).instruction();
+
+      const ixs = [ix];
+      const txSignature = await this.sendTx(ixs);
+      if (!txSignature) throw "tx failed";
+      return { isPass: true, info: { txSignature } };
+    } catch (error) {
+      log({ error });
+      return { isPass: false, info: error };
+    }
+  }
+
+  async setRewardRate(input: {
+    rewardRate: number;
+    startTime: number;
+    endTime: number;
+  }): Promise<SendTxResult> {
+    try {
+      const { rewardRate, startTime, endTime } = input;
+      const admin = this.provider.publicKey;
+
+      const ix = await this.program.methods
+        .setRewardRate({
+          rewardRate: new BN(Math.trunc(rewardRate * TOKEN_DECIMALS_HELPER)),
+          startTime: new BN(startTime),
+          endTime: new BN(endTime),
+        }).accounts({
+          admin,
+          mainState: this.mainState,
+          rewardTokenVault: this.rewardTokenVault,
+          systemProgram,
+        }).instruction();
+
+      const ixs = [ix];
+      const txSignature = await this.sendTx(ixs);
+      if (!txSignature) throw "tx failed";
+      return { isPass: true, info: { txSignature } };
+    } catch (error) {
+      log({ error });
+      return { isPass: false, info: error };
+    }
+  }
+
+  async requestUnstake(input: {
+    amount: number;
+    minMushiAmount?: number;
+    minEclipseAmount?: number;
+  }): Promise<SendTxResult> {
+    try {
+      const { amount, minMushiAmount = 0, minEclipseAmount = 0 } = input;
+      const baseContext = await this.getBaseContext();
+
+      const rawAmount = Math.trunc(amount * SOL_DECIMALS_HELPER)
+
+      const ix = await this.program.methods
+        .requestUnstake({
+          amount: new BN(rawAmount),
+          minMushiTokenAmount: new BN(Math.trunc(minMushiAmount * SOL_DECIMALS_HELPER)),
+          minEclipseTokenAmount: new BN(Math.trunc(minEclipseAmount * TOKEN_DECIMALS_HELPER)),
         }).accounts({
           ...baseContext,
         }).instruction();
@@ -338,4 +508,69 @@ export class MushiStakeVaultProgramRpc {
       return { isPass: false, info: error };
     }
   }
+
+  async withdrawUnstaked(): Promise<SendTxResult> {
+    try {
+      const baseContext = await this.getBaseContext();
+
+      const ix = await this.program.methods
+        .withdrawUnstaked()
+        .accounts({
+          ...baseContext,
+          eclipseTokenSource: baseContext.userEclipseTokenAta,
+        }).instruction();
+
+      const ixs = [ix];
+      const txSignature = await this.sendTx(ixs);
+      if (!txSignature) throw "tx failed";
+      return { isPass: true, info: { txSignature } };
+    } catch (error) {
+      log({ error });
+      return { isPass: false, info: error };
+    }
+  }
+
+  async auditPool(): Promise<SendTxResult> {
+    try {
+      const baseContext = await this.getBaseContext();
+
+      const ix = await this.program.methods
+        .auditPool()
+        .accounts({
+          ...baseContext,
+        }).instruction();
+
+      const ixs = [ix];
+      const txSignature = await this.sendTx(ixs);
+      if (!txSignature) throw "tx failed";
+      return { isPass: true, info: { txSignature } };
+    } catch (error) {
+      log({ error });
+      return { isPass: false, info: error };
+    }
+  }
+
+  async reconcilePool(input: {
+    donateSurplusToRewards: boolean;
+  }): Promise<SendTxResult> {
+    try {
+      const baseContext = await this.getBaseContext();
+      const mode = input.donateSurplusToRewards ? { donateSurplusToRewards: {} } : { correctCounters: {} };
+
+      const ix = await this.program.methods
+        .reconcilePool({ mode })
+        .accounts({
+          ...baseContext,
+          admin: this.provider.publicKey,
+        }).instruction();
+
+      const ixs = [ix];
+      const txSignature = await this.sendTx(ixs);
+      if (!txSignature) throw "tx failed";
+      return { isPass: true, info: { txSignature } };
+    } catch (error) {
+      log({ error });
+      return { isPass: false, info: error };
+    }
+  }
 }